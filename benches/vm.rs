@@ -0,0 +1,68 @@
+// Compares the tree-walking interpreter against compiled bytecode when
+// evaluating one formula many times with different variable bindings.
+//
+//   cargo bench --bench vm
+
+extern crate calculator;
+
+use std::time::{Duration, Instant};
+
+use calculator::interpreter::{Bindings, Interpreter};
+use calculator::lexer::Lexer;
+use calculator::parser::Parser;
+use calculator::value::Value;
+use calculator::vm::{Machine, Program};
+
+const FORMULA: &str = "(x * 3 + y) / (z - 7) - x * (y + 2) + -(z / 3) * 4";
+const ITERATIONS: i32 = 1_000_000;
+
+fn report(name: &str, elapsed: Duration) {
+    println!("{:>14}: {:>8.1} ms ({:.1} ns/eval)",
+             name,
+             elapsed.as_secs_f64() * 1000.0,
+             elapsed.as_secs_f64() * 1e9 / f64::from(ITERATIONS));
+}
+
+fn main() {
+    let tokens = Lexer::tokenize(&mut FORMULA.chars()).unwrap();
+    let expression = Parser::parse(&tokens).unwrap();
+    let program = Program::compile(&expression);
+    println!("{} ({} instructions)", expression, program.code().len());
+
+    let mut bindings = Bindings::new();
+    for name in program.variables() {
+        bindings.insert(name.clone(), Value::Integer(0));
+    }
+    let start = Instant::now();
+    let mut tree_total = 0.0;
+    for i in 0..ITERATIONS {
+        *bindings.get_mut("x").unwrap() = Value::Integer(i % 1000);
+        *bindings.get_mut("y").unwrap() = Value::Integer(i % 37);
+        *bindings.get_mut("z").unwrap() = Value::Integer(i % 11);
+        if let Ok(v) = expression.evaluate_in(&bindings) {
+            tree_total += v.to_f64();
+        }
+    }
+    let tree_time = start.elapsed();
+
+    // slots are in first-occurrence order: x, y, z
+    let mut machine = Machine::new();
+    let mut values = [None, None, None];
+    let start = Instant::now();
+    let mut vm_total = 0.0;
+    for i in 0..ITERATIONS {
+        values[0] = Some(Value::Integer(i % 1000));
+        values[1] = Some(Value::Integer(i % 37));
+        values[2] = Some(Value::Integer(i % 11));
+        if let Ok(v) = machine.run(&program, &values) {
+            vm_total += v.to_f64();
+        }
+    }
+    let vm_time = start.elapsed();
+
+    assert_eq!(tree_total, vm_total);
+    report("tree-walking", tree_time);
+    report("bytecode", vm_time);
+    println!("{:>14}: {:.1}x", "speedup",
+             tree_time.as_secs_f64() / vm_time.as_secs_f64());
+}
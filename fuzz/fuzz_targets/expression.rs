@@ -0,0 +1,25 @@
+#![no_main]
+
+//! Structured expression trees with bindings, checked across every
+//! backend and the printer.
+
+use calculator::interpreter::Interpreter;
+use calculator::lexer::Lexer;
+use calculator::optimizer::optimize;
+use calculator::parser::Parser;
+use calculator::vm::Program;
+use calculator_fuzz::Case;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|case: Case| {
+    let expression = case.expression.to_expression();
+    let bindings = case.bindings();
+
+    let printed = expression.to_string();
+    let tokens = Lexer::tokenize(&mut printed.chars()).unwrap();
+    assert_eq!(Ok(expression.clone()), Parser::parse(&tokens), "printed as {}", printed);
+
+    let expected = expression.evaluate_in(&bindings);
+    assert_eq!(expected, Program::compile(&expression).evaluate_in(&bindings));
+    assert_eq!(expected, optimize(expression).evaluate_in(&bindings));
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use calculator::lexer::Token;
+use calculator::parser::Parser;
+use calculator_fuzz::ArbitraryToken;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|tokens: Vec<ArbitraryToken>| {
+    let tokens: Vec<Token> = tokens.iter().map(ArbitraryToken::to_token).collect();
+    let _ = Parser::parse(&tokens);
+});
@@ -0,0 +1,53 @@
+#![no_main]
+
+//! Source text all the way to a result: lex, parse, evaluate, and check
+//! that the printer, optimizer and bytecode backend agree with the
+//! interpreter on anything that parses.  Anything reading the clock is
+//! left out, as each backend would read it at a different moment.
+
+use calculator::interpreter::{Bindings, Interpreter};
+use calculator::lexer::Lexer;
+use calculator::optimizer::optimize;
+use calculator::parser::{Expression, Parser};
+use calculator::visit::Visitor;
+use calculator::vm::Program;
+use libfuzzer_sys::fuzz_target;
+
+struct ClockFinder {
+    found: bool,
+}
+
+impl Visitor for ClockFinder {
+    fn visit_variable(&mut self, name: &str) {
+        self.found |= name == "today" || name == "now";
+    }
+}
+
+fn reads_clock(e: &Expression) -> bool {
+    let mut finder = ClockFinder { found: false };
+    finder.visit_expression(e);
+    finder.found
+}
+
+fuzz_target!(|source: &str| {
+    let tokens = match Lexer::tokenize(&mut source.chars()) {
+        Ok(tokens) => tokens,
+        Err(_) => return,
+    };
+    let expression = match Parser::parse(&tokens) {
+        Ok(expression) => expression,
+        Err(_) => return,
+    };
+
+    let printed = expression.to_string();
+    let reparsed = Parser::parse(&Lexer::tokenize(&mut printed.chars()).unwrap()).unwrap();
+    assert_eq!(expression, reparsed, "printed as {}", printed);
+
+    if reads_clock(&expression) {
+        return;
+    }
+    let bindings = Bindings::new();
+    let expected = expression.evaluate_in(&bindings);
+    assert_eq!(expected, Program::compile(&expression).evaluate_in(&bindings));
+    assert_eq!(expected, optimize(expression).evaluate_in(&bindings));
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use calculator::lexer::Lexer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    let _ = Lexer::tokenize(&mut source.chars());
+    if let Ok(tokens) = Lexer::tokenize_lossless(&mut source.chars()) {
+        let text: String = tokens.iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(source, text, "lossless lexing lost text");
+    }
+});
@@ -0,0 +1,222 @@
+//! Structured inputs shared by the fuzz targets.  These mirror the
+//! calculator's own types so that libFuzzer can mutate them directly,
+//! without the calculator crate depending on `arbitrary`.
+
+use arbitrary::Arbitrary;
+use calculator::interpreter::Bindings;
+use calculator::lexer::Token;
+use calculator::parser::{Expression, Operation, FUNCTIONS};
+use calculator::value::{Complex, Value};
+
+const VARIABLE_NAMES: [&str; 4] = ["x", "y", "z", "π"];
+
+#[derive(Debug, Arbitrary)]
+pub enum ArbitraryToken {
+    Integer(u32),
+    // the digits before and after the point
+    Decimal(u32, u32),
+    Imaginary(u32),
+    Date(u16, u8, u8),
+    Time(u8, u8),
+    Identifier(u8),
+    Plus,
+    Minus,
+    Times,
+    Div,
+    MatrixTimes,
+    LeftParen,
+    RightParen,
+    Power,
+    Superscript(u32),
+    SquareRoot,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    Factorial,
+    Percent,
+    Comma,
+    To,
+    LeftBracket,
+    RightBracket,
+    Range,
+    Step,
+}
+
+impl ArbitraryToken {
+    pub fn to_token(&self) -> Token {
+        match self {
+            ArbitraryToken::Integer(i) => Token::Integer(*i),
+            ArbitraryToken::Decimal(a, b) => Token::Decimal(format!("{}.{}", a, b)),
+            ArbitraryToken::Imaginary(i) => Token::Imaginary(*i),
+            ArbitraryToken::Date(y, m, d) => Token::Date(format!("{:04}-{:02}-{:02}", y, m, d)),
+            ArbitraryToken::Time(h, m) => Token::Time(format!("{:02}:{:02}", h, m)),
+            ArbitraryToken::Identifier(n) => Token::Identifier(variable_name(*n)),
+            ArbitraryToken::Plus => Token::Plus,
+            ArbitraryToken::Minus => Token::Minus,
+            ArbitraryToken::Times => Token::Times,
+            ArbitraryToken::Div => Token::Div,
+            ArbitraryToken::MatrixTimes => Token::MatrixTimes,
+            ArbitraryToken::LeftParen => Token::LeftParen,
+            ArbitraryToken::RightParen => Token::RightParen,
+            ArbitraryToken::Power => Token::Power,
+            ArbitraryToken::Superscript(i) => Token::Superscript(*i),
+            ArbitraryToken::SquareRoot => Token::SquareRoot,
+            ArbitraryToken::Less => Token::Less,
+            ArbitraryToken::LessEqual => Token::LessEqual,
+            ArbitraryToken::Greater => Token::Greater,
+            ArbitraryToken::GreaterEqual => Token::GreaterEqual,
+            ArbitraryToken::Equal => Token::Equal,
+            ArbitraryToken::NotEqual => Token::NotEqual,
+            ArbitraryToken::Factorial => Token::Factorial,
+            ArbitraryToken::Percent => Token::Percent,
+            ArbitraryToken::Comma => Token::Comma,
+            ArbitraryToken::To => Token::To,
+            ArbitraryToken::LeftBracket => Token::LeftBracket,
+            ArbitraryToken::RightBracket => Token::RightBracket,
+            ArbitraryToken::Range => Token::Range,
+            ArbitraryToken::Step => Token::Step,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Arbitrary)]
+pub enum ArbitraryOperation {
+    Plus,
+    Minus,
+    Times,
+    Div,
+    MatrixTimes,
+    Power,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+impl ArbitraryOperation {
+    pub fn to_operation(self) -> Operation {
+        match self {
+            ArbitraryOperation::Plus => Operation::Plus,
+            ArbitraryOperation::Minus => Operation::Minus,
+            ArbitraryOperation::Times => Operation::Times,
+            ArbitraryOperation::Div => Operation::Div,
+            ArbitraryOperation::MatrixTimes => Operation::MatrixTimes,
+            ArbitraryOperation::Power => Operation::Power,
+            ArbitraryOperation::Less => Operation::Less,
+            ArbitraryOperation::LessEqual => Operation::LessEqual,
+            ArbitraryOperation::Greater => Operation::Greater,
+            ArbitraryOperation::GreaterEqual => Operation::GreaterEqual,
+            ArbitraryOperation::Equal => Operation::Equal,
+            ArbitraryOperation::NotEqual => Operation::NotEqual,
+        }
+    }
+}
+
+#[derive(Debug, Arbitrary)]
+pub enum ArbitraryExpression {
+    Integer(u32),
+    Decimal(u32, u32),
+    Imaginary(u32),
+    Date(u16, u8, u8),
+    Time(u8, u8),
+    Variable(u8),
+    UnaryMinus(Box<ArbitraryExpression>),
+    SquareRoot(Box<ArbitraryExpression>),
+    Factorial(Box<ArbitraryExpression>),
+    Percent(Box<ArbitraryExpression>),
+    BinaryOperation(Box<ArbitraryExpression>, ArbitraryOperation, Box<ArbitraryExpression>),
+    // a decrease when true, otherwise an increase
+    PercentChange(Box<ArbitraryExpression>, bool, Box<ArbitraryExpression>),
+    // a built-in function, by index; the arguments may be the wrong number
+    Call(u8, Vec<ArbitraryExpression>),
+    Convert(Box<ArbitraryExpression>, Box<ArbitraryExpression>),
+    List(Vec<ArbitraryExpression>),
+    Index(Box<ArbitraryExpression>, Box<ArbitraryExpression>),
+    // between small integers, so building one stays quick
+    Range(u8, u8, Option<Box<ArbitraryExpression>>),
+}
+
+fn variable_name(n: u8) -> String {
+    VARIABLE_NAMES[n as usize % VARIABLE_NAMES.len()].to_string()
+}
+
+impl ArbitraryExpression {
+    pub fn to_expression(&self) -> Expression {
+        match self {
+            ArbitraryExpression::Integer(i) => Expression::Integer(*i),
+            ArbitraryExpression::Decimal(a, b) => Expression::Decimal(format!("{}.{}", a, b)),
+            ArbitraryExpression::Imaginary(i) => Expression::Imaginary(*i),
+            ArbitraryExpression::Date(y, m, d) => Expression::Date(format!("{:04}-{:02}-{:02}", y, m, d)),
+            ArbitraryExpression::Time(h, m) => Expression::Time(format!("{:02}:{:02}", h, m)),
+            ArbitraryExpression::Variable(n) => Expression::Variable(variable_name(*n)),
+            ArbitraryExpression::UnaryMinus(e) => Expression::UnaryMinus(Box::new(e.to_expression())),
+            ArbitraryExpression::SquareRoot(e) => Expression::SquareRoot(Box::new(e.to_expression())),
+            ArbitraryExpression::Factorial(e) => Expression::Factorial(Box::new(e.to_expression())),
+            ArbitraryExpression::Percent(e) => Expression::Percent(Box::new(e.to_expression())),
+            ArbitraryExpression::BinaryOperation(e1, op, e2) =>
+                Expression::BinaryOperation(Box::new(e1.to_expression()), op.to_operation(), Box::new(e2.to_expression())),
+            ArbitraryExpression::PercentChange(e1, decrease, e2) => {
+                let op = if *decrease { Operation::Minus } else { Operation::Plus };
+                Expression::PercentChange(Box::new(e1.to_expression()), op, Box::new(e2.to_expression()))
+            },
+            ArbitraryExpression::Call(n, arguments) => {
+                let (name, _, _) = FUNCTIONS[*n as usize % FUNCTIONS.len()];
+                Expression::Call(name.to_string(), arguments.iter().map(|e| e.to_expression()).collect())
+            },
+            ArbitraryExpression::Convert(e, unit) =>
+                Expression::Convert(Box::new(e.to_expression()), Box::new(unit.to_expression())),
+            ArbitraryExpression::List(elements) =>
+                Expression::List(elements.iter().map(|e| e.to_expression()).collect()),
+            ArbitraryExpression::Index(e, position) =>
+                Expression::Index(Box::new(e.to_expression()), Box::new(position.to_expression())),
+            ArbitraryExpression::Range(start, end, step) =>
+                Expression::Range(Box::new(Expression::Integer(u32::from(*start))),
+                                  Box::new(Expression::Integer(u32::from(*end))),
+                                  step.as_ref().map(|step| Box::new(step.to_expression())))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Arbitrary)]
+pub enum ArbitraryValue {
+    Integer(i32),
+    Real(f64),
+    Complex(f64, f64),
+}
+
+impl ArbitraryValue {
+    // infinities and NaN are never values
+    pub fn to_value(self) -> Value {
+        match self {
+            ArbitraryValue::Integer(i) => Value::Integer(i),
+            ArbitraryValue::Real(r) if r.is_finite() => Value::Real(r),
+            ArbitraryValue::Real(_) => Value::Real(0.0),
+            ArbitraryValue::Complex(re, im) if re.is_finite() && im.is_finite() =>
+                Value::from(Complex::new(re, im)),
+            ArbitraryValue::Complex(_, _) => Value::Real(0.0),
+        }
+    }
+}
+
+/// An expression together with values for (some of) its variables, one
+/// slot per name in `VARIABLE_NAMES`, so each input binds each name at
+/// most once.
+#[derive(Debug, Arbitrary)]
+pub struct Case {
+    pub expression: ArbitraryExpression,
+    pub bindings: [Option<ArbitraryValue>; VARIABLE_NAMES.len()],
+}
+
+impl Case {
+    pub fn bindings(&self) -> Bindings {
+        VARIABLE_NAMES.iter()
+            .zip(self.bindings.iter())
+            .filter_map(|(name, v)| v.map(|v| (name.to_string(), v.to_value())))
+            .collect()
+    }
+}
@@ -0,0 +1,333 @@
+use super::interpreter::Interpreter;
+use super::parser::{check_call, Expression, Operation};
+use super::value::Value;
+use super::visit::Visitor;
+
+mod solve;
+
+pub use self::solve::{solve, solves_equation};
+
+// Symbolic differentiation.  An expression is differentiated with respect
+// to one of its variables, every other variable being a constant, and
+// the derivative is an expression too, so it prints as source and
+// evaluates wherever the variable is bound.  It is simplified as it is
+// built: numbers are folded where that is exact, and 0 + e, 1 * e, e ^ 1
+// and the like are reduced.  Unlike the optimizer, this drops whatever
+// is multiplied by zero, as the derivative of anything not mentioning
+// the variable is 0, even where it would fail to evaluate.
+
+struct Mentions<'a> {
+    name: &'a str,
+    found: bool
+}
+
+impl Visitor for Mentions<'_> {
+    fn visit_variable(&mut self, name: &str) {
+        self.found |= name == self.name;
+    }
+}
+
+fn mentions(e: &Expression, x: &str) -> bool {
+    let mut finder = Mentions { name: x, found: false };
+    finder.visit_expression(e);
+    finder.found
+}
+
+fn integer(i: i32) -> Expression {
+    if i < 0 {
+        Expression::UnaryMinus(Box::new(Expression::Integer(i.unsigned_abs())))
+    } else {
+        Expression::Integer(i as u32)
+    }
+}
+
+// the value of a number written out, possibly negated
+fn number(e: &Expression) -> Option<Value> {
+    match e {
+        Expression::Integer(_) | Expression::Decimal(_) => e.evaluate().ok(),
+        Expression::UnaryMinus(inner) => match **inner {
+            Expression::Integer(_) | Expression::Decimal(_) => e.evaluate().ok(),
+            _ => Option::None
+        },
+        _ => Option::None
+    }
+}
+
+fn is_integer(e: &Expression, i: i32) -> bool {
+    number(e) == Option::Some(Value::Integer(i))
+}
+
+// a number written out as a literal, if it can be.  A real is written
+// with a decimal point so it reads back as a real.
+fn literal(value: Value) -> Option<Expression> {
+    let (negative, magnitude) = match value {
+        Value::Integer(i32::MIN) => return Option::None,
+        Value::Integer(i) => return Option::Some(integer(i)),
+        Value::Real(r) => (r < 0.0, r.abs()),
+        _ => return Option::None
+    };
+    let mut digits = magnitude.to_string();
+    if !digits.contains('.') {
+        digits.push_str(".0");
+    }
+    let decimal = Expression::Decimal(digits);
+    Option::Some(if negative { Expression::UnaryMinus(Box::new(decimal)) } else { decimal })
+}
+
+// the value of two numbers combined, unless it would be inexact, as a
+// quotient of integers that truncates is
+fn fold(e1: &Expression, op: Operation, e2: &Expression) -> Option<Expression> {
+    match (number(e1)?, op, number(e2)?) {
+        (Value::Integer(i1), Operation::Div, Value::Integer(i2)) if i2 == 0 || i1 % i2 != 0 =>
+            return Option::None,
+        _ => {}
+    }
+    let e = Expression::BinaryOperation(Box::new(e1.clone()), op, Box::new(e2.clone()));
+    e.evaluate().ok().and_then(literal)
+}
+
+fn binary(e1: Expression, op: Operation, e2: Expression) -> Expression {
+    Expression::BinaryOperation(Box::new(e1), op, Box::new(e2))
+}
+
+fn negative(e: Expression) -> Expression {
+    if number(&e).is_some() {
+        let negated = Expression::UnaryMinus(Box::new(e.clone()));
+        if let Some(folded) = negated.evaluate().ok().and_then(literal) {
+            return folded;
+        }
+    }
+    match e {
+        Expression::UnaryMinus(inner) => *inner,
+        // -(2 * x) is -2 * x
+        Expression::BinaryOperation(e1, Operation::Times, e2) if number(&e1).is_some() =>
+            times(negative(*e1), *e2),
+        e => Expression::UnaryMinus(Box::new(e))
+    }
+}
+
+fn plus(e1: Expression, e2: Expression) -> Expression {
+    if is_integer(&e1, 0) {
+        return e2;
+    }
+    if is_integer(&e2, 0) {
+        return e1;
+    }
+    if let Some(folded) = fold(&e1, Operation::Plus, &e2) {
+        return folded;
+    }
+    match e2 {
+        Expression::UnaryMinus(e2) => minus(e1, *e2),
+        e2 => binary(e1, Operation::Plus, e2)
+    }
+}
+
+fn minus(e1: Expression, e2: Expression) -> Expression {
+    if is_integer(&e2, 0) {
+        return e1;
+    }
+    if is_integer(&e1, 0) {
+        return negative(e2);
+    }
+    if e1 == e2 {
+        return integer(0);
+    }
+    if let Some(folded) = fold(&e1, Operation::Minus, &e2) {
+        return folded;
+    }
+    match e2 {
+        Expression::UnaryMinus(e2) => plus(e1, *e2),
+        e2 => binary(e1, Operation::Minus, e2)
+    }
+}
+
+// a constant factor goes first, merged with any other
+fn times(e1: Expression, e2: Expression) -> Expression {
+    if is_integer(&e1, 0) || is_integer(&e2, 0) {
+        return integer(0);
+    }
+    if is_integer(&e1, 1) {
+        return e2;
+    }
+    if is_integer(&e2, 1) {
+        return e1;
+    }
+    if let Some(folded) = fold(&e1, Operation::Times, &e2) {
+        return folded;
+    }
+    if is_integer(&e1, -1) {
+        return negative(e2);
+    }
+    if number(&e2).is_some() && number(&e1).is_none() {
+        return times(e2, e1);
+    }
+    match (e1, e2) {
+        (Expression::UnaryMinus(e1), e2) if number(&e1).is_none() => negative(times(*e1, e2)),
+        (e1, Expression::UnaryMinus(e2)) => negative(times(e1, *e2)),
+        (e1, Expression::BinaryOperation(e2, Operation::Times, e3)) if number(&e1).is_some() => {
+            match fold(&e1, Operation::Times, &e2) {
+                Some(factor) => times(factor, *e3),
+                None => binary(e1, Operation::Times, binary(*e2, Operation::Times, *e3))
+            }
+        },
+        (e1, e2) => binary(e1, Operation::Times, e2)
+    }
+}
+
+// e1 * e2 ^ -1 rather than e1 / e2, which truncates where both are
+// integers, as they may be wherever the derivative is evaluated
+fn divide(e1: Expression, e2: Expression) -> Expression {
+    if is_integer(&e1, 0) {
+        return integer(0);
+    }
+    if is_integer(&e2, 1) {
+        return e1;
+    }
+    match fold(&e1, Operation::Div, &e2) {
+        Some(folded) => folded,
+        None => times(e1, power(e2, integer(-1)))
+    }
+}
+
+fn power(e1: Expression, e2: Expression) -> Expression {
+    if is_integer(&e2, 0) || is_integer(&e1, 1) {
+        return integer(1);
+    }
+    if is_integer(&e2, 1) {
+        return e1;
+    }
+    if let Some(folded) = fold(&e1, Operation::Power, &e2) {
+        return folded;
+    }
+    match (e1, e2) {
+        // (u ^ n) ^ -1 is u ^ -n
+        (Expression::BinaryOperation(u, Operation::Power, n), e2) if is_integer(&e2, -1) && number(&n).is_some() =>
+            power(*u, negative(*n)),
+        (e1, e2) => binary(e1, Operation::Power, e2)
+    }
+}
+
+fn matrix_times(e1: Expression, e2: Expression) -> Expression {
+    if is_integer(&e1, 0) || is_integer(&e2, 0) {
+        integer(0)
+    } else {
+        binary(e1, Operation::MatrixTimes, e2)
+    }
+}
+
+fn percent(e: Expression) -> Expression {
+    if is_integer(&e, 0) {
+        e
+    } else {
+        Expression::Percent(Box::new(e))
+    }
+}
+
+fn call(name: &str, argument: Expression) -> Expression {
+    Expression::Call(name.to_string(), vec![argument])
+}
+
+// the derivative of a built-in function of u, given u's derivative du
+fn call_derivative(name: &str, u: &Expression, du: Expression) -> Result<Expression, String> {
+    Result::Ok(match name {
+        "sqrt" => divide(du, times(integer(2), call(name, u.clone()))),
+        "exp" => times(du, call(name, u.clone())),
+        "ln" => divide(du, u.clone()),
+        "abs" => times(du, divide(u.clone(), call(name, u.clone()))),
+        "re" | "conj" if number(&du).is_some() => du,
+        "im" if number(&du).is_some() => integer(0),
+        "re" | "im" | "conj" => call(name, du),
+        _ => return Result::Err(format!("Cannot differentiate {}", name))
+    })
+}
+
+// The derivative of e with respect to the variable x.  Comparisons,
+// factorials, ranges and the functions of lists, such as median, have
+// none.
+pub fn derivative(e: &Expression, x: &str) -> Result<Expression, String> {
+    if !mentions(e, x) {
+        return Result::Ok(integer(0));
+    }
+    let d = |e: &Expression| derivative(e, x);
+    Result::Ok(match e {
+        Expression::Integer(_) | Expression::Decimal(_) | Expression::Imaginary(_) |
+        Expression::Date(_) | Expression::Time(_) => integer(0),
+        Expression::Variable(_) => integer(1),
+        Expression::UnaryMinus(u) => negative(d(u)?),
+        Expression::SquareRoot(u) => divide(d(u)?, times(integer(2), Expression::SquareRoot(u.clone()))),
+        Expression::Factorial(_) => return Result::Err("Cannot differentiate a factorial".to_string()),
+        Expression::Percent(u) => percent(d(u)?),
+        // u ± u * v%
+        Expression::PercentChange(u, op, v) => {
+            let du = d(u)?;
+            let change = percent(plus(times(du.clone(), (**v).clone()), times((**u).clone(), d(v)?)));
+            match op {
+                Operation::Plus => plus(du, change),
+                _ => minus(du, change)
+            }
+        },
+        Expression::BinaryOperation(u, op, v) => {
+            let (u, v) = (&**u, &**v);
+            match op {
+                Operation::Plus => plus(d(u)?, d(v)?),
+                Operation::Minus => minus(d(u)?, d(v)?),
+                Operation::Times => plus(times(d(u)?, v.clone()), times(u.clone(), d(v)?)),
+                Operation::MatrixTimes =>
+                    plus(matrix_times(d(u)?, v.clone()), matrix_times(u.clone(), d(v)?)),
+                Operation::Div if !mentions(v, x) => divide(d(u)?, v.clone()),
+                Operation::Div => divide(minus(times(d(u)?, v.clone()), times(u.clone(), d(v)?)),
+                                         power(v.clone(), integer(2))),
+                // v * u ^ (v - 1) * u' + u ^ v * ln(u) * v', leaving out
+                // the logarithm unless v mentions x
+                Operation::Power => {
+                    let exponent = minus(v.clone(), integer(1));
+                    let by_base = times(times(v.clone(), power(u.clone(), exponent)), d(u)?);
+                    if mentions(v, x) {
+                        plus(by_base, times(times(e.clone(), call("ln", u.clone())), d(v)?))
+                    } else {
+                        by_base
+                    }
+                },
+                _ => return Result::Err("Cannot differentiate a comparison".to_string())
+            }
+        },
+        // a derivative in x at a point is a constant, unless the point
+        // moves with x
+        Expression::Call(name, arguments) if name == "diff" && arguments.len() == 3 => {
+            derivative_call(arguments)?;
+            if arguments[1] != Expression::Variable(x.to_string()) || mentions(&arguments[2], x) {
+                return Result::Err("Cannot differentiate a derivative at a point".to_string());
+            }
+            integer(0)
+        },
+        Expression::Call(name, arguments) if name == "diff" => derivative(&derivative_call(arguments)?, x)?,
+        Expression::Call(name, arguments) if arguments.len() == 1 =>
+            call_derivative(name, &arguments[0], d(&arguments[0])?)?,
+        Expression::Call(name, _) => return Result::Err(format!("Cannot differentiate {}", name)),
+        Expression::Convert(u, unit) if !mentions(unit, x) =>
+            Expression::Convert(Box::new(d(u)?), unit.clone()),
+        Expression::Convert(_, _) =>
+            return Result::Err(format!("Cannot differentiate a unit that depends on {}", x)),
+        Expression::List(elements) =>
+            Expression::List(elements.iter().map(d).collect::<Result<Vec<Expression>, String>>()?),
+        Expression::Index(u, position) if !mentions(position, x) =>
+            Expression::Index(Box::new(d(u)?), position.clone()),
+        Expression::Index(_, _) =>
+            return Result::Err(format!("Cannot differentiate an index that depends on {}", x)),
+        Expression::Range(_, _, _) => return Result::Err("Cannot differentiate a range".to_string())
+    })
+}
+
+// diff(e, x) or diff(e, x, a), whose second argument must name the
+// variable.  The point a is left for the caller to evaluate the
+// derivative at.
+pub fn derivative_call(arguments: &[Expression]) -> Result<Expression, String> {
+    check_call("diff", arguments.len())?;
+    match &arguments[1] {
+        Expression::Variable(x) => derivative(&arguments[0], x),
+        _ => Result::Err("Cannot differentiate with respect to anything but a variable".to_string())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,301 @@
+use interpreter::{Bindings, Error, Interpreter};
+use parser::{check_call, Expression, Operation};
+use value::{Complex, Value};
+use super::{derivative, mentions};
+
+// Equations in one variable, every other variable being a constant.  An
+// equation whose sides differ by at most a quadratic in the variable, as
+// their derivatives show, is solved in closed form, with complex roots
+// where there are no real ones.  Any other is sampled across an interval
+// and each root found where the sign changes is refined by Newton's
+// method, falling back to bisection whenever a step would leave the
+// bracket or there is no derivative to step by.  Where the sides touch
+// without crossing, Newton's method alone is tried.  The variable is
+// always a real, so that x/2 does not truncate.
+
+// where roots are looked for when no interval is given
+const INTERVAL: (f64, f64) = (-100.0, 100.0);
+
+// how many steps the interval is sampled in
+const STEPS: usize = 2000;
+
+const ITERATIONS: usize = 100;
+
+// steps shorter than this, relative to the root, have converged
+const TOLERANCE: f64 = 1e-12;
+
+// a converged root is a root rather than a pole if the sides differ there
+// by at most this much, relative to where the search started
+const RESIDUAL: f64 = 1e-6;
+
+// the difference of an equation's sides, and its derivative where there
+// is one, as functions of the variable
+struct Function<'a> {
+    residual: Expression,
+    slope: Option<Expression>,
+    x: &'a str,
+    bindings: Bindings
+}
+
+fn value_at(e: &Expression, bindings: &mut Bindings, x: &str, at: f64) -> Result<f64, Error> {
+    bindings.insert(x.to_string(), Value::Real(at));
+    match e.evaluate_in(bindings)? {
+        Value::Complex(_) => Result::Err(Error::units("Cannot solve an equation with complex sides")),
+        Value::DateTime(_) => Result::Err(Error::units("Cannot solve an equation between dates")),
+        Value::List(_) => Result::Err(Error::units("Cannot solve an equation between lists")),
+        // a quantity is zero in any unit
+        v => Result::Ok(v.to_f64())
+    }
+}
+
+impl Function<'_> {
+    fn at(&mut self, at: f64) -> Result<f64, Error> {
+        value_at(&self.residual, &mut self.bindings, self.x, at)
+    }
+
+    fn slope_at(&mut self, at: f64) -> Option<f64> {
+        match self.slope {
+            Some(ref slope) => value_at(slope, &mut self.bindings, self.x, at).ok(),
+            None => Option::None
+        }
+    }
+
+    fn accept(&mut self, root: f64, scale: f64) -> Option<f64> {
+        match self.at(root) {
+            Ok(y) if y.abs() <= RESIDUAL * scale => Option::Some(root),
+            _ => Option::None
+        }
+    }
+
+    // the root between lo and hi, where the function has opposite signs
+    fn bracketed(&mut self, mut lo: f64, mut y_lo: f64, mut hi: f64, y_hi: f64) -> Option<f64> {
+        let scale = y_lo.abs().max(y_hi.abs()).max(1.0);
+        let mut x = (lo + hi) / 2.0;
+        for _ in 0..ITERATIONS {
+            let y = self.at(x).ok()?;
+            if y == 0.0 {
+                return Option::Some(x);
+            }
+            if (y < 0.0) == (y_lo < 0.0) {
+                lo = x;
+                y_lo = y;
+            } else {
+                hi = x;
+            }
+            let step = match self.slope_at(x) {
+                Some(slope) => x - y / slope,
+                None => f64::NAN
+            };
+            let next = if lo < step && step < hi { step } else { (lo + hi) / 2.0 };
+            if (next - x).abs() <= TOLERANCE * x.abs().max(1.0) {
+                return self.accept(next, scale);
+            }
+            x = next;
+        }
+        Option::None
+    }
+
+    // the root Newton's method reaches from x, if it stays between a and b
+    fn newton(&mut self, mut x: f64, (a, b): (f64, f64)) -> Option<f64> {
+        let scale = self.at(x).ok()?.abs().max(1.0);
+        for _ in 0..ITERATIONS {
+            let y = self.at(x).ok()?;
+            if y == 0.0 {
+                return Option::Some(x);
+            }
+            let next = x - y / self.slope_at(x)?;
+            if !(a <= next && next <= b) {
+                return Option::None;
+            }
+            if (next - x).abs() <= TOLERANCE * x.abs().max(1.0) {
+                return self.accept(next, scale);
+            }
+            x = next;
+        }
+        Option::None
+    }
+}
+
+// a root as a value, with no negative zero
+fn root(r: f64) -> Result<Value, Error> {
+    if r.is_finite() {
+        Result::Ok(Value::Real(r + 0.0))
+    } else {
+        Result::Err(Error::overflow("real overflow"))
+    }
+}
+
+fn not_found(equation: &Expression, (a, b): (f64, f64), diverged: bool) -> Error {
+    Error::undefined(format!("Cannot find a root of {} between {} and {}{}",
+                             equation,
+                             a,
+                             b,
+                             if diverged { ": where it changes sign, nothing converges" } else { "" }))
+}
+
+// the roots of a polynomial of at most this degree, from its values at
+// -1, 0 and 1
+fn closed_form(f: &mut Function, degree: u32, equation: &Expression) -> Result<Vec<Value>, Error> {
+    let c0 = f.at(0.0)?;
+    let (c1, c2) = match degree {
+        0 => (0.0, 0.0),
+        1 => (f.at(1.0)? - c0, 0.0),
+        _ => {
+            let (up, down) = (f.at(1.0)?, f.at(-1.0)?);
+            ((up - down) / 2.0, (up + down) / 2.0 - c0)
+        }
+    };
+    if c2 == 0.0 && c1 == 0.0 {
+        return Result::Err(Error::undefined(format!("Cannot solve {} for {}: it holds for {} value",
+                                                    equation,
+                                                    f.x,
+                                                    if c0 == 0.0 { "every" } else { "no" })));
+    }
+    if c2 == 0.0 {
+        return Result::Ok(vec![root(-c0 / c1)?]);
+    }
+    let discriminant = c1 * c1 - 4.0 * c2 * c0;
+    if discriminant < 0.0 {
+        let re = -c1 / (2.0 * c2) + 0.0;
+        let im = (-discriminant).sqrt() / (2.0 * c2).abs();
+        if !re.is_finite() || !im.is_finite() {
+            return Result::Err(Error::overflow("real overflow"));
+        }
+        return Result::Ok(vec![Value::Complex(Complex::new(re, -im)), Value::Complex(Complex::new(re, im))]);
+    }
+    if discriminant == 0.0 {
+        return Result::Ok(vec![root(-c1 / (2.0 * c2))?]);
+    }
+    // the larger root in magnitude first, then the other from their
+    // product, to avoid cancellation
+    let q = -(c1 + c1.signum() * discriminant.sqrt()) / 2.0;
+    let (r1, r2) = (q / c2, c0 / q);
+    Result::Ok(vec![root(r1.min(r2))?, root(r1.max(r2))?])
+}
+
+fn numeric_roots(f: &mut Function, (a, b): (f64, f64), equation: &Expression) -> Result<Vec<Value>, Error> {
+    let xs: Vec<f64> = (0..=STEPS).map(|k| a + (b - a) * k as f64 / STEPS as f64).collect();
+    let mut error = Option::None;
+    let ys: Vec<Option<f64>> = xs.iter().map(|&x| match f.at(x) {
+        Ok(y) => Option::Some(y),
+        Err(message) => {
+            error.get_or_insert(message);
+            Option::None
+        }
+    }).collect();
+    if ys.iter().all(Option::is_none) {
+        return Result::Err(error.expect("a sample neither failed nor succeeded"));
+    }
+
+    let changes_sign = |k: usize| match (ys[k], ys[k + 1]) {
+        (Some(y0), Some(y1)) => y0 != 0.0 && y1 != 0.0 && (y0 < 0.0) != (y1 < 0.0),
+        _ => false
+    };
+    let mut roots = vec![];
+    let mut diverged = false;
+    for k in 0..=STEPS {
+        let y = match ys[k] {
+            Some(y) => y,
+            None => continue
+        };
+        if y == 0.0 {
+            roots.push(xs[k]);
+        }
+        if k < STEPS && changes_sign(k) {
+            match f.bracketed(xs[k], y, xs[k + 1], ys[k + 1].unwrap()) {
+                Some(r) => roots.push(r),
+                None => diverged = true
+            }
+        }
+        // the nearest the sides come, without crossing
+        if 0 < k && k < STEPS && !changes_sign(k - 1) && !changes_sign(k) {
+            if let (Some(before), Some(after)) = (ys[k - 1], ys[k + 1]) {
+                if y.abs() < before.abs() && y.abs() <= after.abs() {
+                    if let Some(r) = f.newton(xs[k], (a, b)) {
+                        roots.push(r);
+                    }
+                }
+            }
+        }
+    }
+
+    roots.sort_by(|r1, r2| r1.partial_cmp(r2).unwrap());
+    roots.dedup_by(|r2, r1| (*r2 - *r1).abs() <= 1e3 * TOLERANCE * r1.abs().max(1.0));
+    if roots.is_empty() {
+        return Result::Err(not_found(equation, (a, b), diverged));
+    }
+    roots.into_iter().map(root).collect()
+}
+
+fn interval(e: &Expression, bindings: &Bindings) -> Result<(f64, f64), Error> {
+    let bound = |e: &Expression| match e.evaluate_in(bindings)? {
+        v @ Value::Integer(_) | v @ Value::Real(_) | v @ Value::Big(_) | v @ Value::Rational(_) =>
+            Result::Ok(v.to_f64()),
+        _ => Result::Err(Error::units("Cannot solve between anything but plain numbers"))
+    };
+    match e {
+        Expression::Range(start, end, None) => {
+            let (a, b) = (bound(start)?, bound(end)?);
+            Result::Ok((a.min(b), a.max(b)))
+        },
+        _ => Result::Err(Error::unsupported("Cannot solve in anything but an interval, such as -10..10"))
+    }
+}
+
+// whether a call is to the equation solver rather than to solve a matrix
+pub fn solves_equation(name: &str, arguments: &[Expression]) -> bool {
+    name == "solve" &&
+        (arguments.len() != 2 || matches!(arguments[0], Expression::BinaryOperation(_, Operation::Equal, _)))
+}
+
+// solve(lhs = rhs, x), the list of roots in increasing order, or
+// solve(lhs = rhs, x, a..b), only those between a and b
+pub fn solve(arguments: &[Expression], bindings: &Bindings) -> Result<Value, Error> {
+    check_call("solve", arguments.len()).map_err(Error::unsupported)?;
+    let equation = &arguments[0];
+    let residual = match equation {
+        Expression::BinaryOperation(lhs, Operation::Equal, rhs) =>
+            Expression::BinaryOperation(lhs.clone(), Operation::Minus, rhs.clone()),
+        _ => return Result::Err(Error::unsupported("Cannot solve anything but an equation, such as x^2 = 2"))
+    };
+    let x = match &arguments[1] {
+        Expression::Variable(x) => x,
+        _ => return Result::Err(Error::unsupported("Cannot solve for anything but a variable"))
+    };
+    let between = match arguments.get(2) {
+        Some(e) => Option::Some(interval(e, bindings)?),
+        None => Option::None
+    };
+
+    let slope = derivative(&residual, x).ok();
+    let degree = if !mentions(&residual, x) {
+        Option::Some(0)
+    } else {
+        match slope {
+            Some(ref slope) if !mentions(slope, x) => Option::Some(1),
+            Some(ref slope) => match derivative(slope, x) {
+                Ok(ref curvature) if !mentions(curvature, x) => Option::Some(2),
+                _ => Option::None
+            },
+            None => Option::None
+        }
+    };
+    let mut f = Function { residual, slope, x, bindings: bindings.clone() };
+    let roots = match (degree, between) {
+        (Some(degree), None) => closed_form(&mut f, degree, equation)?,
+        (Some(degree), Some((a, b))) => {
+            let roots: Vec<Value> = closed_form(&mut f, degree, equation)?.into_iter()
+                .filter(|r| match r {
+                    Value::Real(r) => a <= *r && *r <= b,
+                    _ => false
+                })
+                .collect();
+            if roots.is_empty() {
+                return Result::Err(not_found(equation, (a, b), false));
+            }
+            roots
+        },
+        (None, between) => numeric_roots(&mut f, between.unwrap_or(INTERVAL), equation)?
+    };
+    Result::Ok(Value::List(roots))
+}
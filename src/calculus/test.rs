@@ -0,0 +1,158 @@
+use interpreter::{Bindings, Error, ErrorKind, Interpreter};
+use lexer::Lexer;
+use parser::{Expression, Parser};
+use value::Value;
+use super::{derivative, solve};
+
+fn parse_source(source: &str) -> Expression {
+    Parser::parse(&Lexer::tokenize(&mut source.chars()).unwrap()).unwrap()
+}
+
+fn derivative_of(source: &str) -> String {
+    match derivative(&parse_source(source), "x") {
+        Ok(e) => e.to_string(),
+        Err(message) => message
+    }
+}
+
+fn at(source: &str, x: Value) -> String {
+    let mut bindings = Bindings::new();
+    bindings.insert("x".to_string(), x);
+    match parse_source(source).evaluate_in(&bindings) {
+        Ok(v) => v.to_string(),
+        Err(error) => error.message
+    }
+}
+
+#[test]
+fn sums_and_products_differentiate_term_by_term() {
+    assert_eq!("1", derivative_of("x"));
+    assert_eq!("0", derivative_of("y + 3"));
+    assert_eq!("2 * x", derivative_of("x^2"));
+    assert_eq!("6 * x + 2", derivative_of("3x^2 + 2x + 1"));
+    assert_eq!("y", derivative_of("x * y"));
+    assert_eq!("2 * x * y + y ^ 2", derivative_of("x^2 * y + x * y^2"));
+    assert_eq!("-3 * x ^ -4", derivative_of("x^-3"));
+    assert_eq!("x - 1 + x", derivative_of("x * (x - 1)"));
+}
+
+#[test]
+fn quotients_powers_and_functions_use_their_rules() {
+    assert_eq!("(x + 1 - x) * (x + 1) ^ -2", derivative_of("x / (x + 1)"));
+    assert_eq!("-1.5 * x ^ -2.5", derivative_of("x^-1.5"));
+    assert_eq!("2 ^ x * ln(2)", derivative_of("2^x"));
+    assert_eq!("x * x ^ (x - 1) + x ^ x * ln(x)", derivative_of("x^x"));
+    assert_eq!("(2 * √x) ^ -1", derivative_of("√x"));
+    assert_eq!("2 * exp(2 * x)", derivative_of("exp(2x)"));
+    assert_eq!("2 * x * exp(x ^ 2)", derivative_of("exp(x^2)"));
+    assert_eq!("x ^ -1", derivative_of("ln(x)"));
+    assert_eq!("x * abs(x) ^ -1", derivative_of("abs(x)"));
+    assert_eq!("[1, 2 * x]", derivative_of("[x, x^2]"));
+    assert_eq!("2", derivative_of("diff(x^2, x)"));
+    assert_eq!("km to m", derivative_of("x km to m"));
+}
+
+#[test]
+fn derivatives_evaluate_at_a_point() {
+    let derivative = derivative(&parse_source("x^3 - 2x"), "x").unwrap();
+    assert_eq!("10", at(&derivative.to_string(), Value::Integer(2)));
+    assert_eq!("0.25", at("diff(√x, x)", Value::Integer(4)));
+    assert_eq!("0.5", at("diff(ln(x), x)", Value::Integer(2)));
+    assert_eq!("1000 m", at("diff(x km to m, x)", Value::Integer(7)));
+    assert_eq!("12", at("diff(x * diff(x^2, x), x)", Value::Integer(3)));
+    assert_eq!(Result::Err(Error::new(ErrorKind::UnboundVariable, "Unbound variable: x")),
+               parse_source("diff(x^2, x)").evaluate());
+}
+
+#[test]
+fn derivatives_evaluate_at_a_given_point() {
+    let value = |source: &str| match parse_source(source).evaluate() {
+        Ok(v) => v.to_string(),
+        Err(error) => error.message
+    };
+    assert_eq!("6", value("diff(x^2, x, 3)"));
+    assert_eq!("10", value("diff(x^3 - 2x, x, 2)"));
+    assert_eq!("0.25", value("diff(√x, x, 4)"));
+    // quotients are not truncated at a whole-number point
+    assert_eq!("-0.25", value("diff(1/x, x, 2)"));
+    assert_eq!("0.5", value("diff(x/2, x, 1)"));
+    assert_eq!("Unbound variable: y", value("diff(x * y, x, 2)"));
+    assert_eq!("Division by zero", value("diff(3, x, 1/0)"));
+    // the point is evaluated outside, where x is bound
+    assert_eq!("8", at("diff(x^2, x, x + 1)", Value::Integer(3)));
+    assert_eq!("0", derivative_of("diff(x^2, x, 3)"));
+    assert_eq!("Cannot differentiate a derivative at a point", derivative_of("diff(x^2, x, x)"));
+}
+
+#[test]
+fn some_expressions_have_no_derivative() {
+    assert_eq!("Cannot differentiate a comparison", derivative_of("x < 2"));
+    assert_eq!("Cannot differentiate a factorial", derivative_of("x!"));
+    assert_eq!("Cannot differentiate median", derivative_of("median([x, 1])"));
+    assert_eq!("Cannot differentiate a range", derivative_of("1..x"));
+    assert_eq!("Cannot differentiate an index that depends on x", derivative_of("[1, 2][x]"));
+    assert_eq!("Cannot differentiate with respect to anything but a variable", derivative_of("diff(x, 2)"));
+}
+
+fn roots_of(source: &str) -> String {
+    match parse_source(source) {
+        Expression::Call(_, arguments) => match solve(&arguments, &Bindings::new()) {
+            Ok(v) => v.to_string(),
+            Err(error) => error.message
+        },
+        e => panic!("{} is not a call", e)
+    }
+}
+
+#[test]
+fn linear_and_quadratic_equations_are_solved_in_closed_form() {
+    assert_eq!("[4]", roots_of("solve(2x + 3 = 11, x)"));
+    assert_eq!("[6]", roots_of("solve(x/2 = 3, x)"));
+    assert_eq!("[3]", roots_of("solve(x km = 3000 m, x)"));
+    assert_eq!("[-3, 0.5]", roots_of("solve(2x^2 + 5x = 3, x)"));
+    assert_eq!("[1]", roots_of("solve(x^2 - 2x + 1 == 0, x)"));
+    assert_eq!("[-1-2i, -1+2i]", roots_of("solve(x^2 + 2x + 5 = 0, x)"));
+    assert_eq!("[0.5]", roots_of("solve(2x^2 + 5x = 3, x, 0..10)"));
+    assert_eq!("Cannot solve x - x == 1 for x: it holds for no value", roots_of("solve(x - x = 1, x)"));
+    assert_eq!("Cannot solve 2 == 2 for x: it holds for every value", roots_of("solve(2 = 2, x)"));
+}
+
+#[test]
+fn other_equations_are_solved_numerically() {
+    let roots = |source: &str| -> Vec<f64> {
+        match parse_source(source) {
+            Expression::Call(_, arguments) => match solve(&arguments, &Bindings::new()) {
+                Ok(Value::List(roots)) => roots.iter().map(Value::to_f64).collect(),
+                result => panic!("{} gave {:?}", source, result)
+            },
+            e => panic!("{} is not a call", e)
+        }
+    };
+    let assert_roots = |source: &str, expected: &[f64]| {
+        let found = roots(source);
+        assert_eq!(expected.len(), found.len(), "{} gave {:?}", source, found);
+        for (r, e) in found.iter().zip(expected) {
+            assert!((r - e).abs() < 1e-9, "{} gave {:?}", source, found);
+        }
+    };
+    let golden = (1.0 + 5f64.sqrt()) / 2.0;
+    assert_roots("solve(x^3 - 2x = 1, x)", &[-1.0, 1.0 - golden, golden]);
+    assert_roots("solve(ln(x) = 1, x)", &[std::f64::consts::E]);
+    assert_roots("solve(2^x = 8, x)", &[3.0]);
+    assert_roots("solve(x! = 120, x, 0..10)", &[5.0]);
+    // touching without crossing
+    assert_roots("solve((x - 1)^4 = 0, x)", &[1.0]);
+}
+
+#[test]
+fn failures_to_solve_say_why() {
+    assert_eq!("Cannot find a root of exp(x) == 0 between -100 and 100", roots_of("solve(exp(x) = 0, x)"));
+    assert_eq!("Cannot find a root of 1 / (x - 0.05) == 0 between -100 and 100: where it changes sign, nothing converges",
+               roots_of("solve(1/(x - 0.05) = 0, x)"));
+    assert_eq!("Cannot find a root of x ^ 2 == 4 between 3 and 10", roots_of("solve(x^2 = 4, x, 3..10)"));
+    assert_eq!("Cannot find a root of x ^ 3 == 8 between -10 and 0.5", roots_of("solve(x^3 = 8, x, -10..0.5)"));
+    assert_eq!("Unbound variable: a", roots_of("solve(x^2 = a, x)"));
+    assert_eq!("Cannot solve for anything but a variable", roots_of("solve(x = 1, 2)"));
+    assert_eq!("Cannot solve in anything but an interval, such as -10..10", roots_of("solve(x = 1, x, [0, 1])"));
+    assert_eq!("Cannot solve an equation between lists", roots_of("solve([x, x^3] = [1, 2], x)"));
+}
@@ -0,0 +1,81 @@
+// An on-disk collection of fuzz inputs worth keeping, one file per input
+// in a readable text form, under a directory per fuzzer.  Failures are
+// saved here automatically; entries can also be written by hand.
+
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::PathBuf;
+
+// set to use a corpus other than the one checked into the repository
+pub const CORPUS_VARIABLE: &str = "CALCULATOR_FUZZ_CORPUS";
+
+// converts a fuzz input to and from its corpus file contents
+pub trait Persist: Sized {
+    fn encode(&self) -> String;
+    fn decode(text: &str) -> Result<Self, String>;
+}
+
+impl Persist for Vec<char> {
+    fn encode(&self) -> String {
+        self.iter().collect()
+    }
+
+    fn decode(text: &str) -> Result<Vec<char>, String> {
+        Result::Ok(text.chars().collect())
+    }
+}
+
+impl Persist for u32 {
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    fn decode(text: &str) -> Result<u32, String> {
+        text.trim().parse().map_err(|e| format!("{}", e))
+    }
+}
+
+pub fn directory(name: &str) -> PathBuf {
+    let root = match env::var(CORPUS_VARIABLE) {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fuzz").join("corpus")
+    };
+    root.join(name)
+}
+
+// files are named by a hash of their contents, so saving twice is harmless
+pub fn save(name: &str, contents: &str) -> io::Result<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    let dir = directory(name);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{:016x}.txt", hasher.finish()));
+    fs::write(&path, contents)?;
+    Result::Ok(path)
+}
+
+// every entry for the named fuzzer, in a stable order
+pub fn load(name: &str) -> io::Result<Vec<(PathBuf, String)>> {
+    let dir = directory(name);
+    if !dir.exists() {
+        return Result::Ok(vec![]);
+    }
+    let mut paths = vec![];
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut entries = vec![];
+    for path in paths {
+        let contents = fs::read_to_string(&path)?;
+        entries.push((path, contents));
+    }
+    Result::Ok(entries)
+}
@@ -1,11 +1,164 @@
 extern crate rand;
 
-use self::rand::Rng;
+use std::any::Any;
+use std::env;
+use std::fmt::{self, Debug};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use self::rand::{thread_rng, Rng, SeedableRng};
+
+use super::corpus::{self, Persist};
+
+// set to replay a run; otherwise a fresh seed is picked and printed
+pub const SEED_VARIABLE: &str = "CALCULATOR_FUZZ_SEED";
+
+// set to override how many cases each fuzzer runs
+pub const NUM_TESTS_VARIABLE: &str = "CALCULATOR_FUZZ_TESTS";
+
+pub const DEFAULT_NUM_TESTS: u32 = 100000;
+
+// set to override how many threads share each fuzzer's cases
+pub const THREADS_VARIABLE: &str = "CALCULATOR_FUZZ_THREADS";
+
+// how many candidates to try before settling for the smallest failure so far
+const MAX_SHRINK_ATTEMPTS: u32 = 10000;
+
+// held while the panic hook is swapped out for shrinking, as it is shared
+// by every thread
+pub(super) static HOOK: Mutex<()> = Mutex::new(());
+
+fn from_env<A: ::std::str::FromStr>(variable: &str) -> Option<A> {
+    env::var(variable).ok().map(|value| {
+        match value.trim().parse() {
+            Ok(parsed) => parsed,
+            Err(_) => panic!("{} is not a valid value for {}", value, variable)
+        }
+    })
+}
+
+pub fn seed_from_env() -> u64 {
+    let seed = from_env(SEED_VARIABLE).unwrap_or_else(|| thread_rng().gen());
+    println!("Fuzzing with seed {} (set {} to replay)", seed, SEED_VARIABLE);
+    seed
+}
+
+pub fn num_tests_from_env() -> Option<u32> {
+    from_env(NUM_TESTS_VARIABLE)
+}
+
+pub fn threads_from_env() -> usize {
+    from_env(THREADS_VARIABLE).unwrap_or_else(|| {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    })
+}
+
+// Worker 0 uses the run's seed itself, so a single-threaded run with a
+// worker's seed replays exactly the cases that worker ran.
+pub fn worker_seed(seed: u64, worker: usize) -> u64 {
+    if worker == 0 {
+        seed
+    } else {
+        seed ^ (worker as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "(non-string panic)".to_string()
+        }
+    }
+}
+
+// a failing case, after shrinking
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Failure {
+    pub seed: u64,
+    pub case: u32,
+    pub item: String,
+    pub minimal: String,
+    pub message: String
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "case {} of seed {} (replay with {}={} {}=1)\n  \
+                   input: {}\n  shrunk to: {}\n  panicked with: {}",
+               self.case, self.seed, SEED_VARIABLE, self.seed, THREADS_VARIABLE,
+               self.item, self.minimal, self.message)
+    }
+}
+
+// Produces simpler variants of a value, simplest first, for minimizing
+// a failing input.  Every candidate must be strictly simpler than the
+// original, so that repeatedly shrinking terminates.
+pub trait Shrink: Sized {
+    fn shrink(&self) -> Vec<Self>;
+}
+
+impl Shrink for u32 {
+    fn shrink(&self) -> Vec<u32> {
+        let i = *self;
+        let mut result = vec![];
+        if i != 0 {
+            result.push(0);
+        }
+        if i / 2 != 0 {
+            result.push(i / 2);
+        }
+        if i > 1 && i - 1 != i / 2 {
+            result.push(i - 1);
+        }
+        result
+    }
+}
+
+impl Shrink for char {
+    fn shrink(&self) -> Vec<char> {
+        ['a', '0', ' '].iter()
+            .take_while(|simpler| *simpler != self)
+            .cloned()
+            .collect()
+    }
+}
+
+// removes ever smaller runs of elements, then shrinks elements in place
+impl<A: Shrink + Clone> Shrink for Vec<A> {
+    fn shrink(&self) -> Vec<Vec<A>> {
+        let mut result = vec![];
+        let mut chunk = self.len();
+        while chunk > 0 {
+            let mut start = 0;
+            while start + chunk <= self.len() {
+                let mut smaller = self[..start].to_vec();
+                smaller.extend_from_slice(&self[start + chunk..]);
+                result.push(smaller);
+                start += chunk;
+            }
+            chunk /= 2;
+        }
+        for (index, element) in self.iter().enumerate() {
+            for simpler in element.shrink() {
+                let mut replaced = self.clone();
+                replaced[index] = simpler;
+                result.push(replaced);
+            }
+        }
+        result
+    }
+}
 
 pub trait Fuzzer {
-    type Item;
-    type R: Rng;
-    
+    type Item: Clone + Debug + Persist;
+    type R: Rng + SeedableRng;
+
+    fn new(random: Self::R) -> Self where Self: Sized;
+
     fn gen_one(&mut self) -> Self::Item;
 
     // panics if test fails
@@ -14,13 +167,160 @@ pub trait Fuzzer {
     fn random_generator(&mut self) -> &mut Self::R;
 
     fn num_tests(&self) -> u32 {
-        100000
+        num_tests_from_env().unwrap_or(DEFAULT_NUM_TESTS)
     }
-    
-    fn run_tests(&mut self) {
-        for _ in 0..self.num_tests() {
+
+    // where failures are saved and replayed from; by default they are not
+    fn corpus_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    // simpler variants of a failing item; by default it is not shrunk
+    fn shrink(&self, _item: &Self::Item) -> Vec<Self::Item> {
+        vec![]
+    }
+
+    fn fails(&mut self, item: Self::Item) -> bool {
+        panic::catch_unwind(AssertUnwindSafe(|| self.run_test(item))).is_err()
+    }
+
+    // Greedily replaces a failing item with its first failing shrink,
+    // until none of its shrinks fail.  Panics on this thread go unreported
+    // meanwhile, as each failing candidate would print one.
+    fn minimize(&mut self, item: Self::Item) -> Self::Item {
+        let _hook = HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = Arc::new(panic::take_hook());
+        let shrinking = thread::current().id();
+        let others = previous.clone();
+        panic::set_hook(Box::new(move |info| if thread::current().id() != shrinking {
+            others(info)
+        }));
+        let minimal = self.shrink_failing(item);
+        drop(panic::take_hook());
+        match Arc::try_unwrap(previous) {
+            Ok(previous) => panic::set_hook(previous),
+            Err(_) => panic!("the shrinking panic hook outlived its removal")
+        }
+        minimal
+    }
+
+    fn shrink_failing(&mut self, item: Self::Item) -> Self::Item {
+        let mut current = item;
+        let mut attempts = 0;
+        'search: loop {
+            for candidate in self.shrink(&current) {
+                if attempts == MAX_SHRINK_ATTEMPTS {
+                    return current;
+                }
+                attempts += 1;
+                if self.fails(candidate.clone()) {
+                    current = candidate;
+                    continue 'search;
+                }
+            }
+            return current;
+        }
+    }
+
+    // Runs up to count cases; the same seed always generates the same
+    // cases.  A failing item is shrunk and saved to the corpus, and stop is
+    // set so other workers can give up.  Stops early if stop is already set.
+    fn run_shard(&mut self, seed: u64, count: u32, stop: &AtomicBool) -> Option<Failure> {
+        *self.random_generator() = Self::R::seed_from_u64(seed);
+        for case in 0..count {
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
             let item = self.gen_one();
-            self.run_test(item);
+            let reported = item.clone();
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| self.run_test(item)));
+            if let Err(payload) = outcome {
+                stop.store(true, Ordering::Relaxed);
+                println!("Failed on case {} with seed {}: {:?}", case, seed, reported);
+                let minimal = self.minimize(reported.clone());
+                println!("Shrunk to: {:?}", minimal);
+                if let Some(name) = self.corpus_name() {
+                    match corpus::save(name, &minimal.encode()) {
+                        Ok(path) => println!("Saved to {}", path.display()),
+                        Err(e) => println!("Could not save to corpus: {}", e)
+                    }
+                }
+                // the shrunk item should fail again; if not, report the original
+                let rerun = minimal.clone();
+                let message = match panic::catch_unwind(AssertUnwindSafe(|| self.run_test(rerun))) {
+                    Err(minimal_payload) => panic_message(&*minimal_payload),
+                    Ok(()) => panic_message(&*payload)
+                };
+                return Some(Failure {
+                    seed,
+                    case,
+                    item: format!("{:?}", reported),
+                    minimal: format!("{:?}", minimal),
+                    message
+                });
+            }
+        }
+        None
+    }
+
+    // runs every case on this thread, panicking on failure
+    fn run_tests(&mut self, seed: u64) {
+        if let Some(failure) = self.run_shard(seed, self.num_tests(), &AtomicBool::new(false)) {
+            panic!("Fuzzer failed on {}", failure);
+        }
+    }
+}
+
+// Splits a fuzzer's cases across threads, each running its own instance
+// from its own seed.  All workers stop once any of them fails, and every
+// failure found by then is returned.
+pub fn run_parallel<F: Fuzzer>(seed: u64, threads: usize) -> Result<(), Vec<Failure>> {
+    let threads = threads.max(1);
+    let total = F::new(F::R::seed_from_u64(seed)).num_tests();
+    let stop = AtomicBool::new(false);
+
+    let failures: Vec<Failure> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|worker| {
+            // the first total % threads workers take one extra case
+            let count = total / threads as u32 +
+                if (worker as u32) < total % threads as u32 { 1 } else { 0 };
+            let stop = &stop;
+            scope.spawn(move || {
+                let seed = worker_seed(seed, worker);
+                F::new(F::R::seed_from_u64(seed)).run_shard(seed, count, stop)
+            })
+        }).collect();
+        workers.into_iter()
+            .filter_map(|worker| worker.join().expect("Fuzzer worker panicked"))
+            .collect()
+    });
+
+    if failures.is_empty() {
+        Result::Ok(())
+    } else {
+        Result::Err(failures)
+    }
+}
+
+pub fn run_fuzzer<F: Fuzzer>() {
+    let seed = seed_from_env();
+    if let Err(failures) = run_parallel::<F>(seed, threads_from_env()) {
+        let reports: Vec<String> = failures.iter().map(|f| f.to_string()).collect();
+        panic!("Fuzzer failed on {}", reports.join("\nand on "));
+    }
+}
+
+// reruns the test on every corpus entry saved for this fuzzer
+pub fn replay_corpus<F: Fuzzer>() {
+    let mut fuzzer = F::new(F::R::seed_from_u64(0));
+    let name = fuzzer.corpus_name().expect("Fuzzer has no corpus");
+    let entries = corpus::load(name)
+        .unwrap_or_else(|e| panic!("Could not read corpus {}: {}", name, e));
+    for (path, contents) in entries {
+        println!("Replaying {}", path.display());
+        match F::Item::decode(&contents) {
+            Ok(item) => fuzzer.run_test(item),
+            Err(e) => panic!("Could not decode {}: {}", path.display(), e)
         }
     }
 }
@@ -1,32 +1,147 @@
-pub struct PushbackIterator<'a, A> where A : 'a {
-    around: &'a mut Iterator<Item = A>,
-    stack: Vec<A>
+use std::collections::VecDeque;
+use std::fmt;
+
+// where an item sits in the input; lines and columns count from 1
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    // in bytes of UTF-8, counting from 0
+    pub offset: usize
+}
+
+impl Position {
+    pub fn start() -> Position {
+        Position { line: 1, column: 1, offset: 0 }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+// what position tracking needs to know about an item
+pub trait Measure {
+    fn is_newline(&self) -> bool;
+    fn len_bytes(&self) -> usize;
+}
+
+impl Measure for char {
+    fn is_newline(&self) -> bool {
+        *self == '\n'
+    }
+
+    fn len_bytes(&self) -> usize {
+        self.len_utf8()
+    }
+}
+
+impl Measure for u8 {
+    fn is_newline(&self) -> bool {
+        *self == b'\n'
+    }
+
+    fn len_bytes(&self) -> usize {
+        1
+    }
+}
+
+// how many of the newlines last read can be pushed back with their
+// columns restored; push-back is for undoing a short look ahead
+const PUSHBACK_LINES: usize = 16;
+
+// Owns the iterator it wraps; pass `&mut iterator` to borrow one instead.
+// Items can be looked at ahead of time, or pushed back after being read,
+// and the position of the next item is kept up to date either way.
+pub struct PushbackIterator<I: Iterator> {
+    around: I,
+    // items to return before reading more from around, next first
+    buffer: VecDeque<I::Item>,
+    position: Position,
+    // the column each of the last PUSHBACK_LINES lines ended at, so
+    // pushing back a newline can restore it
+    line_ends: VecDeque<usize>
 }
 
-impl<'a, A> PushbackIterator<'a, A> where A : 'a {
-    pub fn new(input: &'a mut Iterator<Item = A>) -> PushbackIterator<'a, A> {
+impl<I: Iterator> PushbackIterator<I> where I::Item: Measure {
+    pub fn new(input: I) -> PushbackIterator<I> {
         PushbackIterator {
             around: input,
-            stack: vec![]
+            buffer: VecDeque::new(),
+            position: Position::start(),
+            line_ends: VecDeque::new()
+        }
+    }
+
+    // Returns an item so that it is read next.  It is taken to be the
+    // last item read, and the position moves back over it.  Pushing back
+    // more than PUSHBACK_LINES newlines loses the columns of the lines
+    // before them, which are then taken to end at column 1.
+    pub fn push(&mut self, item: I::Item) {
+        if item.is_newline() {
+            // a newline that was never read has no line to go back to
+            if self.position.line > 1 {
+                self.position.line -= 1;
+                self.position.column = self.line_ends.pop_back().unwrap_or(1);
+            }
+        } else {
+            self.position.column = self.position.column.saturating_sub(1).max(1);
+        }
+        self.position.offset = self.position.offset.saturating_sub(item.len_bytes());
+        self.buffer.push_front(item);
+    }
+
+    pub fn peek(&mut self) -> Option<&I::Item> {
+        self.peek_nth(0)
+    }
+
+    // the item n places after the next one, without consuming anything
+    pub fn peek_nth(&mut self, n: usize) -> Option<&I::Item> {
+        while self.buffer.len() <= n {
+            match self.around.next() {
+                Some(item) => self.buffer.push_back(item),
+                None => return None
+            }
         }
+        self.buffer.get(n)
     }
 
-    pub fn push(&mut self, item: A) {
-        self.stack.push(item);
+    // where the next item starts
+    pub fn position(&self) -> Position {
+        self.position
     }
 }
 
-impl<'a, A> Iterator for PushbackIterator<'a, A> {
-    type Item = A;
+impl<I: Iterator> Iterator for PushbackIterator<I> where I::Item: Measure {
+    type Item = I::Item;
 
-    fn next(&mut self) -> Option<A> {
-        match self.stack.pop() {
+    fn next(&mut self) -> Option<I::Item> {
+        let result = match self.buffer.pop_front() {
             None => self.around.next(),
             some => some
+        };
+        if let Some(ref item) = result {
+            if item.is_newline() {
+                if self.line_ends.len() == PUSHBACK_LINES {
+                    self.line_ends.pop_front();
+                }
+                self.line_ends.push_back(self.position.column);
+                self.position.line += 1;
+                self.position.column = 1;
+            } else {
+                self.position.column += 1;
+            }
+            self.position.offset += item.len_bytes();
         }
+        result
     }
 }
 
 #[cfg(test)]
 mod test;
+#[cfg(test)]
 pub mod fuzzer;
+#[cfg(test)]
+pub mod corpus;
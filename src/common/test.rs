@@ -1,4 +1,15 @@
-use super::PushbackIterator;
+extern crate rand;
+
+use std::panic;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use super::{Position, PushbackIterator, PUSHBACK_LINES};
+use super::corpus::Persist;
+use super::fuzzer::{run_parallel, worker_seed, Fuzzer, Shrink, HOOK};
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
 
 #[test]
 fn next_works_without_push() {
@@ -17,3 +28,291 @@ fn next_char_works_with_look_here_first() {
     assert_eq!(Option::Some('a'), pushback.next());
     assert_eq!(Option::None, pushback.next());
 }
+
+#[test]
+fn owns_its_iterator() {
+    let mut pushback = PushbackIterator::new("ab".chars());
+    assert_eq!(Option::Some('a'), pushback.next());
+    assert_eq!(Option::Some('b'), pushback.next());
+    assert_eq!(Option::None, pushback.next());
+}
+
+#[test]
+fn peek_does_not_consume() {
+    let mut pushback = PushbackIterator::new("ab".chars());
+    assert_eq!(Option::Some(&'a'), pushback.peek());
+    assert_eq!(Option::Some(&'a'), pushback.peek());
+    assert_eq!(Option::Some('a'), pushback.next());
+    assert_eq!(Option::Some(&'b'), pushback.peek());
+}
+
+#[test]
+fn peek_nth_looks_past_pushed_items() {
+    let mut pushback = PushbackIterator::new("bc".chars());
+    pushback.push('a');
+    assert_eq!(Option::Some(&'a'), pushback.peek_nth(0));
+    assert_eq!(Option::Some(&'b'), pushback.peek_nth(1));
+    assert_eq!(Option::Some(&'c'), pushback.peek_nth(2));
+    assert_eq!(Option::None, pushback.peek_nth(3));
+    assert_eq!("abc", pushback.collect::<String>());
+}
+
+#[test]
+fn tracks_lines_columns_and_bytes() {
+    let mut pushback = PushbackIterator::new("a\n\u{e9}b".chars());
+    assert_eq!(Position { line: 1, column: 1, offset: 0 }, pushback.position());
+    pushback.next();
+    assert_eq!(Position { line: 1, column: 2, offset: 1 }, pushback.position());
+    pushback.next();
+    assert_eq!(Position { line: 2, column: 1, offset: 2 }, pushback.position());
+    pushback.next();
+    assert_eq!(Position { line: 2, column: 2, offset: 4 }, pushback.position());
+    pushback.peek_nth(1);
+    assert_eq!(Position { line: 2, column: 2, offset: 4 }, pushback.position());
+}
+
+#[test]
+fn push_moves_position_back() {
+    let mut pushback = PushbackIterator::new("ab\nc".chars());
+    let a = pushback.next().unwrap();
+    let b = pushback.next().unwrap();
+    let newline = pushback.next().unwrap();
+    let after_newline = pushback.position();
+    pushback.push(newline);
+    assert_eq!(Position { line: 1, column: 3, offset: 2 }, pushback.position());
+    pushback.push(b);
+    pushback.push(a);
+    assert_eq!(Position::start(), pushback.position());
+    pushback.nth(2);
+    assert_eq!(after_newline, pushback.position());
+}
+
+#[test]
+fn pushing_a_newline_at_the_start_stays_at_the_start() {
+    let mut pushback = PushbackIterator::new("a".chars());
+    pushback.push('\n');
+    pushback.push('\n');
+    assert_eq!(Position::start(), pushback.position());
+    assert_eq!(Option::Some('\n'), pushback.next());
+    assert_eq!(Position { line: 2, column: 1, offset: 1 }, pushback.position());
+    assert_eq!("\na", pushback.collect::<String>());
+}
+
+#[test]
+fn remembers_only_the_line_ends_push_back_can_reach() {
+    let input = "ab\n".repeat(1000);
+    let mut pushback = PushbackIterator::new(input.chars());
+    pushback.nth(3 * 1000 - 1);
+    assert_eq!(PUSHBACK_LINES, pushback.line_ends.len());
+    for _ in 0..PUSHBACK_LINES {
+        pushback.push('\n');
+        assert_eq!(3, pushback.position().column);
+        pushback.push('b');
+        pushback.push('a');
+    }
+    assert_eq!(1000 - PUSHBACK_LINES + 1, pushback.position().line);
+}
+
+struct RecordingFuzzer {
+    random: StdRng,
+    seen: Vec<u32>
+}
+
+impl Fuzzer for RecordingFuzzer {
+    type Item = u32;
+    type R = StdRng;
+
+    fn new(random: StdRng) -> RecordingFuzzer {
+        RecordingFuzzer { random, seen: vec![] }
+    }
+
+    fn gen_one(&mut self) -> u32 {
+        self.random.gen()
+    }
+
+    fn run_test(&mut self, item: u32) {
+        self.seen.push(item);
+    }
+
+    fn random_generator(&mut self) -> &mut StdRng {
+        &mut self.random
+    }
+
+    fn num_tests(&self) -> u32 {
+        4
+    }
+}
+
+#[test]
+fn same_seed_generates_same_cases() {
+    let mut first = RecordingFuzzer::new(StdRng::seed_from_u64(1));
+    let mut second = RecordingFuzzer::new(StdRng::seed_from_u64(2));
+    first.run_tests(42);
+    second.run_tests(42);
+    assert_eq!(4, first.seen.len());
+    assert_eq!(first.seen, second.seen);
+}
+
+#[test]
+fn different_seeds_generate_different_cases() {
+    let mut first = RecordingFuzzer::new(StdRng::seed_from_u64(1));
+    let mut second = RecordingFuzzer::new(StdRng::seed_from_u64(1));
+    first.run_tests(1);
+    second.run_tests(2);
+    assert!(first.seen != second.seen);
+}
+
+struct FailingFuzzer {
+    random: StdRng
+}
+
+impl Fuzzer for FailingFuzzer {
+    type Item = u32;
+    type R = StdRng;
+
+    fn new(random: StdRng) -> FailingFuzzer {
+        FailingFuzzer { random }
+    }
+
+    fn gen_one(&mut self) -> u32 {
+        self.random.gen_range(0, 10)
+    }
+
+    fn run_test(&mut self, item: u32) {
+        assert!(item != 3);
+    }
+
+    fn random_generator(&mut self) -> &mut StdRng {
+        &mut self.random
+    }
+}
+
+#[test]
+#[should_panic]
+fn failure_is_propagated() {
+    FailingFuzzer::new(StdRng::seed_from_u64(0)).run_tests(0);
+}
+
+#[test]
+fn integers_shrink_toward_zero() {
+    assert_eq!(Vec::<u32>::new(), 0.shrink());
+    assert_eq!(vec![0], 1.shrink());
+    assert_eq!(vec![0, 5, 9], 10.shrink());
+}
+
+#[test]
+fn vectors_shrink_by_removing_then_simplifying() {
+    assert_eq!(vec![vec![], vec![2], vec![1], vec![0, 2], vec![1, 0], vec![1, 1]],
+               vec![1u32, 2].shrink());
+}
+
+impl Persist for Vec<u32> {
+    fn encode(&self) -> String {
+        self.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(" ")
+    }
+
+    fn decode(text: &str) -> Result<Vec<u32>, String> {
+        text.split_whitespace().map(u32::decode).collect()
+    }
+}
+
+// fails on any list holding a value of at least five
+struct ThresholdFuzzer {
+    random: StdRng
+}
+
+impl Fuzzer for ThresholdFuzzer {
+    type Item = Vec<u32>;
+    type R = StdRng;
+
+    fn new(random: StdRng) -> ThresholdFuzzer {
+        ThresholdFuzzer { random }
+    }
+
+    fn gen_one(&mut self) -> Vec<u32> {
+        (0..20).map(|_| self.random.gen_range(0, 100)).collect()
+    }
+
+    fn run_test(&mut self, item: Vec<u32>) {
+        assert!(item.iter().all(|i| *i < 5));
+    }
+
+    fn random_generator(&mut self) -> &mut StdRng {
+        &mut self.random
+    }
+
+    fn shrink(&self, item: &Vec<u32>) -> Vec<Vec<u32>> {
+        item.shrink()
+    }
+}
+
+#[test]
+fn minimize_finds_smallest_failure() {
+    let mut fuzzer = ThresholdFuzzer::new(StdRng::seed_from_u64(0));
+    let failing = fuzzer.gen_one();
+    assert!(fuzzer.fails(failing.clone()));
+    assert_eq!(vec![5], fuzzer.minimize(failing));
+}
+
+// other tests panic on their own threads, so only this one's count
+#[test]
+fn minimize_silences_panics_until_it_is_done() {
+    let this = thread::current().id();
+    let reported = Arc::new(AtomicUsize::new(0));
+    let count = reported.clone();
+    let original = {
+        let _hook = HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let original = panic::take_hook();
+        panic::set_hook(Box::new(move |_| if thread::current().id() == this {
+            count.fetch_add(1, Ordering::SeqCst);
+        }));
+        original
+    };
+    let mut fuzzer = ThresholdFuzzer::new(StdRng::seed_from_u64(0));
+    let failing = fuzzer.gen_one();
+    assert_eq!(vec![5], fuzzer.minimize(failing));
+    assert_eq!(0, reported.load(Ordering::SeqCst));
+    assert!(fuzzer.fails(vec![7]));
+    assert_eq!(1, reported.load(Ordering::SeqCst));
+
+    let _hook = HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    panic::set_hook(original);
+}
+
+#[test]
+fn parallel_run_passes_when_every_worker_passes() {
+    assert_eq!(Result::Ok(()), run_parallel::<RecordingFuzzer>(7, 3));
+}
+
+#[test]
+fn parallel_run_reports_shrunk_failures() {
+    let failures = run_parallel::<ThresholdFuzzer>(0, 4).unwrap_err();
+    assert!(!failures.is_empty());
+    for failure in failures {
+        assert_eq!("[5]", failure.minimal);
+        assert!((0..4).any(|worker| worker_seed(0, worker) == failure.seed));
+    }
+}
+
+#[test]
+fn parallel_failure_replays_on_one_thread() {
+    for failure in run_parallel::<FailingFuzzer>(9, 3).unwrap_err() {
+        assert_eq!(Result::Err(vec![failure.clone()]),
+                   run_parallel::<FailingFuzzer>(failure.seed, 1));
+    }
+}
+
+#[test]
+fn worker_seeds_are_distinct() {
+    assert_eq!(5, worker_seed(5, 0));
+    let seeds: Vec<u64> = (0..8).map(|worker| worker_seed(5, worker)).collect();
+    for (i, seed) in seeds.iter().enumerate() {
+        assert!(!seeds[i + 1..].contains(seed));
+    }
+}
+
+#[test]
+fn chars_persist_exactly() {
+    let chars = vec!['1', '\n', ' ', '\u{1F600}'];
+    assert_eq!(Result::Ok(chars.clone()), Vec::<char>::decode(&chars.encode()));
+}
@@ -0,0 +1,333 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::interpreter::Bindings;
+use super::value::{Quantity, Unit, Value};
+
+// ISO 4217 codes in common use, so that naming one without a rate says
+// so rather than reporting an unbound variable
+const CURRENCY_CODES: [&str; 44] = [
+    "AED", "ARS", "AUD", "BGN", "BRL", "CAD", "CHF", "CLP", "CNY", "COP", "CZK",
+    "DKK", "EGP", "EUR", "GBP", "HKD", "HUF", "IDR", "ILS", "INR", "ISK", "JPY",
+    "KRW", "MXN", "MYR", "NGN", "NOK", "NZD", "PEN", "PHP", "PKR", "PLN", "RON",
+    "RUB", "SAR", "SEK", "SGD", "THB", "TRY", "TWD", "UAH", "USD", "VND", "ZAR"
+];
+
+pub fn is_currency_code(name: &str) -> bool {
+    CURRENCY_CODES.contains(&name)
+}
+
+// three capital letters, as in EUR
+fn is_code(name: &str) -> bool {
+    name.len() == 3 && name.chars().all(|c| c.is_ascii_uppercase())
+}
+
+// YYYY-MM-DD
+fn is_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() == 10 && bytes.iter().enumerate().all(|(i, b)| {
+        if i == 4 || i == 7 { *b == b'-' } else { b.is_ascii_digit() }
+    })
+}
+
+// What a rates file holds, whether it was written as JSON or TOML.
+// Anything a rate table has no use for is kept as Other.
+#[derive(Debug, Clone, PartialEq)]
+enum Document {
+    Table(Vec<(String, Document)>),
+    String(String),
+    Number(f64),
+    Other
+}
+
+impl Document {
+    fn get(&self, key: &str) -> Option<&Document> {
+        match self {
+            Document::Table(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    match chars.next() {
+        Some(c) if c == expected => Result::Ok(()),
+        Some(c) => Result::Err(format!("Expected '{}', found '{}'", expected, c)),
+        None => Result::Err(format!("Expected '{}', found the end", expected))
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+// a string with JSON escapes, after its opening quote; TOML's basic
+// strings use the same ones
+fn read_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut out = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Result::Ok(out),
+            Some('\\') => {
+                let escaped = match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let digits: String = chars.by_ref().take(4).collect();
+                        u32::from_str_radix(&digits, 16).ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("Bad escape: \\u{}", digits))?
+                    },
+                    Some(c) => return Result::Err(format!("Bad escape: \\{}", c)),
+                    None => return Result::Err("Unterminated string".to_string())
+                };
+                out.push(escaped);
+            },
+            Some(c) => out.push(c),
+            None => return Result::Err("Unterminated string".to_string())
+        }
+    }
+}
+
+// the letters, digits and other allowed characters up to the next that is not
+fn read_word(chars: &mut Peekable<Chars>, allowed: &str) -> String {
+    let mut out = String::new();
+    while let Some(&c) = chars.peek() {
+        if !(c.is_alphanumeric() || allowed.contains(c)) {
+            break;
+        }
+        out.push(c);
+        chars.next();
+    }
+    out
+}
+
+fn parse_json_value(chars: &mut Peekable<Chars>) -> Result<Document, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('{') => {
+            chars.next();
+            let mut entries = vec![];
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Result::Ok(Document::Table(entries));
+            }
+            loop {
+                skip_whitespace(chars);
+                expect(chars, '"')?;
+                let key = read_string(chars)?;
+                skip_whitespace(chars);
+                expect(chars, ':')?;
+                entries.push((key, parse_json_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Result::Ok(Document::Table(entries)),
+                    _ => return Result::Err("Expected ',' or '}' in an object".to_string())
+                }
+            }
+        },
+        Some('[') => {
+            chars.next();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Result::Ok(Document::Other);
+            }
+            loop {
+                parse_json_value(chars)?;
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Result::Ok(Document::Other),
+                    _ => return Result::Err("Expected ',' or ']' in an array".to_string())
+                }
+            }
+        },
+        Some('"') => {
+            chars.next();
+            read_string(chars).map(Document::String)
+        },
+        Some(_) => {
+            let word = read_word(chars, "+-.");
+            match word.as_str() {
+                "true" | "false" | "null" => Result::Ok(Document::Other),
+                _ => word.parse().map(Document::Number)
+                    .map_err(|_| format!("Expected a value, found '{}'", word))
+            }
+        },
+        None => Result::Err("Expected a value, found the end".to_string())
+    }
+}
+
+fn parse_json(source: &str) -> Result<Document, String> {
+    let mut chars = source.chars().peekable();
+    let document = parse_json_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        Some(c) => Result::Err(format!("Unexpected '{}' after the document", c)),
+        None => Result::Ok(document)
+    }
+}
+
+// A bare or quoted key, a string, a number or a date.  Dates are kept as
+// the text they are written as.
+fn parse_toml_line(line: &str) -> Result<(String, Document), String> {
+    let mut chars = line.chars().peekable();
+    let key = match chars.peek() {
+        Some('"') => {
+            chars.next();
+            read_string(&mut chars)?
+        },
+        _ => read_word(&mut chars, "_-")
+    };
+    if key.is_empty() {
+        return Result::Err("Expected a key".to_string());
+    }
+    skip_whitespace(&mut chars);
+    expect(&mut chars, '=')?;
+    skip_whitespace(&mut chars);
+    let value = match chars.peek() {
+        Some('"') => {
+            chars.next();
+            Document::String(read_string(&mut chars)?)
+        },
+        _ => {
+            let word = read_word(&mut chars, "+-._:");
+            if is_date(&word) {
+                Document::String(word)
+            } else {
+                match word.as_str() {
+                    "true" | "false" => Document::Other,
+                    _ => word.replace('_', "").parse().map(Document::Number)
+                        .map_err(|_| format!("Expected a value, found '{}'", word))?
+                }
+            }
+        }
+    };
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None | Some('#') => Result::Ok((key, value)),
+        Some(c) => Result::Err(format!("Unexpected '{}' after the value", c))
+    }
+}
+
+// The subset of TOML a rate table needs: key = value lines, either at the
+// top or under a [table] header, and comments.
+fn parse_toml(source: &str) -> Result<Document, String> {
+    let mut top = vec![];
+    let mut tables: Vec<(String, Document)> = vec![];
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let in_line = |e: String| format!("line {}: {}", index + 1, e);
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header.split('#').next().unwrap().trim_end().strip_suffix(']')
+                .ok_or_else(|| in_line("Expected ']'".to_string()))?;
+            tables.push((name.trim().to_string(), Document::Table(vec![])));
+            continue;
+        }
+        let entry = parse_toml_line(line).map_err(in_line)?;
+        match tables.last_mut() {
+            Some((_, Document::Table(entries))) => entries.push(entry),
+            _ => top.push(entry)
+        }
+    }
+    top.extend(tables);
+    Result::Ok(Document::Table(top))
+}
+
+// Exchange rates on one date, each saying how much of a currency one of
+// the base currency buys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateTable {
+    date: String,
+    base: String,
+    rates: Vec<(String, f64)>
+}
+
+impl RateTable {
+    // A JSON object, or a TOML document, with a date, a base currency and
+    // a table of rates.  Anything else in it is ignored.
+    pub fn parse(source: &str) -> Result<RateTable, String> {
+        let document = if source.trim_start().starts_with('{') {
+            parse_json(source)?
+        } else {
+            parse_toml(source)?
+        };
+        let date = match document.get("date") {
+            Some(Document::String(date)) if is_date(date) => date.clone(),
+            Some(_) => return Result::Err("The date must be written YYYY-MM-DD".to_string()),
+            None => return Result::Err("Missing date".to_string())
+        };
+        let base = match document.get("base") {
+            Some(Document::String(base)) if is_code(base) => base.clone(),
+            Some(_) => return Result::Err("The base must be a currency code, like USD".to_string()),
+            None => return Result::Err("Missing base".to_string())
+        };
+        let entries = match document.get("rates") {
+            Some(Document::Table(entries)) => entries,
+            Some(_) => return Result::Err("The rates must be a table".to_string()),
+            None => return Result::Err("Missing rates".to_string())
+        };
+        let mut rates: Vec<(String, f64)> = vec![];
+        for (code, rate) in entries {
+            if !is_code(code) {
+                return Result::Err(format!("Not a currency code: {}", code));
+            }
+            let rate = match rate {
+                Document::Number(rate) if *rate > 0.0 && rate.is_finite() => *rate,
+                _ => return Result::Err(format!("The rate for {} must be a positive number", code))
+            };
+            if rates.iter().any(|(existing, _)| existing == code) {
+                return Result::Err(format!("More than one rate for {}", code));
+            }
+            if *code == base && rate != 1.0 {
+                return Result::Err(format!("The rate for the base, {}, must be 1", code));
+            }
+            rates.push((code.clone(), rate));
+        }
+        Result::Ok(RateTable { date, base, rates })
+    }
+
+    pub fn date(&self) -> &str {
+        &self.date
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    // how much of the currency one of the base buys
+    pub fn rate(&self, code: &str) -> Option<f64> {
+        if code == self.base {
+            return Some(1.0);
+        }
+        self.rates.iter().find(|(c, _)| c == code).map(|(_, rate)| *rate)
+    }
+
+    // Binds each currency's code to one of it, so 100 USD is a quantity and
+    // converts like any other.
+    pub fn bindings(&self) -> Bindings {
+        let mut bindings = Bindings::new();
+        bindings.insert(self.base.clone(), Value::Quantity(Quantity::new(1.0, Unit::currency(&self.base, 1.0))));
+        for (code, rate) in &self.rates {
+            bindings.insert(code.clone(), Value::Quantity(Quantity::new(1.0, Unit::currency(code, 1.0 / rate))));
+        }
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,76 @@
+use interpreter::Interpreter;
+use lexer::Lexer;
+use parser::Parser;
+use super::RateTable;
+
+const TOML: &str = "# from the ECB
+date = 2026-10-16
+base = \"USD\"
+
+[rates]
+EUR = 0.92
+GBP = 0.8  # sterling
+\"JPY\" = 1_50
+";
+
+const JSON: &str = r#"{
+  "amount": 1.0,
+  "base": "USD",
+  "date": "2026-10-16",
+  "source": ["ECB", null, true],
+  "rates": {"EUR": 0.92, "GBP": 0.8, "JPY": 1.5e2}
+}"#;
+
+fn table() -> RateTable {
+    RateTable::parse(TOML).unwrap()
+}
+
+fn evaluate(source: &str) -> String {
+    let tokens = Lexer::tokenize(&mut source.chars()).unwrap();
+    match Parser::parse(&tokens).unwrap().evaluate_in(&table().bindings()) {
+        Ok(v) => v.to_string(),
+        Err(e) => e.message
+    }
+}
+
+#[test]
+fn toml_and_json_tables_agree() {
+    let table = table();
+    assert_eq!("2026-10-16", table.date());
+    assert_eq!("USD", table.base());
+    assert_eq!(Some(1.0), table.rate("USD"));
+    assert_eq!(Some(0.92), table.rate("EUR"));
+    assert_eq!(Some(150.0), table.rate("JPY"));
+    assert_eq!(None, table.rate("CHF"));
+    assert_eq!(Result::Ok(table), RateTable::parse(JSON));
+}
+
+#[test]
+fn bad_tables_say_why() {
+    assert_eq!(Result::Err("Missing date".to_string()),
+               RateTable::parse("base = \"USD\"\n[rates]\n"));
+    assert_eq!(Result::Err("The date must be written YYYY-MM-DD".to_string()),
+               RateTable::parse(r#"{"date": "16/10/2026", "base": "USD", "rates": {}}"#));
+    assert_eq!(Result::Err("Not a currency code: euro".to_string()),
+               RateTable::parse("date = 2026-10-16\nbase = \"USD\"\n[rates]\neuro = 1.1\n"));
+    assert_eq!(Result::Err("The rate for EUR must be a positive number".to_string()),
+               RateTable::parse(r#"{"date": "2026-10-16", "base": "USD", "rates": {"EUR": -1}}"#));
+    assert_eq!(Result::Err("line 4: Expected '=', found ':'".to_string()),
+               RateTable::parse("date = 2026-10-16\nbase = \"USD\"\n[rates]\nEUR: 0.92\n"));
+    assert!(RateTable::parse(r#"{"date": "2026-10-16", "base": "USD""#).is_err());
+}
+
+#[test]
+fn currencies_convert_at_the_rates() {
+    assert_eq!("120 GBP", evaluate("100 USD + 46 EUR in GBP"));
+    assert_eq!("150 USD", evaluate("100 USD + 46 EUR"));
+    assert_eq!("75 EUR", evaluate("150 EUR/h * 30 min in EUR"));
+    assert_eq!("1", evaluate("150 JPY / 1 USD"));
+}
+
+#[test]
+fn missing_rates_are_errors() {
+    assert_eq!("No exchange rate for CHF", evaluate("100 USD in CHF"));
+    assert_eq!("Cannot add USD and m", evaluate("1 USD + 1 m"));
+    assert_eq!("Unbound variable: XYZ", evaluate("1 XYZ"));
+}
@@ -0,0 +1,179 @@
+use super::parser::{Expression, Operation};
+
+// Transforming traversal of an expression, consuming the input and
+// building a new tree.  Every method defaults to folding the children and
+// rebuilding the same node, so an implementation only overrides the cases
+// it rewrites, calling the matching walk_* function to keep recursing.
+pub trait Folder {
+    fn fold_expression(&mut self, e: Expression) -> Expression {
+        walk_expression(self, e)
+    }
+
+    fn fold_integer(&mut self, i: u32) -> Expression {
+        Expression::Integer(i)
+    }
+
+    fn fold_decimal(&mut self, digits: String) -> Expression {
+        Expression::Decimal(digits)
+    }
+
+    fn fold_imaginary(&mut self, i: u32) -> Expression {
+        Expression::Imaginary(i)
+    }
+
+    fn fold_date(&mut self, text: String) -> Expression {
+        Expression::Date(text)
+    }
+
+    fn fold_time(&mut self, text: String) -> Expression {
+        Expression::Time(text)
+    }
+
+    fn fold_variable(&mut self, name: String) -> Expression {
+        Expression::Variable(name)
+    }
+
+    fn fold_unary_minus(&mut self, e: Expression) -> Expression {
+        walk_unary_minus(self, e)
+    }
+
+    fn fold_square_root(&mut self, e: Expression) -> Expression {
+        walk_square_root(self, e)
+    }
+
+    fn fold_factorial(&mut self, e: Expression) -> Expression {
+        walk_factorial(self, e)
+    }
+
+    fn fold_percent(&mut self, e: Expression) -> Expression {
+        walk_percent(self, e)
+    }
+
+    fn fold_percent_change(&mut self,
+                           e1: Expression,
+                           op: Operation,
+                           e2: Expression) -> Expression {
+        walk_percent_change(self, e1, op, e2)
+    }
+
+    fn fold_binary_operation(&mut self,
+                             e1: Expression,
+                             op: Operation,
+                             e2: Expression) -> Expression {
+        walk_binary_operation(self, e1, op, e2)
+    }
+
+    fn fold_call(&mut self, name: String, arguments: Vec<Expression>) -> Expression {
+        walk_call(self, name, arguments)
+    }
+
+    fn fold_convert(&mut self, e: Expression, unit: Expression) -> Expression {
+        walk_convert(self, e, unit)
+    }
+
+    fn fold_list(&mut self, elements: Vec<Expression>) -> Expression {
+        walk_list(self, elements)
+    }
+
+    fn fold_index(&mut self, e: Expression, index: Expression) -> Expression {
+        walk_index(self, e, index)
+    }
+
+    fn fold_range(&mut self, start: Expression, end: Expression, step: Option<Expression>) -> Expression {
+        walk_range(self, start, end, step)
+    }
+}
+
+pub fn walk_expression<F: Folder + ?Sized>(folder: &mut F, e: Expression) -> Expression {
+    match e {
+        Expression::Integer(i) => folder.fold_integer(i),
+        Expression::Decimal(digits) => folder.fold_decimal(digits),
+        Expression::Imaginary(i) => folder.fold_imaginary(i),
+        Expression::Date(text) => folder.fold_date(text),
+        Expression::Time(text) => folder.fold_time(text),
+        Expression::Variable(name) => folder.fold_variable(name),
+        Expression::UnaryMinus(e) => folder.fold_unary_minus(*e),
+        Expression::SquareRoot(e) => folder.fold_square_root(*e),
+        Expression::Factorial(e) => folder.fold_factorial(*e),
+        Expression::Percent(e) => folder.fold_percent(*e),
+        Expression::PercentChange(e1, op, e2) =>
+            folder.fold_percent_change(*e1, op, *e2),
+        Expression::BinaryOperation(e1, op, e2) =>
+            folder.fold_binary_operation(*e1, op, *e2),
+        Expression::Call(name, arguments) => folder.fold_call(name, arguments),
+        Expression::Convert(e, unit) => folder.fold_convert(*e, *unit),
+        Expression::List(elements) => folder.fold_list(elements),
+        Expression::Index(e, index) => folder.fold_index(*e, *index),
+        Expression::Range(start, end, step) => folder.fold_range(*start, *end, step.map(|step| *step))
+    }
+}
+
+pub fn walk_unary_minus<F: Folder + ?Sized>(folder: &mut F, e: Expression) -> Expression {
+    Expression::UnaryMinus(Box::new(folder.fold_expression(e)))
+}
+
+pub fn walk_square_root<F: Folder + ?Sized>(folder: &mut F, e: Expression) -> Expression {
+    Expression::SquareRoot(Box::new(folder.fold_expression(e)))
+}
+
+pub fn walk_factorial<F: Folder + ?Sized>(folder: &mut F, e: Expression) -> Expression {
+    Expression::Factorial(Box::new(folder.fold_expression(e)))
+}
+
+pub fn walk_percent<F: Folder + ?Sized>(folder: &mut F, e: Expression) -> Expression {
+    Expression::Percent(Box::new(folder.fold_expression(e)))
+}
+
+pub fn walk_percent_change<F: Folder + ?Sized>(folder: &mut F,
+                                               e1: Expression,
+                                               op: Operation,
+                                               e2: Expression) -> Expression {
+    let new_e1 = folder.fold_expression(e1);
+    let new_e2 = folder.fold_expression(e2);
+    Expression::PercentChange(Box::new(new_e1), op, Box::new(new_e2))
+}
+
+pub fn walk_binary_operation<F: Folder + ?Sized>(folder: &mut F,
+                                                 e1: Expression,
+                                                 op: Operation,
+                                                 e2: Expression) -> Expression {
+    let new_e1 = folder.fold_expression(e1);
+    let new_e2 = folder.fold_expression(e2);
+    Expression::BinaryOperation(Box::new(new_e1), op, Box::new(new_e2))
+}
+
+pub fn walk_call<F: Folder + ?Sized>(folder: &mut F,
+                                     name: String,
+                                     arguments: Vec<Expression>) -> Expression {
+    let new_arguments = arguments.into_iter().map(|argument| folder.fold_expression(argument)).collect();
+    Expression::Call(name, new_arguments)
+}
+
+pub fn walk_convert<F: Folder + ?Sized>(folder: &mut F, e: Expression, unit: Expression) -> Expression {
+    let new_e = folder.fold_expression(e);
+    let new_unit = folder.fold_expression(unit);
+    Expression::Convert(Box::new(new_e), Box::new(new_unit))
+}
+
+pub fn walk_list<F: Folder + ?Sized>(folder: &mut F, elements: Vec<Expression>) -> Expression {
+    Expression::List(elements.into_iter().map(|element| folder.fold_expression(element)).collect())
+}
+
+pub fn walk_index<F: Folder + ?Sized>(folder: &mut F, e: Expression, index: Expression) -> Expression {
+    let new_e = folder.fold_expression(e);
+    let new_index = folder.fold_expression(index);
+    Expression::Index(Box::new(new_e), Box::new(new_index))
+}
+
+pub fn walk_range<F: Folder + ?Sized>(folder: &mut F,
+                                      start: Expression,
+                                      end: Expression,
+                                      step: Option<Expression>) -> Expression {
+    let new_start = folder.fold_expression(start);
+    let new_end = folder.fold_expression(end);
+    let new_step = step.map(|step| Box::new(folder.fold_expression(step)));
+    Expression::Range(Box::new(new_start), Box::new(new_end), new_step)
+}
+
+#[cfg(test)]
+mod test;
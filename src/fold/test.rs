@@ -0,0 +1,66 @@
+use parser::{Expression, Operation};
+use super::{Folder, walk_binary_operation};
+
+fn int(i: u32) -> Box<Expression> {
+    Box::new(Expression::Integer(i))
+}
+
+struct Identity;
+
+impl Folder for Identity {}
+
+#[test]
+fn default_fold_rebuilds_same_tree() {
+    let e = Expression::BinaryOperation(
+        int(1),
+        Operation::Div,
+        Box::new(Expression::UnaryMinus(int(2))));
+    let expected = Expression::BinaryOperation(
+        int(1),
+        Operation::Div,
+        Box::new(Expression::UnaryMinus(int(2))));
+    assert_eq!(expected, Identity.fold_expression(e));
+}
+
+struct PlusToTimes;
+
+impl Folder for PlusToTimes {
+    fn fold_binary_operation(&mut self,
+                             e1: Expression,
+                             op: Operation,
+                             e2: Expression) -> Expression {
+        let new_op = match op {
+            Operation::Plus => Operation::Times,
+            other => other
+        };
+        walk_binary_operation(self, e1, new_op, e2)
+    }
+}
+
+#[test]
+fn override_rewrites_nested_nodes() {
+    // (1 + 2) - (3 + 4)
+    let e = Expression::BinaryOperation(
+        Box::new(Expression::BinaryOperation(int(1), Operation::Plus, int(2))),
+        Operation::Minus,
+        Box::new(Expression::BinaryOperation(int(3), Operation::Plus, int(4))));
+    let expected = Expression::BinaryOperation(
+        Box::new(Expression::BinaryOperation(int(1), Operation::Times, int(2))),
+        Operation::Minus,
+        Box::new(Expression::BinaryOperation(int(3), Operation::Times, int(4))));
+    assert_eq!(expected, PlusToTimes.fold_expression(e));
+}
+
+struct Doubler;
+
+impl Folder for Doubler {
+    fn fold_integer(&mut self, i: u32) -> Expression {
+        Expression::Integer(i * 2)
+    }
+}
+
+#[test]
+fn leaves_are_replaced() {
+    let e = Expression::UnaryMinus(int(21));
+    assert_eq!(Expression::UnaryMinus(int(42)), Doubler.fold_expression(e));
+}
@@ -0,0 +1,58 @@
+use std::io::Read;
+
+use super::lexer::Lexer;
+use super::parser::Parser;
+use super::syntax::{Line, SyntaxTree};
+
+fn format_line(line: &Line) -> Result<String, String> {
+    let tokens = line.significant_tokens();
+    let comment = line.comment().map(|c| c.trim_end());
+
+    if tokens.is_empty() {
+        return Result::Ok(comment.unwrap_or("").to_string());
+    }
+
+    let expression = Parser::parse(&tokens)?;
+    match comment {
+        Some(c) => Result::Ok(format!("{} {}", expression, c)),
+        None => Result::Ok(expression.to_string())
+    }
+}
+
+// Pretty-prints a whole script.  Blank lines are collapsed to at most one
+// between statements and trimmed from either end.  Formatting the output
+// again yields the same output.
+pub fn format_source(source: &str) -> Result<String, String> {
+    format_tree(SyntaxTree::parse(source)?)
+}
+
+// as format_source, lexing the script as it is read
+pub fn format_reader<R: Read>(reader: R) -> Result<String, String> {
+    format_tree(SyntaxTree::read(Lexer::from_reader(reader))?)
+}
+
+fn format_tree(tree: SyntaxTree) -> Result<String, String> {
+    let mut lines: Vec<String> = vec![];
+
+    for (index, line) in tree.lines.iter().enumerate() {
+        let formatted = format_line(line)
+            .map_err(|e| format!("line {}: {}", index + 1, e))?;
+        let previous_blank = lines.last().is_none_or(|l| l.is_empty());
+        if !(formatted.is_empty() && previous_blank) {
+            lines.push(formatted);
+        }
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+
+    let mut result = String::new();
+    for line in lines {
+        result.push_str(&line);
+        result.push('\n');
+    }
+    Result::Ok(result)
+}
+
+#[cfg(test)]
+mod test;
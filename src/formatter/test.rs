@@ -0,0 +1,69 @@
+use super::{format_reader, format_source};
+
+fn assert_formats(source: &str, expected: &str) {
+    assert_eq!(Result::Ok(expected.to_string()), format_source(source));
+    // formatting is idempotent
+    assert_eq!(Result::Ok(expected.to_string()), format_source(expected));
+}
+
+#[test]
+fn spacing_is_canonical() {
+    assert_formats("1+2*   3", "1 + 2 * 3\n");
+}
+
+#[test]
+fn redundant_parens_are_removed() {
+    assert_formats("((1)) + (2 * 3)", "1 + 2 * 3\n");
+}
+
+#[test]
+fn needed_parens_are_kept() {
+    assert_formats("(1 + 2) * 3", "(1 + 2) * 3\n");
+    assert_formats("1 - (2 - 3)", "1 - (2 - 3)\n");
+    assert_formats("1 / (2 * 3)", "1 / (2 * 3)\n");
+    assert_formats("-(1 + 2)", "-(1 + 2)\n");
+}
+
+#[test]
+fn left_associated_parens_are_removed() {
+    assert_formats("(1 - 2) - 3", "1 - 2 - 3\n");
+}
+
+#[test]
+fn unary_minus_is_kept_tight() {
+    assert_formats("- 1 -  - 2", "-1 - -2\n");
+}
+
+#[test]
+fn comments_are_kept() {
+    assert_formats("# header  \n1+1    # two", "# header\n1 + 1 # two\n");
+}
+
+#[test]
+fn blank_lines_are_collapsed() {
+    assert_formats("\n\n1\n\n\n\n2\n\n", "1\n\n2\n");
+}
+
+#[test]
+fn empty_script_formats_to_empty() {
+    assert_formats("  \n", "");
+}
+
+#[test]
+fn variables_are_kept() {
+    assert_formats("(rate)*(x+1)", "rate * (x + 1)\n");
+}
+
+#[test]
+fn parse_error_reports_line() {
+    assert_eq!(Result::Err("line 2: Out of tokens".to_string()),
+               format_source("1\n(\n"));
+}
+
+#[test]
+fn scripts_format_as_they_are_read() {
+    assert_eq!(Result::Ok("1 + 2 # sum\n\nx * 3\n".to_string()),
+               format_reader(&b"1+2 # sum\n\n\n x*3"[..]));
+    assert_eq!(Result::Err("Invalid UTF-8 at byte 4".to_string()),
+               format_reader(&b"1\n2 \xff"[..]));
+}
@@ -1,77 +1,646 @@
 extern crate rand;
 
-use common::fuzzer::Fuzzer;
-use self::rand::{thread_rng, Rng};
-use parser::{Expression, Operation};
-use super::Interpreter;
+use common::corpus::Persist;
+use common::fuzzer::{replay_corpus, run_fuzzer, Fuzzer};
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
+use lexer::Lexer;
+use parser::{Expression, Operation, ParseOptions, Parser, FUNCTIONS};
+use parser::fuzzer::{rand_expression, shrink_paired, EXPRESSION_MAX_DEPTH, VARIABLE_NAMES};
+use value::{BigInteger, Complex, DateTime, Quantity, Rational, Value};
+use vm::Program;
+use super::{Bindings, ErrorKind, Interpreter, InternalInterpreterResult};
+use super::reference::{self, Failure, Number};
 
-const EXPRESSION_MAX_DEPTH: u32 = 10;
+// binds each variable, most often to a value at the edge of the range,
+// and leaves some unbound
+pub fn rand_bindings<R: Rng>(rng: &mut R) -> Bindings {
+    let mut bindings = Bindings::new();
+    for name in VARIABLE_NAMES.iter() {
+        let value = match rng.gen_range(0, 7) {
+            0 => continue,
+            1 => Value::Integer(0),
+            2 => Value::Integer(1),
+            3 => Value::Integer(i32::MIN),
+            4 => Value::Integer(i32::MAX),
+            5 => Value::Real(rng.gen_range(-1000.0, 1000.0)),
+            _ => Value::Integer(rng.gen())
+        };
+        bindings.insert(name.to_string(), value);
+    }
+    bindings
+}
+
+// Corpus entries put bindings and expectations in leading comment lines,
+// so every entry is also a script the calculator can read:
+//
+//   # x = 5
+//   # expected: Overflow
+//   x * 2147483647
+
+const HEADER_PREFIX: &str = "# ";
+
+// reals always have a point or an exponent, so they read back as reals.
+// Fractions are written as 3/2, complex numbers as two reals, as in
+// 1.0+-2.5i, quantities as a real and a unit, as in 3.0 km/h, dates in
+// ISO 8601, and lists as their elements in brackets, as in [1, 2.5].
+fn encode_value(value: &Value) -> String {
+    match value {
+        Value::Integer(i) => i.to_string(),
+        Value::Real(r) => format!("{:?}", r),
+        Value::Big(b) => b.to_string(),
+        Value::Rational(r) => r.to_string(),
+        Value::Complex(z) => format!("{:?}+{:?}i", z.re, z.im),
+        Value::Quantity(q) => format!("{:?} {}", q.amount, q.unit),
+        Value::DateTime(d) => d.to_string(),
+        Value::List(values) => format!("[{}]", values.iter().map(encode_value).collect::<Vec<_>>().join(", "))
+    }
+}
+
+// the elements of an encoded list, split at the commas between them
+fn decode_list(text: &str) -> Result<Value, String> {
+    let inner = text.strip_prefix('[').and_then(|t| t.strip_suffix(']'))
+        .ok_or_else(|| format!("Not a list: {}", text))?;
+    let mut values = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                values.push(decode_value(inner[start..i].trim())?);
+                start = i + 1;
+            },
+            _ => {}
+        }
+    }
+    if !inner.trim().is_empty() {
+        values.push(decode_value(inner[start..].trim())?);
+    }
+    Result::Ok(Value::List(values))
+}
+
+fn decode_real(text: &str) -> Result<f64, String> {
+    text.parse().map_err(|e| format!("{}: {}", text, e))
+}
+
+// the unit is read as source, which evaluates to one of it
+fn decode_quantity(amount: &str, unit: &str) -> Result<Value, String> {
+    let tokens = Lexer::tokenize(&mut unit.chars())?;
+    match Parser::parse(&tokens)?.evaluate().map_err(|e| e.message)? {
+        Value::Quantity(q) => Result::Ok(Value::Quantity(Quantity::new(decode_real(amount)?, q.unit))),
+        _ => Result::Err(format!("Not a unit: {}", unit))
+    }
+}
+
+fn decode_date(text: &str) -> Result<Value, String> {
+    let date = match text.split_once('T') {
+        Some((day, time)) => DateTime::parse_date(day)
+            .and_then(|day| DateTime::parse_time(time).and_then(|time| day.at(&time))),
+        None => DateTime::parse_date(text).or_else(|| DateTime::parse_time(text))
+    };
+    date.map(Value::DateTime).ok_or_else(|| format!("Not a date: {}", text))
+}
+
+// integers too big for an i32 are big integers
+fn decode_value(text: &str) -> Result<Value, String> {
+    if text.starts_with('[') {
+        return decode_list(text);
+    }
+    if let Some((amount, unit)) = text.split_once(' ') {
+        return decode_quantity(amount, unit);
+    }
+    if text.contains(':') || text.get(4..5) == Some("-") {
+        return decode_date(text);
+    }
+    if let Some(parts) = text.strip_suffix('i') {
+        let (re, im) = parts.split_at(parts.find("+").ok_or(format!("Not a complex number: {}", text))?);
+        return Result::Ok(Value::Complex(Complex::new(decode_real(re)?, decode_real(&im[1..])?)));
+    }
+    if let Some((numerator, denominator)) = text.split_once('/') {
+        let decode_integer = |text: &str| {
+            text.parse::<i128>().map(BigInteger::from).map_err(|e| format!("{}: {}", text, e))
+        };
+        let (numerator, denominator) = (decode_integer(numerator)?, decode_integer(denominator)?);
+        if denominator.is_zero() {
+            return Result::Err(format!("Not a fraction: {}", text));
+        }
+        return Result::Ok(Value::from(Rational::new(&numerator, &denominator)));
+    }
+    match text.parse::<i128>() {
+        Ok(i) => Result::Ok(Value::from(BigInteger::from(i))),
+        Err(_) => decode_real(text).map(Value::Real)
+    }
+}
+
+fn encode_bindings(bindings: &Bindings) -> String {
+    let mut names: Vec<&String> = bindings.keys().collect();
+    names.sort();
+    names.iter()
+        .map(|name| format!("{}{} = {}\n", HEADER_PREFIX, name, encode_value(&bindings[*name])))
+        .collect()
+}
+
+// splits off the header lines, returning them and the rest of the text
+fn split_header(text: &str) -> (Vec<&str>, &str) {
+    let mut header = vec![];
+    let mut rest = text;
+    while rest.starts_with(HEADER_PREFIX) {
+        let end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        header.push(rest[HEADER_PREFIX.len()..end].trim());
+        rest = &rest[end..];
+    }
+    (header, rest)
+}
+
+fn decode_binding(line: &str, bindings: &mut Bindings) -> Result<(), String> {
+    let parts: Vec<&str> = line.splitn(2, '=').map(|part| part.trim()).collect();
+    match parts.as_slice() {
+        [name, value] => {
+            let parsed = decode_value(value).map_err(|e| format!("{}: {}", line, e))?;
+            bindings.insert(name.to_string(), parsed);
+            Result::Ok(())
+        },
+        _ => Result::Err(format!("Not a binding: {}", line))
+    }
+}
+
+impl Persist for (Expression, Bindings) {
+    fn encode(&self) -> String {
+        format!("{}{}\n", encode_bindings(&self.1), self.0)
+    }
+
+    fn decode(text: &str) -> Result<(Expression, Bindings), String> {
+        let (header, rest) = split_header(text);
+        let mut bindings = Bindings::new();
+        for line in header {
+            decode_binding(line, &mut bindings)?;
+        }
+        Result::Ok((Expression::decode(rest)?, bindings))
+    }
+}
+
+const EXPECTED_PREFIX: &str = "expected:";
+
+fn encode_expected(expected: &Result<Value, Failure>) -> String {
+    match expected {
+        Ok(v) => encode_value(v),
+        Err(Failure::Error(kind)) => format!("{:?}", kind),
+        Err(Failure::Unchecked) => panic!("an unchecked case is never kept")
+    }
+}
+
+fn decode_expected(text: &str) -> Result<Result<Value, Failure>, String> {
+    match text {
+        "Overflow" => Result::Ok(Result::Err(Failure::Error(ErrorKind::Overflow))),
+        "DivisionByZero" => Result::Ok(Result::Err(Failure::Error(ErrorKind::DivisionByZero))),
+        "UnboundVariable" => Result::Ok(Result::Err(Failure::Error(ErrorKind::UnboundVariable))),
+        "Undefined" => Result::Ok(Result::Err(Failure::Error(ErrorKind::Undefined))),
+        "Units" => Result::Ok(Result::Err(Failure::Error(ErrorKind::Units))),
+        other => decode_value(other)
+            .map(Result::Ok)
+            .map_err(|_| format!("Not an expected result: {}", other))
+    }
+}
+
+// the source is kept exactly, whitespace and all
+impl Persist for (String, Bindings, Result<Value, Failure>) {
+    fn encode(&self) -> String {
+        format!("{}{}{} {}\n{}",
+                encode_bindings(&self.1),
+                HEADER_PREFIX,
+                EXPECTED_PREFIX,
+                encode_expected(&self.2),
+                self.0)
+    }
+
+    fn decode(text: &str) -> Result<(String, Bindings, Result<Value, Failure>), String> {
+        let (header, rest) = split_header(text);
+        let mut bindings = Bindings::new();
+        let mut expected = Option::None;
+        for line in header {
+            match line.strip_prefix(EXPECTED_PREFIX) {
+                Some(value) => expected = Some(decode_expected(value.trim())?),
+                None => decode_binding(line, &mut bindings)?
+            }
+        }
+        match expected {
+            Some(e) => Result::Ok((rest.to_string(), bindings, e)),
+            None => Result::Err("Missing expected result".to_string())
+        }
+    }
+}
+
+fn kind_of(result: InternalInterpreterResult) -> Result<Value, Failure> {
+    result.map_err(|error| Failure::Error(error.kind))
+}
 
+// differential testing: every backend must agree with the reference
+// evaluator on the value, or on the kind of error
 struct InterpreterFuzzer<R: Rng> {
     random: R
 }
 
-impl<R: Rng> InterpreterFuzzer<R> {
-    fn rand_integer_expression(&mut self) -> Expression {
-        Expression::Integer(self.random.gen())
+impl<ActualR: Rng + SeedableRng> Fuzzer for InterpreterFuzzer<ActualR> {
+    type Item = (Expression, Bindings);
+    type R = ActualR;
+
+    fn new(random: ActualR) -> InterpreterFuzzer<ActualR> {
+        InterpreterFuzzer { random }
     }
 
-    fn rand_operation(&mut self) -> Operation {
-        match self.random.gen_range(0, 4) {
-            0 => Operation::Plus,
-            1 => Operation::Minus,
-            2 => Operation::Times,
-            3 => Operation::Div,
-            _ => panic!("Operation out of range")
+    fn gen_one(&mut self) -> (Expression, Bindings) {
+        let e = rand_expression(self.random_generator(), EXPRESSION_MAX_DEPTH);
+        (e, rand_bindings(self.random_generator()))
+    }
+
+    // where the reference cannot say, the backends must still agree
+    fn run_test(&mut self, (item, bindings): (Expression, Bindings)) {
+        let expected = reference::evaluate(&item, &bindings);
+        let interpreted = kind_of(item.evaluate_in(&bindings));
+        if expected != Result::Err(Failure::Unchecked) {
+            assert_eq!(expected, interpreted, "interpreter on {} with {:?}", item, bindings);
         }
+        assert_eq!(interpreted, kind_of(Program::compile(&item).evaluate_in(&bindings)),
+                   "bytecode on {} with {:?}", item, bindings);
+    }
+
+    fn corpus_name(&self) -> Option<&'static str> {
+        Some("interpreter")
+    }
+
+    fn shrink(&self, item: &(Expression, Bindings)) -> Vec<(Expression, Bindings)> {
+        shrink_paired(item)
     }
     
-    fn rand_expression(&mut self, depth_remaining: u32) -> Expression {
-        if depth_remaining == 0 {
-            self.rand_integer_expression()
-        } else {
-            match self.random.gen_range(0, 3) {
-                0 => self.rand_integer_expression(),
-                1 => {
-                    let e = self.rand_expression(depth_remaining - 1);
-                    Expression::UnaryMinus(Box::new(e))
+    fn random_generator(&mut self) -> &mut ActualR {
+        &mut self.random
+    }
+}
+
+#[test]
+fn run_interpreter_fuzzer() {
+    run_fuzzer::<InterpreterFuzzer<StdRng>>();
+}
+
+#[test]
+fn replay_interpreter_corpus() {
+    replay_corpus::<InterpreterFuzzer<StdRng>>();
+}
+
+const SOURCE_MAX_DEPTH: u32 = 6;
+
+type Reference = Result<Number, Failure>;
+
+const COMPARISONS: [(&str, Operation); 10] = [
+    ("<", Operation::Less),
+    ("<=", Operation::LessEqual),
+    ("≤", Operation::LessEqual),
+    (">", Operation::Greater),
+    (">=", Operation::GreaterEqual),
+    ("≥", Operation::GreaterEqual),
+    ("==", Operation::Equal),
+    ("=", Operation::Equal),
+    ("!=", Operation::NotEqual),
+    ("≠", Operation::NotEqual)
+];
+
+const ADDITIVE: [(&str, Operation); 3] = [
+    ("+", Operation::Plus),
+    ("-", Operation::Minus),
+    ("\u{2212}", Operation::Minus)
+];
+
+const MULTIPLICATIVE: [(&str, Operation); 6] = [
+    ("*", Operation::Times),
+    ("×", Operation::Times),
+    ("·", Operation::Times),
+    ("\u{22c5}", Operation::Times),
+    ("/", Operation::Div),
+    ("÷", Operation::Div)
+];
+
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+// Generates source text straight from the grammar, computing what it
+// should evaluate to as it goes:
+//
+//   comparison     ::= additive (('<' | '<=' | '>' | ...) additive)*
+//   additive       ::= multiplicative (('+' | '-') multiplicative)*
+//   multiplicative ::= implicit (('*' | '/') implicit)*
+//   implicit       ::= unary factor*
+//   factor         ::= '√' unary | (variable | parenthesized) postfixes ('^' unary)?
+//   unary          ::= ('-' | '√') unary | power
+//   power          ::= postfix ('^' unary)?
+//   postfix        ::= primary postfixes
+//   postfixes      ::= (superscript | '!' | '%')*
+//   primary        ::= number | variable | parenthesized | call
+//   number         ::= digits ('.' digits | 'i' | 'j')?
+//   call           ::= function '(' comparison (',' comparison)* ')'
+//   parenthesized  ::= '(' comparison ')'
+//
+// A variable juxtaposed as a factor is always preceded by a space, so it
+// cannot run into an identifier before it.  Percentages are parsed as
+// plain hundredths, as a generator this simple cannot tell when one would
+// be a percent change; the parser and printer tests cover those.
+// Operators are spelled in ASCII or Unicode at random.  Whitespace,
+// redundant parentheses and literal sizes (including leading zeros) are
+// all random too.
+struct SourceFuzzer<R: Rng> {
+    random: R,
+    bindings: Bindings
+}
+
+impl<R: Rng> SourceFuzzer<R> {
+    fn whitespace(&mut self, out: &mut String) {
+        while self.random.gen_range(0, 3) == 0 {
+            out.push(match self.random.gen_range(0, 4) {
+                0 => ' ',
+                1 => '\t',
+                2 => '\n',
+                _ => '\r'
+            });
+        }
+    }
+
+    fn integer(&mut self, out: &mut String) -> Reference {
+        let start = out.len();
+        for _ in 0..self.random.gen_range(0, 3) {
+            out.push('0');
+        }
+        let shift = self.random.gen_range(0, 32);
+        let i = self.random.gen::<u32>() >> shift;
+        out.push_str(&i.to_string());
+        if self.random.gen_range(0, 16) == 0 {
+            out.push('.');
+            for _ in 0..self.random.gen_range(1, 4) {
+                out.push(self.random.gen_range(b'0', b'9' + 1) as char);
+            }
+            return reference::decimal(&out[start..]);
+        }
+        // the reference cannot check imaginary numbers, so they are rare
+        if self.random.gen_range(0, 64) == 0 {
+            out.push(if self.random.gen() { 'i' } else { 'j' });
+            return Result::Err(Failure::Unchecked);
+        }
+        reference::literal(i)
+    }
+
+    fn variable(&mut self, out: &mut String) -> Reference {
+        let name = VARIABLE_NAMES[self.random.gen_range(0, VARIABLE_NAMES.len())];
+        out.push_str(name);
+        reference::variable(name, &self.bindings)
+    }
+
+    fn parenthesized(&mut self, out: &mut String, depth: u32) -> Reference {
+        out.push('(');
+        self.whitespace(out);
+        let value = self.comparison(out, depth - 1);
+        self.whitespace(out);
+        out.push(')');
+        value
+    }
+
+    // arguments are evaluated left to right, and the first error wins.  A
+    // derivative evaluates none of them and solve may be of an equation,
+    // so neither is checked.
+    fn call(&mut self, out: &mut String, depth: u32) -> Reference {
+        let (name, least, most) = FUNCTIONS[self.random.gen_range(0, FUNCTIONS.len())];
+        out.push_str(name);
+        out.push('(');
+        let mut arguments = Result::Ok(vec![]);
+        for i in 0..self.random.gen_range(least, most + 1) {
+            if i > 0 {
+                out.push(',');
+            }
+            self.whitespace(out);
+            let argument = self.comparison(out, depth - 1);
+            self.whitespace(out);
+            arguments = arguments.and_then(|mut values: Vec<Number>| argument.map(|v| {
+                values.push(v);
+                values
+            }));
+        }
+        out.push(')');
+        if name == "diff" || name == "solve" {
+            return Result::Err(Failure::Unchecked);
+        }
+        arguments.and_then(|values| reference::call(name, values))
+    }
+
+    fn primary(&mut self, out: &mut String, depth: u32) -> Reference {
+        match self.random.gen_range(0, if depth == 0 { 2 } else { 5 }) {
+            0 => self.variable(out),
+            1 => self.integer(out),
+            2 if self.random.gen() => self.call(out, depth),
+            _ => self.parenthesized(out, depth)
+        }
+    }
+
+    fn postfixes(&mut self, out: &mut String, mut value: Reference) -> Reference {
+        while self.random.gen_range(0, 8) == 0 {
+            value = match self.random.gen_range(0, 4) {
+                0 => {
+                    out.push('!');
+                    value.and_then(reference::factorial)
                 },
-                2 => {
-                    let new_bound = depth_remaining - 1;
-                    let left = self.rand_expression(new_bound);
-                    let op = self.rand_operation();
-                    let right = self.rand_expression(new_bound);
-                    Expression::BinaryOperation(Box::new(left),
-                                                op,
-                                                Box::new(right))
+                1 => {
+                    out.push('%');
+                    value.and_then(reference::percent)
                 },
-                _ => panic!("Expression out of range")
+                _ => {
+                    // x²³ is x to the 23rd, not (x²)³
+                    if out.ends_with(|c| SUPERSCRIPT_DIGITS.contains(&c)) {
+                        out.push(' ');
+                    }
+                    let exponent = self.random.gen_range(0, 10);
+                    out.push(SUPERSCRIPT_DIGITS[exponent as usize]);
+                    value.and_then(|v| reference::operate(&Operation::Power, v, Number::Exact(exponent)))
+                }
+            };
+        }
+        value
+    }
+
+    fn postfix(&mut self, out: &mut String, depth: u32) -> Reference {
+        let value = self.primary(out, depth);
+        self.postfixes(out, value)
+    }
+
+    fn power(&mut self, out: &mut String, depth: u32) -> Reference {
+        let value = self.postfix(out, depth);
+        self.exponent(out, depth, value)
+    }
+
+    fn exponent(&mut self, out: &mut String, depth: u32, value: Reference) -> Reference {
+        if depth > 0 && self.random.gen_range(0, 8) == 0 {
+            self.whitespace(out);
+            out.push('^');
+            self.whitespace(out);
+            let exponent = self.unary(out, depth - 1);
+            value.and_then(|v1| exponent.and_then(|v2| reference::operate(&Operation::Power, v1, v2)))
+        } else {
+            value
+        }
+    }
+
+    fn unary(&mut self, out: &mut String, depth: u32) -> Reference {
+        match self.random.gen_range(0, 8) {
+            0 | 1 => {
+                out.push(if self.random.gen() { '-' } else { '\u{2212}' });
+                self.whitespace(out);
+                let value = self.unary(out, depth);
+                value.and_then(reference::negate)
+            },
+            2 => {
+                out.push('√');
+                self.whitespace(out);
+                let value = self.unary(out, depth);
+                value.and_then(reference::square_root)
+            },
+            _ => self.power(out, depth)
+        }
+    }
+
+    // operands are evaluated left to right, and the first error wins
+    fn chain(&mut self,
+             out: &mut String,
+             depth: u32,
+             ops: &[(&str, Operation)],
+             operand: fn(&mut SourceFuzzer<R>, &mut String, u32) -> Reference) -> Reference {
+        let mut value = operand(self, out, depth);
+        while depth > 0 && self.random.gen_range(0, 3) == 0 {
+            let (symbol, op) = ops[self.random.gen_range(0, ops.len())];
+            self.whitespace(out);
+            // 3!==6 would read as 3 != = 6
+            if out.ends_with('!') && symbol.starts_with('=') {
+                out.push(' ');
             }
+            out.push_str(symbol);
+            self.whitespace(out);
+            let right = operand(self, out, depth - 1);
+            value = value.and_then(|v1| right.and_then(|v2| reference::operate(&op, v1, v2)));
+        }
+        value
+    }
+
+    fn factor(&mut self, out: &mut String, depth: u32) -> Reference {
+        self.whitespace(out);
+        if self.random.gen_range(0, 3) == 0 {
+            out.push('√');
+            self.whitespace(out);
+            let value = self.unary(out, depth);
+            value.and_then(reference::square_root)
+        } else {
+            let value = if depth == 0 || self.random.gen() {
+                out.push(' ');
+                self.variable(out)
+            } else {
+                self.parenthesized(out, depth)
+            };
+            let value = self.postfixes(out, value);
+            self.exponent(out, depth, value)
+        }
+    }
+
+    fn implicit(&mut self, out: &mut String, depth: u32) -> Reference {
+        let mut value = self.unary(out, depth);
+        while depth > 0 && self.random.gen_range(0, 6) == 0 {
+            let right = self.factor(out, depth - 1);
+            value = value.and_then(|v1| right.and_then(|v2| reference::operate(&Operation::Times, v1, v2)));
+        }
+        value
+    }
+
+    fn multiplicative(&mut self, out: &mut String, depth: u32) -> Reference {
+        self.chain(out, depth, &MULTIPLICATIVE, SourceFuzzer::implicit)
+    }
+
+    fn additive(&mut self, out: &mut String, depth: u32) -> Reference {
+        self.chain(out, depth, &ADDITIVE, SourceFuzzer::multiplicative)
+    }
+
+    fn comparison(&mut self, out: &mut String, depth: u32) -> Reference {
+        // comparisons are rare, or almost every result would be 0 or 1
+        if self.random.gen_range(0, 4) == 0 {
+            self.chain(out, depth, &COMPARISONS, SourceFuzzer::additive)
+        } else {
+            self.additive(out, depth)
         }
     }
 }
 
-impl<ActualR: Rng> Fuzzer for InterpreterFuzzer<ActualR> {
-    type Item = Expression;
+impl<ActualR: Rng + SeedableRng> Fuzzer for SourceFuzzer<ActualR> {
+    type Item = (String, Bindings, Result<Value, Failure>);
     type R = ActualR;
 
-    fn gen_one(&mut self) -> Expression {
-        self.rand_expression(EXPRESSION_MAX_DEPTH)
+    fn new(random: ActualR) -> SourceFuzzer<ActualR> {
+        SourceFuzzer { random, bindings: Bindings::new() }
     }
 
-    fn run_test(&mut self, item: Expression) {
-        let _ = item.evaluate();
+    fn gen_one(&mut self) -> (String, Bindings, Result<Value, Failure>) {
+        self.bindings = rand_bindings(self.random_generator());
+        let mut source = String::new();
+        self.whitespace(&mut source);
+        let value = self.comparison(&mut source, SOURCE_MAX_DEPTH);
+        self.whitespace(&mut source);
+        (source, self.bindings.clone(), value.map(Number::to_value))
     }
-    
+
+    fn corpus_name(&self) -> Option<&'static str> {
+        Some("source")
+    }
+
+    fn run_test(&mut self, (source, bindings, expected): (String, Bindings, Result<Value, Failure>)) {
+        if expected == Result::Err(Failure::Unchecked) {
+            return;
+        }
+        let tokens = Lexer::tokenize(&mut source.chars())
+            .unwrap_or_else(|e| panic!("{:?} did not lex: {}", source, e));
+        let options = ParseOptions {
+            relative_percent: false,
+            ..ParseOptions::default()
+        };
+        let expression = Parser::parse_with(&tokens, options)
+            .unwrap_or_else(|e| panic!("{:?} did not parse: {}", source, e));
+        assert_eq!(expected, kind_of(expression.evaluate_in(&bindings)),
+                   "{:?} parsed as {}", source, expression);
+    }
+
     fn random_generator(&mut self) -> &mut ActualR {
         &mut self.random
     }
 }
 
 #[test]
-fn run_interpreter_fuzzer() {
-    let mut fuzzer = InterpreterFuzzer {
-        random: thread_rng()
-    };
-    fuzzer.run_tests();
+fn run_source_fuzzer() {
+    run_fuzzer::<SourceFuzzer<StdRng>>();
+}
+
+#[test]
+fn replay_source_corpus() {
+    replay_corpus::<SourceFuzzer<StdRng>>();
+}
+
+#[test]
+fn corpus_entries_roundtrip() {
+    let mut bindings = Bindings::new();
+    bindings.insert("y".to_string(), Value::Real(-3.0));
+    bindings.insert("x".to_string(), Value::Integer(i32::MIN));
+    let pair = (Expression::Variable("x".to_string()), bindings.clone());
+    assert_eq!("# x = -2147483648\n# y = -3.0\nx\n", pair.encode());
+    assert_eq!(Result::Ok(pair.clone()), <(Expression, Bindings)>::decode(&pair.encode()));
+
+    let source = (" \n(1)\t".to_string(), bindings.clone(), Result::Err(Failure::Error(ErrorKind::Overflow)));
+    assert_eq!(Result::Ok(source.clone()),
+               <(String, Bindings, Result<Value, Failure>)>::decode(&source.encode()));
+
+    let real = ("√2".to_string(), bindings.clone(), Result::Ok(Value::Real(2f64.sqrt())));
+    assert_eq!(Result::Ok(real.clone()),
+               <(String, Bindings, Result<Value, Failure>)>::decode(&real.encode()));
+
+    let fraction = Value::from(Rational::new(&BigInteger::from(-3), &BigInteger::from(2)));
+    let inverse = ("inverse([[2]])".to_string(), bindings, Result::Ok(Value::List(vec![fraction])));
+    assert_eq!(Result::Ok(inverse.clone()),
+               <(String, Bindings, Result<Value, Failure>)>::decode(&inverse.encode()));
 }
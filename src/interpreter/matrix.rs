@@ -0,0 +1,369 @@
+// Linear algebra on lists of lists.  A matrix is a list of rows of the
+// same length, and a list that holds no lists is a vector, which stands
+// as a row on the left of a product and as a column on the right.
+// Matrices of whole numbers and fractions are reduced exactly, by
+// fraction-free elimination, so their determinants, ranks, inverses and
+// solutions are exact, as fractions where they are not whole; other
+// entries are f64.
+
+use parser::Operation;
+use value::{BigInteger, Rational, Value};
+use super::{big, rational, real, units_of, Error, InternalInterpreterResult, OperationEvaluator};
+
+pub const MATRIX_FUNCTIONS: [&str; 5] = ["transpose", "det", "inverse", "rank", "solve"];
+
+enum Shape {
+    Vector(Vec<Value>),
+    Matrix(Vec<Vec<Value>>)
+}
+
+impl Shape {
+    fn describe(&self) -> String {
+        match self {
+            Shape::Vector(values) => format!("a vector of length {}", values.len()),
+            Shape::Matrix(rows) => format!("a {}×{} matrix", rows.len(), rows[0].len())
+        }
+    }
+}
+
+// what a list is, or an error saying what cannot be done with v, like
+// take det of
+fn shape(v: Value, action: &str) -> Result<Shape, Error> {
+    let values = match v {
+        Value::List(values) => values,
+        other => return Result::Err(Error::units(format!("Cannot {} {}", action, units_of(&other))))
+    };
+    if !values.iter().any(|v| matches!(v, Value::List(_))) {
+        return Result::Ok(Shape::Vector(values));
+    }
+    let mut rows: Vec<Vec<Value>> = Vec::with_capacity(values.len());
+    for v in values {
+        match v {
+            Value::List(row) => {
+                if !rows.is_empty() && row.len() != rows[0].len() {
+                    return Result::Err(Error::units(format!("Cannot {} rows of lengths {} and {}",
+                                                            action, rows[0].len(), row.len())));
+                }
+                rows.push(row)
+            },
+            _ => return Result::Err(Error::units(format!("Cannot {} a mix of lists and numbers", action)))
+        }
+    }
+    Result::Ok(Shape::Matrix(rows))
+}
+
+fn column(rows: &[Vec<Value>], j: usize) -> Vec<Value> {
+    rows.iter().map(|row| row[j].clone()).collect()
+}
+
+fn transposed(rows: &[Vec<Value>]) -> Vec<Vec<Value>> {
+    (0..rows[0].len()).map(|j| column(rows, j)).collect()
+}
+
+fn to_value(rows: Vec<Vec<Value>>) -> Value {
+    Value::List(rows.into_iter().map(Value::List).collect())
+}
+
+fn dot(v1: &[Value], v2: &[Value]) -> InternalInterpreterResult {
+    let mut products = v1.iter().zip(v2).map(|(e1, e2)| Operation::Times.operation(e1.clone(), e2.clone()));
+    match products.next() {
+        Some(first) => products.fold(first, |total, product| Operation::Plus.operation(total?, product?)),
+        None => Result::Ok(Value::Integer(0))
+    }
+}
+
+fn dots(rows: &[Vec<Value>], v: &[Value]) -> Result<Vec<Value>, Error> {
+    rows.iter().map(|row| dot(row, v)).collect()
+}
+
+// The matrix product, which works on entries of any kind that multiply
+// and add.  Two vectors give their dot product.
+pub fn product(v1: Value, v2: Value) -> InternalInterpreterResult {
+    let action = "take the matrix product of";
+    let (s1, s2) = (shape(v1, action)?, shape(v2, action)?);
+    let fits = match (&s1, &s2) {
+        (Shape::Vector(a), Shape::Vector(b)) => a.len() == b.len(),
+        (Shape::Vector(a), Shape::Matrix(b)) => a.len() == b.len(),
+        (Shape::Matrix(a), Shape::Vector(b)) => a[0].len() == b.len(),
+        (Shape::Matrix(a), Shape::Matrix(b)) => a[0].len() == b.len()
+    };
+    if !fits {
+        return Result::Err(Error::units(format!("Cannot multiply {} by {}", s1.describe(), s2.describe())));
+    }
+    match (s1, s2) {
+        (Shape::Vector(a), Shape::Vector(b)) => dot(&a, &b),
+        (Shape::Vector(a), Shape::Matrix(b)) => dots(&transposed(&b), &a).map(Value::List),
+        (Shape::Matrix(a), Shape::Vector(b)) => dots(&a, &b).map(Value::List),
+        (Shape::Matrix(a), Shape::Matrix(b)) => {
+            let columns = transposed(&b);
+            a.iter()
+                .map(|row| dots(&columns, row))
+                .collect::<Result<Vec<Vec<Value>>, Error>>()
+                .map(to_value)
+        }
+    }
+}
+
+// Exact entries are whole numbers, each row multiplied through by its
+// denominators, which leaves the rank and any solution as they were but
+// multiplies the determinant by the scale.
+enum Entries {
+    Exact(Vec<Vec<BigInteger>>, BigInteger),
+    Real(Vec<Vec<f64>>)
+}
+
+// a row of fractions multiplied by the least common multiple of their
+// denominators, and that multiple
+fn whole_row(row: &[Value]) -> (Vec<BigInteger>, BigInteger) {
+    let fractions: Vec<Rational> = row.iter().map(|v| v.to_rational().unwrap()).collect();
+    // scale / d in lowest terms has what d adds to the multiple below
+    let scale = fractions.iter().fold(BigInteger::from(1), |scale, r| {
+        &scale * Rational::new(&scale, r.denominator()).denominator()
+    });
+    let scaled = fractions.iter().map(|r| &(r.numerator() * &scale) / r.denominator()).collect();
+    (scaled, scale)
+}
+
+// the entries as numbers, exact if all of them are whole numbers or
+// fractions
+fn numbers(rows: Vec<Vec<Value>>, name: &str) -> Result<Entries, Error> {
+    let mut exact = true;
+    for v in rows.iter().flatten() {
+        match v {
+            Value::Integer(_) | Value::Big(_) | Value::Rational(_) => {},
+            Value::Real(_) => exact = false,
+            Value::Complex(_) => return Result::Err(Error::units(format!("Cannot take {} of a complex matrix", name))),
+            other =>
+                return Result::Err(Error::units(format!("Cannot take {} of a matrix of {}", name, units_of(other))))
+        }
+    }
+    Result::Ok(if exact {
+        let (rows, scales): (Vec<Vec<BigInteger>>, Vec<BigInteger>) = rows.iter().map(|row| whole_row(row)).unzip();
+        let scale = scales.iter().fold(BigInteger::from(1), |product, scale| &product * scale);
+        Entries::Exact(rows, scale)
+    } else {
+        Entries::Real(rows.iter().map(|row| row.iter().map(Value::to_f64).collect()).collect())
+    })
+}
+
+// A matrix reduced in its first columns by Gauss-Jordan elimination.
+// Each pivot row i has a pivot in column pivots[i] and zeros above and
+// below it, and the rows were swapped an odd number of times if negated.
+struct Reduced<T> {
+    rows: Vec<Vec<T>>,
+    pivots: Vec<usize>,
+    negated: bool
+}
+
+// Bareiss's fraction-free elimination, dividing each new entry by the
+// previous pivot, which it is always a multiple of.  Every pivot ends up
+// as the same minor, the determinant when the matrix is square.
+fn reduce_exact(mut rows: Vec<Vec<BigInteger>>, columns: usize) -> Reduced<BigInteger> {
+    let mut pivots = vec![];
+    let mut negated = false;
+    let mut previous = BigInteger::from(1);
+    for c in 0..columns {
+        let r = pivots.len();
+        let found = match (r..rows.len()).find(|&i| !rows[i][c].is_zero()) {
+            Some(i) => i,
+            None => continue
+        };
+        if found != r {
+            rows.swap(found, r);
+            negated = !negated;
+        }
+        let pivot_row = rows[r].clone();
+        let pivot = &pivot_row[c];
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i == r {
+                continue;
+            }
+            let factor = row[c].clone();
+            for (entry, above) in row.iter_mut().zip(&pivot_row) {
+                *entry = &(&(pivot * &*entry) - &(&factor * above)) / &previous;
+            }
+        }
+        previous = pivot.clone();
+        pivots.push(c);
+    }
+    Reduced { rows, pivots, negated }
+}
+
+// elimination with partial pivoting, treating what rounding leaves of a
+// cancelled column as zero
+fn reduce_real(mut rows: Vec<Vec<f64>>, columns: usize) -> Reduced<f64> {
+    let largest = rows.iter().flat_map(|row| &row[..columns]).fold(0.0, |m: f64, x| m.max(x.abs()));
+    let tolerance = largest * f64::EPSILON * rows.len().max(columns) as f64;
+    let mut pivots = vec![];
+    let mut negated = false;
+    for c in 0..columns {
+        let r = pivots.len();
+        if r == rows.len() {
+            break;
+        }
+        let best = (r..rows.len()).max_by(|&i, &j| rows[i][c].abs().total_cmp(&rows[j][c].abs())).unwrap();
+        if rows[best][c].abs() <= tolerance {
+            continue;
+        }
+        if best != r {
+            rows.swap(best, r);
+            negated = !negated;
+        }
+        let pivot_row = rows[r].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i == r {
+                continue;
+            }
+            let factor = row[c] / pivot_row[c];
+            for (entry, above) in row.iter_mut().zip(&pivot_row) {
+                *entry -= factor * above;
+            }
+            row[c] = 0.0;
+        }
+        pivots.push(c);
+    }
+    Reduced { rows, pivots, negated }
+}
+
+// n / d, a fraction unless it is whole
+fn exact_quotient(n: &BigInteger, d: &BigInteger) -> InternalInterpreterResult {
+    match n.divide(d) {
+        Some((q, r)) if r.is_zero() => big(q),
+        _ => rational(Rational::new(n, d))
+    }
+}
+
+// the rank of the first columns, the determinant if they are square, and
+// each pivot row's entries after them divided by its pivot, which solve
+// for those columns when the rank is full
+struct Solution {
+    rank: usize,
+    determinant: Value,
+    quotients: Vec<Vec<Value>>
+}
+
+fn eliminate(entries: Entries, columns: usize) -> Result<Solution, Error> {
+    match entries {
+        Entries::Exact(rows, scale) => {
+            let reduced = reduce_exact(rows, columns);
+            let rank = reduced.pivots.len();
+            let mut determinant = match reduced.pivots.last() {
+                Some(&c) if rank == columns => reduced.rows[rank - 1][c].clone(),
+                _ => BigInteger::from(0)
+            };
+            if reduced.negated {
+                determinant = -&determinant;
+            }
+            let mut quotients = vec![];
+            for (row, &c) in reduced.rows.iter().zip(&reduced.pivots) {
+                quotients.push(row[columns..].iter()
+                    .map(|n| exact_quotient(n, &row[c]))
+                    .collect::<Result<Vec<Value>, Error>>()?);
+            }
+            Result::Ok(Solution { rank, determinant: exact_quotient(&determinant, &scale)?, quotients })
+        },
+        Entries::Real(rows) => {
+            let reduced = reduce_real(rows, columns);
+            let rank = reduced.pivots.len();
+            let mut determinant = if rank == columns { 1.0 } else { 0.0 };
+            let mut quotients = vec![];
+            for (row, &c) in reduced.rows.iter().zip(&reduced.pivots) {
+                determinant *= row[c];
+                // adding zero turns -0 into 0
+                quotients.push(row[columns..].iter()
+                    .map(|x| real(x / row[c] + 0.0))
+                    .collect::<Result<Vec<Value>, Error>>()?);
+            }
+            if reduced.negated {
+                determinant = -determinant;
+            }
+            Result::Ok(Solution { rank, determinant: real(determinant + 0.0)?, quotients })
+        }
+    }
+}
+
+fn matrix(v: Value, name: &str) -> Result<Vec<Vec<Value>>, Error> {
+    match shape(v, &format!("take {} of", name))? {
+        Shape::Matrix(rows) => Result::Ok(rows),
+        vector => Result::Err(Error::units(format!("Cannot take {} of {}", name, vector.describe())))
+    }
+}
+
+fn square(v: Value, name: &str) -> Result<Vec<Vec<Value>>, Error> {
+    let rows = matrix(v, name)?;
+    if rows.len() != rows[0].len() {
+        return Result::Err(Error::units(format!("Cannot take {} of {}", name, Shape::Matrix(rows).describe())));
+    }
+    Result::Ok(rows)
+}
+
+fn transpose(v: Value) -> InternalInterpreterResult {
+    match shape(v, "take transpose of")? {
+        Shape::Vector(values) => Result::Ok(to_value(values.into_iter().map(|v| vec![v]).collect())),
+        Shape::Matrix(rows) => Result::Ok(to_value(transposed(&rows)))
+    }
+}
+
+fn determinant(v: Value) -> InternalInterpreterResult {
+    let rows = square(v, "det")?;
+    let n = rows.len();
+    Result::Ok(eliminate(numbers(rows, "det")?, n)?.determinant)
+}
+
+fn rank(v: Value) -> InternalInterpreterResult {
+    let rows = matrix(v, "rank")?;
+    let columns = rows[0].len();
+    Result::Ok(Value::Integer(eliminate(numbers(rows, "rank")?, columns)?.rank as i32))
+}
+
+fn inverse(v: Value) -> InternalInterpreterResult {
+    let mut rows = square(v, "inverse")?;
+    let n = rows.len();
+    for (i, row) in rows.iter_mut().enumerate() {
+        row.extend((0..n).map(|j| Value::Integer(if i == j { 1 } else { 0 })));
+    }
+    let solution = eliminate(numbers(rows, "inverse")?, n)?;
+    if solution.rank < n {
+        return Result::Err(Error::undefined("Cannot invert a singular matrix"));
+    }
+    Result::Ok(to_value(solution.quotients))
+}
+
+// x such that a @ x is b, for a vector or a matrix b
+fn solve(a: Value, b: Value) -> InternalInterpreterResult {
+    let mut rows = square(a, "solve")?;
+    let n = rows.len();
+    let (b_rows, vector) = match shape(b, "solve for")? {
+        Shape::Vector(values) => (values.into_iter().map(|v| vec![v]).collect::<Vec<Vec<Value>>>(), true),
+        Shape::Matrix(b_rows) => (b_rows, false)
+    };
+    if b_rows.len() != n {
+        let b_shape = if vector { Shape::Vector(column(&b_rows, 0)) } else { Shape::Matrix(b_rows) };
+        return Result::Err(Error::units(format!("Cannot solve {} for {}",
+                                                Shape::Matrix(rows).describe(), b_shape.describe())));
+    }
+    for (row, b_row) in rows.iter_mut().zip(b_rows) {
+        row.extend(b_row);
+    }
+    let solution = eliminate(numbers(rows, "solve")?, n)?;
+    if solution.rank < n {
+        return Result::Err(Error::undefined("Cannot solve with a singular matrix"));
+    }
+    if vector {
+        Result::Ok(Value::List(column(&solution.quotients, 0)))
+    } else {
+        Result::Ok(to_value(solution.quotients))
+    }
+}
+
+pub fn call(name: &str, mut arguments: Vec<Value>) -> InternalInterpreterResult {
+    let first = arguments.remove(0);
+    match name {
+        "transpose" => transpose(first),
+        "det" => determinant(first),
+        "rank" => rank(first),
+        "inverse" => inverse(first),
+        "solve" => solve(first, arguments.remove(0)),
+        _ => panic!("{} is not a matrix function", name)
+    }
+}
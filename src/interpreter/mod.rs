@@ -1,77 +1,1137 @@
+use std::collections::HashMap;
+use std::f64::consts::{E, PI};
 use std::fmt;
 
-use super::parser::{Expression, Operation};
+use super::calculus::{derivative_call, solve, solves_equation};
+use super::currency::is_currency_code;
+use super::parser::{check_call, Expression, Operation};
+use super::value::{BigInteger, Complex, ComplexForm, DateTime, NumericMode, Quantity, Rational, Unit, Value,
+                   MILLISECONDS_PER_DAY};
 
-type InternalInterpreterResult = Result<i32, String>;
+mod matrix;
+
+use self::matrix::MATRIX_FUNCTIONS;
+
+// the sort of failure an error reports, for callers that act on it
+// rather than show it
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ErrorKind {
+    Overflow,
+    DivisionByZero,
+    // a variable, or a currency's exchange rate, with no value
+    UnboundVariable,
+    // an operation with no result, like (-1)! or the inverse of a
+    // singular matrix
+    Undefined,
+    // a quantity in the wrong units, like 1 m + 1 s, or a date or a
+    // list where something else belongs
+    Units,
+    // something the calculator cannot do at all, like differentiate a
+    // factorial
+    Unsupported
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(kind: ErrorKind, message: S) -> Error {
+        Error { kind, message: message.into() }
+    }
+
+    pub fn overflow<S: Into<String>>(message: S) -> Error {
+        Error::new(ErrorKind::Overflow, message)
+    }
+
+    pub fn division_by_zero() -> Error {
+        Error::new(ErrorKind::DivisionByZero, "Division by zero")
+    }
+
+    pub fn undefined<S: Into<String>>(message: S) -> Error {
+        Error::new(ErrorKind::Undefined, message)
+    }
+
+    pub fn units<S: Into<String>>(message: S) -> Error {
+        Error::new(ErrorKind::Units, message)
+    }
+
+    pub fn unsupported<S: Into<String>>(message: S) -> Error {
+        Error::new(ErrorKind::Unsupported, message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+pub type InternalInterpreterResult = Result<Value, Error>;
+
+// the values of an expression's free variables
+pub type Bindings = HashMap<String, Value>;
 
 // newtype
 pub struct InterpreterResult {
-    result: InternalInterpreterResult
+    result: InternalInterpreterResult,
+    form: ComplexForm,
+    mode: NumericMode
+}
+
+impl InterpreterResult {
+    // the same result, displaying a complex value in the given form
+    pub fn in_form(self, form: ComplexForm) -> InterpreterResult {
+        InterpreterResult { form, ..self }
+    }
+
+    // the same result, displaying a fraction in the given mode
+    pub fn in_mode(self, mode: NumericMode) -> InterpreterResult {
+        InterpreterResult { mode, ..self }
+    }
+
+    pub fn value(&self) -> Option<&Value> {
+        self.result.as_ref().ok()
+    }
 }
 
 impl fmt::Display for InterpreterResult {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.result {
-            Result::Ok(ref i) => f.write_fmt(format_args!("{}", i)),
+            Result::Ok(ref v) => f.write_fmt(format_args!("{}", v.display(self.form, self.mode))),
             Result::Err(ref s) => f.write_fmt(format_args!("Error: {}", s))
         }
     }
 }
 
 pub trait OperationEvaluator {
-    fn operation(&self, v1: i32, v2: i32) -> InternalInterpreterResult;
+    fn operation(&self, v1: Value, v2: Value) -> InternalInterpreterResult;
+}
+
+// the message is only allocated on failure, which keeps the common path cheap
+fn to_result(op: Option<i32>, if_error: &str) -> InternalInterpreterResult {
+    match op {
+        Option::Some(i) => Result::Ok(Value::Integer(i)),
+        Option::None => Result::Err(Error::overflow(if_error))
+    }
+}
+
+// reals never become infinite or NaN; those are reported as errors instead
+fn real(r: f64) -> InternalInterpreterResult {
+    if r.is_nan() {
+        Result::Err(Error::undefined("Undefined result"))
+    } else if r.is_infinite() {
+        Result::Err(Error::overflow("real overflow"))
+    } else {
+        Result::Ok(Value::Real(r))
+    }
+}
+
+// as real, for both parts; a zero imaginary part gives a Real
+fn complex(z: Complex) -> InternalInterpreterResult {
+    if z.re.is_nan() || z.im.is_nan() {
+        Result::Err(Error::undefined("Undefined result"))
+    } else if z.re.is_infinite() || z.im.is_infinite() {
+        Result::Err(Error::overflow("complex overflow"))
+    } else {
+        Result::Ok(Value::from(z))
+    }
+}
+
+// as real, for the amount; a dimensionless result is a plain number
+fn quantity(q: Quantity) -> InternalInterpreterResult {
+    if q.amount.is_nan() {
+        return Result::Err(Error::undefined("Undefined result"));
+    }
+    match Value::from(q) {
+        Value::Real(r) => real(r),
+        Value::Quantity(ref q) if q.amount.is_infinite() => Result::Err(Error::overflow("quantity overflow")),
+        v => Result::Ok(v)
+    }
+}
+
+// what a value is measured in, for errors
+fn units_of(v: &Value) -> String {
+    match v {
+        Value::Quantity(q) => q.unit.to_string(),
+        Value::DateTime(d) => d.describe().to_string(),
+        Value::List(_) => "a list".to_string(),
+        _ => "a plain number".to_string()
+    }
+}
+
+fn is_list(v: &Value) -> bool {
+    matches!(v, Value::List(_))
+}
+
+// applies f to each element, keeping the list's shape
+fn each<F>(values: Vec<Value>, f: F) -> InternalInterpreterResult
+    where F: FnMut(Value) -> InternalInterpreterResult {
+    values.into_iter().map(f).collect::<Result<Vec<Value>, Error>>().map(Value::List)
+}
+
+// Applies f element by element where either side is a list.  A single
+// value goes with every element of a list, and two lists pair their
+// elements, so must be the same length.  f is applied to the pairs as
+// they are, so nested lists broadcast too.
+fn broadcast<F>(v1: Value, v2: Value, f: F) -> InternalInterpreterResult
+    where F: Fn(Value, Value) -> InternalInterpreterResult {
+    match (v1, v2) {
+        (Value::List(l1), Value::List(l2)) => {
+            if l1.len() != l2.len() {
+                return Result::Err(Error::units(format!("Cannot combine lists of lengths {} and {}",
+                                                        l1.len(),
+                                                        l2.len())));
+            }
+            let mut pairs = l2.into_iter();
+            each(l1, |e1| f(e1, pairs.next().unwrap()))
+        },
+        (Value::List(l1), v2) => each(l1, |e1| f(e1, v2.clone())),
+        (v1, Value::List(l2)) => each(l2, |e2| f(v1.clone(), e2)),
+        _ => panic!("no list to broadcast over")
+    }
+}
+
+fn unit_power(unit: Option<Unit>) -> Result<Unit, Error> {
+    unit.ok_or_else(|| Error::overflow("unit power overflow"))
+}
+
+fn complex_quantity() -> InternalInterpreterResult {
+    Result::Err(Error::units("Cannot give a complex number units"))
+}
+
+// the most bits a big integer may have, about 4900 decimal digits
+pub const MAX_BIG_BITS: usize = 1 << 14;
+
+fn big(b: BigInteger) -> InternalInterpreterResult {
+    if b.bits() > MAX_BIG_BITS {
+        Result::Err(Error::overflow("big integer overflow"))
+    } else {
+        Result::Ok(Value::from(b))
+    }
+}
+
+// as big, for the numerator and the denominator
+fn rational(r: Rational) -> InternalInterpreterResult {
+    if r.bits() > MAX_BIG_BITS {
+        Result::Err(Error::overflow("rational overflow"))
+    } else {
+        Result::Ok(Value::from(r))
+    }
+}
+
+// literals are read unsigned, so anything past i32::MAX cannot be represented
+pub fn literal(i: u32) -> InternalInterpreterResult {
+    if i <= i32::MAX as u32 {
+        Result::Ok(Value::Integer(i as i32))
+    } else {
+        Result::Err(Error::overflow("integer literal overflow"))
+    }
+}
+
+// the nearest real to the digits
+pub fn decimal(digits: &str) -> InternalInterpreterResult {
+    real(digits.parse().expect("the lexer only reads digits around a point"))
+}
+
+// a day written YYYY-MM-DD, which must be in its month
+pub fn date(text: &str) -> InternalInterpreterResult {
+    DateTime::parse_date(text).map(Value::DateTime).ok_or_else(|| Error::undefined(format!("No such date: {}", text)))
+}
+
+// a time of day written H:MM, with optional seconds
+pub fn time(text: &str) -> InternalInterpreterResult {
+    DateTime::parse_time(text).map(Value::DateTime).ok_or_else(|| Error::undefined(format!("No such time: {}", text)))
+}
+
+// any u32 times i is exact in an f64
+pub fn imaginary(i: u32) -> Value {
+    Value::from(Complex::new(0.0, f64::from(i)))
+}
+
+pub fn negate(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::List(values) => each(values, negate),
+        Value::Integer(i) => to_result(i.checked_neg(), "negation overflow"),
+        Value::Real(r) => Result::Ok(Value::Real(-r)),
+        Value::Big(b) => big(-&b),
+        Value::Rational(r) => rational(-&r),
+        Value::Complex(z) => Result::Ok(Value::Complex(-z)),
+        Value::Quantity(q) => Result::Ok(Value::Quantity(Quantity::new(-q.amount, q.unit))),
+        Value::DateTime(d) => Result::Err(Error::units(format!("Cannot negate {}", d.describe())))
+    }
+}
+
+// The principal root, so a negative number has an imaginary one.  A
+// quantity's unit must be a square, as in m², and its amount positive.
+pub fn square_root(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::List(values) => each(values, square_root),
+        Value::Complex(z) => complex(z.sqrt()),
+        Value::DateTime(d) => Result::Err(Error::units(format!("Cannot take the square root of {}", d.describe()))),
+        Value::Quantity(ref q) if q.amount < 0.0 => complex_quantity(),
+        Value::Quantity(q) => match q.unit.sqrt() {
+            Some(unit) => quantity(Quantity::new(q.amount.sqrt(), unit)),
+            None => Result::Err(Error::units(format!("Cannot take the square root of {}", q.unit)))
+        },
+        _ if v.to_f64() < 0.0 => complex(v.to_complex().sqrt()),
+        _ => real(v.to_f64().sqrt())
+    }
+}
+
+// Lanczos approximation of the gamma function (g = 7, n = 9), good to
+// about 15 significant digits, with the reflection formula below 1/2.
+// Poles give an infinity or NaN, which callers report.
+#[allow(clippy::excessive_precision)]
+pub fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.99999999999980993,
+        676.5203681218851,
+        -1259.1392167224028,
+        771.32342877765313,
+        -176.61502916214059,
+        12.507343278686905,
+        -0.13857109526572012,
+        9.9843695780195716e-6,
+        1.5056327351493116e-7
+    ];
+    if x < 0.5 {
+        return PI / ((PI * x).sin() * gamma(1.0 - x));
+    }
+    let x = x - 1.0;
+    let mut series = COEFFICIENTS[0];
+    for (i, c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        series += c / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+    // t^(x + 1/2) is split in two so it stays finite as long as the result does
+    let half_power = t.powf((x + 0.5) / 2.0);
+    (2.0 * PI).sqrt() * half_power * (-t).exp() * half_power * series
+}
+
+fn exact_factorial(n: i32) -> InternalInterpreterResult {
+    if n < 0 {
+        return Result::Err(Error::undefined("Factorial of a negative integer"));
+    }
+    // n! > (n/e)^n, so this rejects hopeless cases without computing them
+    let n_real = f64::from(n);
+    if n_real * (n_real / E).log2() > MAX_BIG_BITS as f64 {
+        return Result::Err(Error::overflow("factorial overflow"));
+    }
+    let mut product = BigInteger::from(1);
+    for k in 2..=n as u32 {
+        product = product.multiply_by(k);
+        if product.bits() > MAX_BIG_BITS {
+            return Result::Err(Error::overflow("factorial overflow"));
+        }
+    }
+    Result::Ok(Value::from(product))
+}
+
+// Integers have exact factorials.  Reals use the gamma function, except
+// that whole numbers are multiplied out so they come out exact where the
+// f64 can hold it.
+pub fn factorial(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::List(values) => each(values, factorial),
+        Value::Integer(n) => exact_factorial(n),
+        Value::Big(ref b) if b.is_negative() =>
+            Result::Err(Error::undefined("Factorial of a negative integer")),
+        Value::Big(_) => Result::Err(Error::overflow("factorial overflow")),
+        Value::Complex(_) => Result::Err(Error::undefined("Factorial of a complex number")),
+        Value::Quantity(q) => Result::Err(Error::units(format!("Cannot take the factorial of {}", q.unit))),
+        Value::DateTime(d) => Result::Err(Error::units(format!("Cannot take the factorial of {}", d.describe()))),
+        Value::Real(x) if x.fract() == 0.0 && x < 0.0 =>
+            Result::Err(Error::undefined("Factorial of a negative integer")),
+        Value::Real(x) if x.fract() == 0.0 && x > 170.0 => real(f64::INFINITY),
+        Value::Real(x) if x.fract() == 0.0 =>
+            real((2..=x as u32).fold(1.0, |product, k| product * f64::from(k))),
+        Value::Real(x) => real(gamma(x + 1.0)),
+        Value::Rational(r) => real(gamma(r.to_f64() + 1.0))
+    }
+}
+
+pub fn percent(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::List(values) => each(values, percent),
+        Value::Complex(z) => complex(z / Complex::from(100.0)),
+        Value::Quantity(q) => quantity(Quantity::new(q.amount / 100.0, q.unit)),
+        Value::DateTime(d) => Result::Err(Error::units(format!("Cannot take a percentage of {}", d.describe()))),
+        _ => real(v.to_f64() / 100.0)
+    }
+}
+
+// v1 raised or lowered by v2 percent of itself
+pub fn percent_change(op: &Operation, v1: Value, v2: Value) -> InternalInterpreterResult {
+    if is_list(&v1) || is_list(&v2) {
+        return broadcast(v1, v2, |v1, v2| percent_change(op, v1, v2));
+    }
+    match (v1, v2) {
+        (Value::DateTime(d), _) | (_, Value::DateTime(d)) =>
+            Result::Err(Error::units(format!("Cannot change {} by a percentage", d.describe()))),
+        (_, Value::Quantity(q)) => Result::Err(Error::units(format!("Cannot change by a percentage in {}", q.unit))),
+        (Value::Quantity(_), Value::Complex(_)) => complex_quantity(),
+        (Value::Quantity(q), v2) => {
+            let factor = match op {
+                Operation::Plus => 100.0 + v2.to_f64(),
+                _ => 100.0 - v2.to_f64()
+            };
+            quantity(Quantity::new(q.amount * factor / 100.0, q.unit))
+        },
+        (v1, v2) => number_percent_change(op, v1, v2)
+    }
+}
+
+fn number_percent_change(op: &Operation, v1: Value, v2: Value) -> InternalInterpreterResult {
+    let hundred = Complex::from(100.0);
+    let factor = match op {
+        Operation::Plus => hundred + v2.to_complex(),
+        Operation::Minus => hundred - v2.to_complex(),
+        _ => panic!("{} is not a percent change", op)
+    };
+    match (&v1, &v2) {
+        (Value::Complex(_), _) | (_, Value::Complex(_)) =>
+            complex(v1.to_complex() * factor / hundred),
+        _ => real(v1.to_f64() * factor.re / 100.0)
+    }
+}
+
+fn absolute_value(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::Integer(i) => to_result(i.checked_abs(), "absolute value overflow"),
+        Value::Real(r) => Result::Ok(Value::Real(r.abs())),
+        Value::Big(ref b) if b.is_negative() => big(-b),
+        Value::Big(_) => Result::Ok(v),
+        Value::Rational(ref r) if r.is_negative() => rational(-r),
+        Value::Rational(_) => Result::Ok(v),
+        Value::Complex(z) => real(z.abs()),
+        Value::Quantity(q) => Result::Ok(Value::Quantity(Quantity::new(q.amount.abs(), q.unit))),
+        Value::DateTime(_) => panic!("call rejects dates"),
+        Value::List(_) => panic!("call maps over lists")
+    }
+}
+
+// the angle from the positive real axis, in (-π, π]
+fn argument(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::Complex(z) => real(z.arg()),
+        _ if v.to_f64() < 0.0 => real(PI),
+        _ => real(0.0)
+    }
+}
+
+fn conjugate(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::Complex(z) => Result::Ok(Value::Complex(z.conj())),
+        _ => Result::Ok(v)
+    }
+}
+
+fn real_part(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::Complex(z) => real(z.re),
+        _ => Result::Ok(v)
+    }
+}
+
+fn imaginary_part(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::Complex(z) => real(z.im),
+        _ => Result::Ok(Value::Integer(0))
+    }
+}
+
+fn exponential(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::Complex(z) => complex(z.exp()),
+        Value::Quantity(q) => Result::Err(Error::units(format!("Cannot take exp of {}", q.unit))),
+        _ => real(v.to_f64().exp())
+    }
+}
+
+// the natural logarithm, complex for a negative number
+fn logarithm(v: Value) -> InternalInterpreterResult {
+    match v {
+        Value::Complex(z) => complex(z.ln()),
+        Value::Quantity(q) => Result::Err(Error::units(format!("Cannot take ln of {}", q.unit))),
+        _ if v.to_f64() == 0.0 => Result::Err(Error::undefined("Undefined result")),
+        _ if v.to_f64() < 0.0 => complex(v.to_complex().ln()),
+        _ => real(v.to_f64().ln())
+    }
+}
+
+// the functions of a whole list; the others apply to each element
+const AGGREGATES: [&str; 6] = ["sum", "product", "mean", "median", "stddev", "count"];
+
+fn sum(values: Vec<Value>) -> InternalInterpreterResult {
+    values.into_iter().try_fold(Value::Integer(0), |total, v| Operation::Plus.operation(total, v))
+}
+
+// a real, so the mean of 1 and 2 is 1.5
+fn mean(values: Vec<Value>, name: &str) -> InternalInterpreterResult {
+    if values.is_empty() {
+        return Result::Err(Error::undefined(format!("Too few values for {}", name)));
+    }
+    let count = values.len() as f64;
+    Operation::Div.operation(sum(values)?, Value::Real(count))
+}
+
+// The middle value, or the mean of the middle two.  The values must be
+// numbers, or quantities of one dimension, that can be ordered.
+fn median(mut values: Vec<Value>) -> InternalInterpreterResult {
+    if values.is_empty() {
+        return Result::Err(Error::undefined("Too few values for median"));
+    }
+    for v in &values {
+        if let Value::DateTime(_) | Value::List(_) = v {
+            return Result::Err(Error::units(format!("Cannot take median of {}", units_of(v))));
+        }
+        Operation::Less.operation(values[0].clone(), v.clone())?;
+    }
+    values.sort_by(|v1, v2| v1.to_f64().total_cmp(&v2.to_f64()));
+    let middle = values.len() / 2;
+    if values.len() % 2 == 1 {
+        return Result::Ok(values.swap_remove(middle));
+    }
+    let pair = Operation::Plus.operation(values[middle - 1].clone(), values[middle].clone())?;
+    Operation::Div.operation(pair, Value::Real(2.0))
+}
+
+// the sample standard deviation, dividing by one less than the count
+fn standard_deviation(values: Vec<Value>) -> InternalInterpreterResult {
+    if values.len() < 2 {
+        return Result::Err(Error::undefined("Too few values for stddev"));
+    }
+    let count = values.len() as f64;
+    let average = mean(values.clone(), "stddev")?;
+    let mut squares = Value::Integer(0);
+    for v in values {
+        let deviation = Operation::Minus.operation(v, average.clone())?;
+        let square = Operation::Times.operation(deviation.clone(), deviation)?;
+        squares = Operation::Plus.operation(squares, square)?;
+    }
+    square_root(Operation::Div.operation(squares, Value::Real(count - 1.0))?)
+}
+
+fn aggregate(name: &str, v: Value) -> InternalInterpreterResult {
+    let values = match v {
+        Value::List(values) => values,
+        other => return Result::Err(Error::units(format!("Cannot take {} of {}", name, units_of(&other))))
+    };
+    match name {
+        "sum" => sum(values),
+        "product" => values.into_iter().try_fold(Value::Integer(1), |total, v| Operation::Times.operation(total, v)),
+        "mean" => mean(values, name),
+        "median" => median(values),
+        "stddev" => standard_deviation(values),
+        "count" => Result::Ok(Value::Integer(values.len() as i32)),
+        _ => panic!("{} is not an aggregate", name)
+    }
+}
+
+// Applies a built-in function to its evaluated arguments.  An aggregate
+// or a matrix function takes a list; any other function applies to each
+// element of one.
+pub fn call(name: &str, mut arguments: Vec<Value>) -> InternalInterpreterResult {
+    check_call(name, arguments.len()).map_err(Error::unsupported)?;
+    if MATRIX_FUNCTIONS.contains(&name) {
+        return matrix::call(name, arguments);
+    }
+    if AGGREGATES.contains(&name) {
+        return aggregate(name, arguments.remove(0));
+    }
+    if let Some(Value::List(_)) = arguments.first() {
+        return match arguments.remove(0) {
+            Value::List(values) => each(values, |v| call(name, vec![v])),
+            _ => panic!("not a list")
+        };
+    }
+    if let Some(Value::DateTime(d)) = arguments.iter().find(|v| matches!(v, Value::DateTime(_))) {
+        return Result::Err(Error::units(format!("Cannot take {} of {}", name, d.describe())));
+    }
+    match name {
+        "sqrt" => square_root(arguments.remove(0)),
+        "abs" => absolute_value(arguments.remove(0)),
+        "arg" => argument(arguments.remove(0)),
+        "conj" => conjugate(arguments.remove(0)),
+        "re" => real_part(arguments.remove(0)),
+        "im" => imaginary_part(arguments.remove(0)),
+        "exp" => exponential(arguments.remove(0)),
+        "ln" => logarithm(arguments.remove(0)),
+        _ => panic!("{} is not implemented", name)
+    }
+}
+
+// names that mean something without being bound
+pub fn constant(name: &str) -> Option<Value> {
+    match name {
+        "π" | "pi" => Some(Value::Real(PI)),
+        "i" | "j" => Some(Value::Complex(Complex::new(0.0, 1.0))),
+        "today" => Some(Value::DateTime(DateTime::today())),
+        "now" => Some(Value::DateTime(DateTime::now())),
+        _ => None
+    }
+}
+
+// one of a unit; a dimensionless one, like °, is just a number
+pub fn unit(name: &str) -> Option<Value> {
+    Unit::named(name).map(|unit| Value::from(Quantity::new(1.0, unit)))
+}
+
+// currencies are bound from a rate table, so one that is unbound is missing from it
+pub fn unbound(name: &str) -> Error {
+    let message = if is_currency_code(name) {
+        format!("No exchange rate for {}", name)
+    } else {
+        format!("Unbound variable: {}", name)
+    };
+    Error::new(ErrorKind::UnboundVariable, message)
+}
+
+// bindings shadow constants, and constants shadow units
+pub fn lookup(bindings: &Bindings, name: &str) -> InternalInterpreterResult {
+    match bindings.get(name).cloned().or_else(|| constant(name)).or_else(|| unit(name)) {
+        Some(v) => Result::Ok(v),
+        None => Result::Err(unbound(name))
+    }
+}
+
+// A quantity can only be raised to a whole number, which raises its unit
+// too, and cannot itself be an exponent.
+fn quantity_power(v1: Value, v2: Value) -> InternalInterpreterResult {
+    let q = match (v1, &v2) {
+        (_, Value::Quantity(exponent)) =>
+            return Result::Err(Error::units(format!("Cannot use {} as an exponent", exponent.unit))),
+        (Value::Quantity(q), _) => q,
+        _ => panic!("no quantity to raise")
+    };
+    let exponent = v2.to_f64();
+    if let Value::Complex(_) = v2 {
+        return Result::Err(Error::units(format!("Cannot raise {} to a complex power", q.unit)));
+    }
+    if exponent.fract() != 0.0 || exponent.abs() > f64::from(i32::MAX) {
+        return Result::Err(Error::units(format!("Cannot raise {} to a non-integer power", q.unit)));
+    }
+    if q.amount == 0.0 && exponent < 0.0 {
+        return Result::Err(Error::division_by_zero());
+    }
+    let exponent = exponent as i32;
+    quantity(Quantity::new(q.amount.powi(exponent), unit_power(q.unit.powi(exponent))?))
+}
+
+// A negative integer exponent gives a real, as there is no integer
+// result to truncate to.  So does an exponent too big for an i32.  A
+// complex base or exponent gives the principal value, multiplied out when
+// the exponent is an integer.
+fn power(v1: Value, v2: Value) -> InternalInterpreterResult {
+    match (&v1, &v2) {
+        (Value::Quantity(_), _) | (_, Value::Quantity(_)) => quantity_power(v1, v2),
+        (Value::Complex(base), Value::Integer(exponent)) => complex(base.powi(*exponent)),
+        (Value::Complex(_), _) | (_, Value::Complex(_)) =>
+            complex(v1.to_complex().powc(v2.to_complex())),
+        _ if v1.to_f64() == 0.0 && v2.to_f64() < 0.0 =>
+            Result::Err(Error::division_by_zero()),
+        (Value::Integer(base), Value::Integer(exponent)) if *exponent >= 0 =>
+            to_result(base.checked_pow(*exponent as u32), "exponentiation overflow"),
+        (Value::Big(base), Value::Integer(exponent)) if *exponent >= 0 => {
+            // the base has at least 32 bits, so this bounds the result's size
+            if (base.bits() - 1).saturating_mul(*exponent as usize) > MAX_BIG_BITS {
+                return Result::Err(Error::overflow("big integer overflow"));
+            }
+            big((0..*exponent).fold(BigInteger::from(1), |product, _| &product * base))
+        },
+        // a fraction stays exact, its denominator having at least two bits
+        (Value::Rational(base), Value::Integer(exponent)) => {
+            if (base.bits() - 1).saturating_mul(exponent.unsigned_abs() as usize) > MAX_BIG_BITS {
+                return Result::Err(Error::overflow("rational overflow"));
+            }
+            let one = Rational::from(BigInteger::from(1));
+            let power = base.powi(exponent.unsigned_abs());
+            rational(if *exponent < 0 { &one / &power } else { power })
+        },
+        (Value::Integer(_), Value::Integer(exponent)) | (Value::Big(_), Value::Integer(exponent)) =>
+            real(v1.to_f64().powi(*exponent)),
+        _ => real(v1.to_f64().powf(v2.to_f64()))
+    }
+}
+
+// complex numbers can only be compared for equality
+fn compare(op: &Operation, v1: Value, v2: Value) -> InternalInterpreterResult {
+    if let (Value::Complex(_), _) | (_, Value::Complex(_)) = (&v1, &v2) {
+        let equal = v1.to_complex() == v2.to_complex();
+        return match op {
+            Operation::Equal => Result::Ok(Value::from(equal)),
+            Operation::NotEqual => Result::Ok(Value::from(!equal)),
+            _ => Result::Err(Error::undefined("Complex numbers cannot be ordered"))
+        };
+    }
+    let ordering = match (&v1, &v2) {
+        (Value::Integer(i1), Value::Integer(i2)) => i1.partial_cmp(i2),
+        _ => match (v1.to_rational(), v2.to_rational()) {
+            (Some(r1), Some(r2)) => r1.partial_cmp(&r2),
+            _ => v1.to_f64().partial_cmp(&v2.to_f64())
+        }
+    };
+    let ordering = ordering.expect("values are never NaN");
+    Result::Ok(Value::from(match op {
+        Operation::Less => ordering.is_lt(),
+        Operation::LessEqual => ordering.is_le(),
+        Operation::Greater => ordering.is_gt(),
+        Operation::GreaterEqual => ordering.is_ge(),
+        Operation::Equal => ordering.is_eq(),
+        Operation::NotEqual => ordering.is_ne(),
+        _ => panic!("{} is not a comparison", op)
+    }))
+}
+
+// a plain zero stands for zero of any unit, so 0 + 3 m is 3 m
+fn as_quantity(v: &Value, other: &Quantity) -> Option<Quantity> {
+    match v {
+        Value::Quantity(q) => Some(q.clone()),
+        Value::DateTime(_) => None,
+        _ if v.to_f64() == 0.0 => Some(Quantity::new(0.0, other.unit.clone())),
+        _ => None
+    }
+}
+
+// Sums, differences and comparisons need both sides in the same
+// dimension; the result is in the units of the left.  Products and
+// quotients multiply the units.
+fn quantity_operation(op: &Operation, v1: Value, v2: Value) -> InternalInterpreterResult {
+    if let (Value::Complex(_), _) | (_, Value::Complex(_)) = (&v1, &v2) {
+        return complex_quantity();
+    }
+    match op {
+        Operation::Times | Operation::Div => {
+            let (amount1, unit1) = match v1 {
+                Value::Quantity(q) => (q.amount, Some(q.unit)),
+                _ => (v1.to_f64(), None)
+            };
+            let (amount2, unit2) = match v2 {
+                Value::Quantity(q) => (q.amount, Some(q.unit)),
+                _ => (v2.to_f64(), None)
+            };
+            if *op == Operation::Div && amount2 == 0.0 {
+                return Result::Err(Error::division_by_zero());
+            }
+            let (amount, unit) = match (op, unit1, unit2) {
+                (Operation::Times, Some(u1), Some(u2)) => (amount1 * amount2, u1.multiply(&u2)),
+                (Operation::Times, Some(u), None) | (Operation::Times, None, Some(u)) => (amount1 * amount2, Some(u)),
+                (_, Some(u1), Some(u2)) => (amount1 / amount2, u1.divide(&u2)),
+                (_, Some(u), None) => (amount1 / amount2, Some(u)),
+                (_, None, Some(u)) => (amount1 / amount2, u.powi(-1)),
+                (_, None, None) => panic!("no quantity to operate on")
+            };
+            quantity(Quantity::new(amount, unit_power(unit)?))
+        },
+        _ => {
+            let (q1, q2) = match (&v1, &v2) {
+                (Value::Quantity(q1), _) => (Some(q1.clone()), as_quantity(&v2, q1)),
+                (_, Value::Quantity(q2)) => (as_quantity(&v1, q2), Some(q2.clone())),
+                _ => panic!("no quantity to operate on")
+            };
+            let converted = match (&q1, &q2) {
+                (Some(q1), Some(q2)) => q2.convert(&q1.unit).map(|q2| (q1, q2.amount)),
+                _ => None
+            };
+            let (q1, amount2) = match converted {
+                Some(pair) => pair,
+                None => {
+                    let verb = match op {
+                        Operation::Plus => "add",
+                        Operation::Minus => "subtract",
+                        _ => "compare"
+                    };
+                    // the quantity comes first, so 1 + m and m + 1 fail alike
+                    let (first, second) = match v1 {
+                        Value::Quantity(_) => (&v1, &v2),
+                        _ => (&v2, &v1)
+                    };
+                    let message = format!("Cannot {} {} and {}", verb, units_of(first), units_of(second));
+                    return Result::Err(Error::units(message));
+                }
+            };
+            match op {
+                Operation::Plus => quantity(Quantity::new(q1.amount + amount2, q1.unit.clone())),
+                Operation::Minus => quantity(Quantity::new(q1.amount - amount2, q1.unit.clone())),
+                _ if amount2.is_nan() => Result::Err(Error::undefined("Undefined result")),
+                _ => compare(op, Value::Real(q1.amount), Value::Real(amount2))
+            }
+        }
+    }
+}
+
+// the value in the unit that target is one of, for each element of a list
+pub fn convert(v: Value, target: Value) -> InternalInterpreterResult {
+    if is_list(&target) {
+        return Result::Err(Error::units("Cannot convert to a list"));
+    }
+    if let Value::List(values) = v {
+        return each(values, |v| convert(v, target.clone()));
+    }
+    // the message leaves out any amount, whose sign of zero may depend on
+    // how the target was written
+    let unit = match target {
+        Value::Quantity(ref q) if q.amount == 1.0 => &q.unit,
+        Value::Quantity(ref q) =>
+            return Result::Err(Error::units(format!("Cannot convert to a multiple of {}", q.unit))),
+        Value::DateTime(ref d) => return Result::Err(Error::units(format!("Cannot convert to {}", d.describe()))),
+        _ => return Result::Err(Error::units("Cannot convert to a plain number"))
+    };
+    if let Value::Complex(_) = v {
+        return complex_quantity();
+    }
+    match as_quantity(&v, &Quantity::new(0.0, unit.clone())).and_then(|q| q.convert(unit)) {
+        Some(q) => quantity(q),
+        None => Result::Err(Error::units(format!("Cannot convert {} to {}", units_of(&v), unit)))
+    }
+}
+
+fn integer_operation(op: &Operation, v1: i32, v2: i32) -> InternalInterpreterResult {
+    match op {
+        Operation::Plus => to_result(v1.checked_add(v2),
+                                     "addition overflow"),
+        Operation::Minus => to_result(v1.checked_sub(v2),
+                                      "subtraction overflow"),
+        Operation::Times => to_result(v1.checked_mul(v2),
+                                      "multiplication overflow"),
+        Operation::Div => {
+            if v2 == 0 {
+                Result::Err(Error::division_by_zero())
+            } else {
+                to_result(v1.checked_div(v2),
+                          "Division overflow / underflow")
+            }
+        },
+        _ => panic!("{} is not arithmetic", op)
+    }
+}
+
+// exact, truncating division toward zero as for i32
+fn big_operation(op: &Operation, v1: &BigInteger, v2: &BigInteger) -> InternalInterpreterResult {
+    match op {
+        Operation::Plus => big(v1 + v2),
+        Operation::Minus => big(v1 - v2),
+        Operation::Times => big(v1 * v2),
+        Operation::Div => match v1.divide(v2) {
+            Some((quotient, _)) => big(quotient),
+            None => Result::Err(Error::division_by_zero())
+        },
+        _ => panic!("{} is not arithmetic", op)
+    }
 }
 
-fn to_result(op: Option<i32>, if_error: String) -> InternalInterpreterResult {
+fn rational_operation(op: &Operation, v1: &Rational, v2: &Rational) -> InternalInterpreterResult {
     match op {
-        Option::Some(i) => Result::Ok(i),
-        Option::None => Result::Err(if_error)
+        Operation::Plus => rational(v1 + v2),
+        Operation::Minus => rational(v1 - v2),
+        Operation::Times => rational(v1 * v2),
+        Operation::Div if v2.numerator().is_zero() => Result::Err(Error::division_by_zero()),
+        Operation::Div => rational(v1 / v2),
+        _ => panic!("{} is not arithmetic", op)
+    }
+}
+
+fn real_operation(op: &Operation, v1: f64, v2: f64) -> InternalInterpreterResult {
+    match op {
+        Operation::Plus => real(v1 + v2),
+        Operation::Minus => real(v1 - v2),
+        Operation::Times => real(v1 * v2),
+        Operation::Div if v2 == 0.0 => Result::Err(Error::division_by_zero()),
+        Operation::Div => real(v1 / v2),
+        _ => panic!("{} is not arithmetic", op)
+    }
+}
+
+// How far a duration moves a date.  Whole months or years move it along
+// the calendar; any other duration, including a fraction of a month, moves
+// it by its length, a month being a twelfth of a year.  Plain zero is no
+// time at all.
+enum Shift {
+    Months(i64),
+    Milliseconds(i64)
+}
+
+fn shift(v: &Value) -> Option<Result<Shift, Error>> {
+    let q = match v {
+        Value::Quantity(q) if q.unit.dimension() == Unit::named("s").unwrap().dimension() => q,
+        Value::Integer(0) => return Some(Result::Ok(Shift::Milliseconds(0))),
+        Value::Real(r) if *r == 0.0 => return Some(Result::Ok(Shift::Milliseconds(0))),
+        _ => return None
+    };
+    let months = if Some(&q.unit) == Unit::named("month").as_ref() {
+        Some(q.amount)
+    } else if Some(&q.unit) == Unit::named("yr").as_ref() || Some(&q.unit) == Unit::named("year").as_ref() {
+        Some(q.amount * 12.0)
+    } else {
+        None
+    };
+    let (count, whole_months) = match months {
+        Some(months) if months.fract() == 0.0 => (months, true),
+        _ => ((q.to_si() * 1000.0).round(), false)
+    };
+    // far more than the span of four-digit years, in either unit
+    if count.abs() > 1e15 {
+        return Some(Result::Err(Error::overflow("date overflow")));
+    }
+    Some(Result::Ok(if whole_months { Shift::Months(count as i64) } else { Shift::Milliseconds(count as i64) }))
+}
+
+fn move_date(d: &DateTime, shift: Shift, backward: bool) -> InternalInterpreterResult {
+    let moved = match shift {
+        Shift::Months(_) if !d.has_date() => return Result::Err(Error::units("Cannot add months to a time of day")),
+        Shift::Months(months) => d.add_months(if backward { -months } else { months }),
+        Shift::Milliseconds(ms) => d.add_milliseconds(if backward { -ms } else { ms })
+    };
+    moved.map(Value::DateTime).ok_or_else(|| Error::overflow("date overflow"))
+}
+
+// A date plus or minus a duration is a date, and the difference of two
+// dates is a duration: in days between dates, otherwise in seconds.  A
+// date plus a time of day is that time on that date.  Dates can also be
+// compared, but nothing else.
+fn date_operation(op: &Operation, v1: Value, v2: Value) -> InternalInterpreterResult {
+    let moved = match (op, &v1, &v2) {
+        (Operation::Plus, Value::DateTime(d1), Value::DateTime(d2)) =>
+            d1.at(d2).or_else(|| d2.at(d1)).map(|d| Result::Ok(Value::DateTime(d))),
+        (Operation::Plus, Value::DateTime(d), other) | (Operation::Plus, other, Value::DateTime(d)) =>
+            shift(other).map(|s| move_date(d, s?, false)),
+        (Operation::Minus, Value::DateTime(d), other) => shift(other).map(|s| move_date(d, s?, true)),
+        _ => None
+    };
+    if let Some(result) = moved {
+        return result;
+    }
+    let elapsed = match (&v1, &v2) {
+        (Value::DateTime(d1), Value::DateTime(d2)) => d1.since(d2).map(|ms| (d1, d2, ms)),
+        _ => None
+    };
+    match (op, elapsed) {
+        (Operation::Minus, Some((d1, d2, ms))) if !d1.has_time() && !d2.has_time() =>
+            quantity(Quantity::new((ms / MILLISECONDS_PER_DAY) as f64, Unit::named("d").unwrap())),
+        (Operation::Minus, Some((_, _, ms))) =>
+            quantity(Quantity::new(ms as f64 / 1000.0, Unit::named("s").unwrap())),
+        (Operation::Less, Some((_, _, ms))) | (Operation::LessEqual, Some((_, _, ms))) |
+        (Operation::Greater, Some((_, _, ms))) | (Operation::GreaterEqual, Some((_, _, ms))) |
+        (Operation::Equal, Some((_, _, ms))) | (Operation::NotEqual, Some((_, _, ms))) =>
+            compare(op, Value::Integer(ms.signum() as i32), Value::Integer(0)),
+        _ => {
+            let verb = match op {
+                Operation::Plus => "add",
+                Operation::Minus => "subtract",
+                Operation::Times => "multiply",
+                Operation::Div => "divide",
+                Operation::Power => "raise",
+                _ => "compare"
+            };
+            // the date comes first, so 1 + d and d + 1 fail alike
+            let (first, second) = match v1 {
+                Value::DateTime(_) => (&v1, &v2),
+                _ => (&v2, &v1)
+            };
+            Result::Err(Error::units(format!("Cannot {} {} and {}", verb, units_of(first), units_of(second))))
+        }
+    }
+}
+
+fn complex_operation(op: &Operation, v1: Complex, v2: Complex) -> InternalInterpreterResult {
+    match op {
+        Operation::Plus => complex(v1 + v2),
+        Operation::Minus => complex(v1 - v2),
+        Operation::Times => complex(v1 * v2),
+        Operation::Div if v2.is_zero() => Result::Err(Error::division_by_zero()),
+        Operation::Div => complex(v1 / v2),
+        _ => panic!("{} is not arithmetic", op)
     }
 }
 
 impl OperationEvaluator for Operation {
-    fn operation(&self, v1: i32, v2: i32) -> InternalInterpreterResult {
+    fn operation(&self, v1: Value, v2: Value) -> InternalInterpreterResult {
         match self {
-            Operation::Plus => to_result(v1.checked_add(v2),
-                                         format!("addition overflow")),
-            Operation::Minus => to_result(v1.checked_sub(v2),
-                                          format!("subtraction overflow")),
-            Operation::Times => to_result(v1.checked_mul(v2),
-                                          format!("multiplication overflow")),
-            Operation::Div => {
-                if v2 == 0 {
-                    Result::Err(format!("Division by zero"))
-                } else {
-                    to_result(v1.checked_div(v2),
-                              format!("Division overflow / underflow"))
+            Operation::MatrixTimes => matrix::product(v1, v2),
+            _ if is_list(&v1) || is_list(&v2) => broadcast(v1, v2, |v1, v2| self.operation(v1, v2)),
+            _ if matches!((&v1, &v2), (Value::DateTime(_), _) | (_, Value::DateTime(_))) =>
+                date_operation(self, v1, v2),
+            Operation::Power => power(v1, v2),
+            _ if matches!((&v1, &v2), (Value::Quantity(_), _) | (_, Value::Quantity(_))) =>
+                quantity_operation(self, v1, v2),
+            Operation::Less | Operation::LessEqual |
+            Operation::Greater | Operation::GreaterEqual |
+            Operation::Equal | Operation::NotEqual => compare(self, v1, v2),
+            _ => match (&v1, &v2) {
+                (Value::Integer(i1), Value::Integer(i2)) => integer_operation(self, *i1, *i2),
+                (Value::Complex(_), _) | (_, Value::Complex(_)) =>
+                    complex_operation(self, v1.to_complex(), v2.to_complex()),
+                (Value::Rational(_), _) | (_, Value::Rational(_)) => match (v1.to_rational(), v2.to_rational()) {
+                    (Some(r1), Some(r2)) => rational_operation(self, &r1, &r2),
+                    _ => real_operation(self, v1.to_f64(), v2.to_f64())
+                },
+                _ => match (v1.to_big(), v2.to_big()) {
+                    (Some(b1), Some(b2)) => big_operation(self, &b1, &b2),
+                    _ => real_operation(self, v1.to_f64(), v2.to_f64())
                 }
             }
         }
     }
 }
 
+// the most elements a range may have
+pub const MAX_RANGE_LENGTH: usize = 1_000_000;
+
+// The values from start to end, step apart, counting up or down with the
+// sign of the step.  The end is included when a whole number of steps
+// reaches it, allowing for rounding, so 0..1 step 0.1 ends at 1.  Any
+// values whose difference divided by the step is a number will do, such
+// as quantities or dates.
+pub fn range(start: Value, end: Value, step: Option<Value>) -> InternalInterpreterResult {
+    let step = step.unwrap_or(Value::Integer(1));
+    let span = Operation::Minus.operation(end.clone(), start.clone())?;
+    let steps = match Operation::Div.operation(span, step.clone())? {
+        Value::Integer(i) => f64::from(i),
+        Value::Real(r) => r,
+        Value::Big(b) => b.to_f64(),
+        Value::Rational(r) => r.to_f64(),
+        _ => return Result::Err(Error::units(format!("Cannot step from {} to {} by {}",
+                                                     units_of(&start), units_of(&end), units_of(&step))))
+    };
+    let last = (steps + steps.abs().max(1.0) * 1e-9).floor();
+    if last < 0.0 {
+        return Result::Ok(Value::List(vec![]));
+    }
+    if last >= MAX_RANGE_LENGTH as f64 {
+        return Result::Err(Error::overflow("range overflow"));
+    }
+    let mut values = Vec::with_capacity(last as usize + 1);
+    for k in 0..=last as i32 {
+        let offset = Operation::Times.operation(Value::Integer(k), step.clone())?;
+        values.push(Operation::Plus.operation(start.clone(), offset)?);
+    }
+    Result::Ok(Value::List(values))
+}
+
+// The element at a position counted from 0, or back from the end when
+// negative, so xs[-1] is the last.
+pub fn index(v: Value, position: Value) -> InternalInterpreterResult {
+    let mut values = match v {
+        Value::List(values) => values,
+        other => return Result::Err(Error::units(format!("Cannot index {}", units_of(&other))))
+    };
+    let length = values.len() as i64;
+    let i = match position {
+        Value::Integer(i) => i64::from(i),
+        Value::Big(b) =>
+            return Result::Err(Error::undefined(format!("No element {} in a list of length {}", b, length))),
+        Value::Real(_) | Value::Rational(_) | Value::Complex(_) =>
+            return Result::Err(Error::units("Cannot index with a non-integer")),
+        other => return Result::Err(Error::units(format!("Cannot index with {}", units_of(&other))))
+    };
+    let from_start = if i < 0 { i + length } else { i };
+    if from_start < 0 || from_start >= length {
+        return Result::Err(Error::undefined(format!("No element {} in a list of length {}", i, length)));
+    }
+    Result::Ok(values.swap_remove(from_start as usize))
+}
+
 pub trait Interpreter {
-    fn evaluate(&self) -> InternalInterpreterResult;
+    fn evaluate_in(&self, bindings: &Bindings) -> InternalInterpreterResult;
+    fn evaluate(&self) -> InternalInterpreterResult {
+        self.evaluate_in(&Bindings::new())
+    }
     fn evaluate_toplevel(&self) -> InterpreterResult {
+        self.evaluate_toplevel_in(&Bindings::new())
+    }
+    fn evaluate_toplevel_in(&self, bindings: &Bindings) -> InterpreterResult {
         InterpreterResult {
-            result: self.evaluate()
+            result: self.evaluate_in(bindings),
+            form: ComplexForm::Rectangular,
+            mode: NumericMode::Real
         }
     }
 }
 
 impl Interpreter for Expression {
-    fn evaluate(&self) -> InternalInterpreterResult {
+    fn evaluate_in(&self, bindings: &Bindings) -> InternalInterpreterResult {
         match self {
-            Expression::Integer(i) => Result::Ok(*i as i32),
+            Expression::Integer(i) => literal(*i),
+            Expression::Decimal(digits) => decimal(digits),
+            Expression::Date(text) => date(text),
+            Expression::Time(text) => time(text),
+            Expression::Imaginary(i) => Result::Ok(imaginary(*i)),
+            Expression::Variable(name) => lookup(bindings, name),
             Expression::UnaryMinus(e) => {
-                let e_value = try!(e.evaluate());
-                Result::Ok(-e_value)
+                let e_value = e.evaluate_in(bindings)?;
+                negate(e_value)
+            },
+            Expression::SquareRoot(e) => {
+                let e_value = e.evaluate_in(bindings)?;
+                square_root(e_value)
+            },
+            Expression::Factorial(e) => {
+                let e_value = e.evaluate_in(bindings)?;
+                factorial(e_value)
+            },
+            Expression::Percent(e) => {
+                let e_value = e.evaluate_in(bindings)?;
+                percent(e_value)
+            },
+            Expression::PercentChange(e1, op, e2) => {
+                let e1_value = e1.evaluate_in(bindings)?;
+                let e2_value = e2.evaluate_in(bindings)?;
+                percent_change(op, e1_value, e2_value)
             },
             Expression::BinaryOperation(e1, op, e2) => {
-                let e1_value = try!(e1.evaluate());
-                let e2_value = try!(e2.evaluate());
-                let result = try!(op.operation(e1_value, e2_value));
+                let e1_value = e1.evaluate_in(bindings)?;
+                let e2_value = e2.evaluate_in(bindings)?;
+                let result = op.operation(e1_value, e2_value)?;
                 Result::Ok(result)
+            },
+            Expression::Call(name, arguments) if name == "diff" => {
+                let derivative = derivative_call(arguments).map_err(Error::unsupported)?;
+                match (&arguments[1], arguments.get(2)) {
+                    (Expression::Variable(x), Some(point)) => {
+                        let mut at_point = bindings.clone();
+                        at_point.insert(x.clone(), point.evaluate_in(bindings)?);
+                        derivative.evaluate_in(&at_point)
+                    },
+                    _ => derivative.evaluate_in(bindings)
+                }
+            },
+            Expression::Call(name, arguments) if solves_equation(name, arguments) => solve(arguments, bindings),
+            Expression::Call(name, arguments) => {
+                let mut values = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    values.push(argument.evaluate_in(bindings)?);
+                }
+                call(name, values)
+            },
+            Expression::Convert(e, unit) => {
+                let e_value = e.evaluate_in(bindings)?;
+                let unit_value = unit.evaluate_in(bindings)?;
+                convert(e_value, unit_value)
+            },
+            Expression::List(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(element.evaluate_in(bindings)?);
+                }
+                Result::Ok(Value::List(values))
+            },
+            Expression::Index(e, position) => {
+                let e_value = e.evaluate_in(bindings)?;
+                let position_value = position.evaluate_in(bindings)?;
+                index(e_value, position_value)
+            },
+            Expression::Range(start, end, step) => {
+                let start_value = start.evaluate_in(bindings)?;
+                let end_value = end.evaluate_in(bindings)?;
+                let step_value = match step {
+                    Some(step) => Some(step.evaluate_in(bindings)?),
+                    None => None
+                };
+                range(start_value, end_value, step_value)
             }
         }
     }
@@ -80,4 +1140,6 @@ impl Interpreter for Expression {
 #[cfg(test)]
 mod test;
 #[cfg(test)]
-mod fuzzer;
+pub mod reference;
+#[cfg(test)]
+pub mod fuzzer;
@@ -0,0 +1,299 @@
+// A second evaluator, used as a test oracle, for expressions of plain
+// numbers.  It computes integers in i128, where no operation on i32
+// operands can overflow, and only then checks whether the result fits.
+// An exact value outside the i32 range is a big integer, as only a
+// factorial can make one; arithmetic on it is exact, and a result too
+// big for an i128 leaves the case unchecked.  Reals are plain f64
+// arithmetic, with the interpreter's rule that infinities and NaN are
+// errors.
+//
+// It does not cover, and leaves unchecked, any case that makes or names
+// an imaginary number, a unit, a date or a list, or that indexes, makes a
+// range, converts, takes a derivative or solves anything, matrices or
+// equations.  It evaluates with none of the interpreter's code except the
+// gamma function, which is tested on its own; it reads bindings and gives
+// its results as the interpreter's values, and expects errors of the
+// interpreter's kinds.
+
+use interpreter::ErrorKind;
+use parser::{Expression, Operation};
+use value::{BigInteger, Unit, Value};
+use super::{gamma, Bindings};
+
+// what the oracle expects where there is no value
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Failure {
+    Error(ErrorKind),
+    // a value the oracle cannot represent, so the case is not checked
+    Unchecked
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Exact(i128),
+    Real(f64)
+}
+
+impl Number {
+    fn real(&self) -> f64 {
+        match *self {
+            Number::Exact(i) => i as f64,
+            Number::Real(r) => r
+        }
+    }
+
+    pub fn to_value(self) -> Value {
+        match self {
+            Number::Exact(i) if fits_i32(i) => Value::Integer(i as i32),
+            Number::Exact(i) => Value::Big(BigInteger::from(i)),
+            Number::Real(r) => Value::Real(r)
+        }
+    }
+}
+
+fn fits_i32(value: i128) -> bool {
+    value >= i128::from(i32::MIN) && value <= i128::from(i32::MAX)
+}
+
+fn in_range(value: i128) -> Result<Number, Failure> {
+    if fits_i32(value) {
+        Result::Ok(Number::Exact(value))
+    } else {
+        Result::Err(Failure::Error(ErrorKind::Overflow))
+    }
+}
+
+// the exact result of an operation with a big operand
+fn big(value: Option<i128>) -> Result<Number, Failure> {
+    value.map(Number::Exact).ok_or(Failure::Unchecked)
+}
+
+fn finite(value: f64) -> Result<Number, Failure> {
+    if value.is_nan() {
+        Result::Err(Failure::Error(ErrorKind::Undefined))
+    } else if value.is_infinite() {
+        Result::Err(Failure::Error(ErrorKind::Overflow))
+    } else {
+        Result::Ok(Number::Real(value))
+    }
+}
+
+pub fn literal(i: u32) -> Result<Number, Failure> {
+    in_range(i128::from(i))
+}
+
+pub fn decimal(digits: &str) -> Result<Number, Failure> {
+    finite(digits.parse().unwrap())
+}
+
+pub fn variable(name: &str, bindings: &Bindings) -> Result<Number, Failure> {
+    match bindings.get(name) {
+        Some(Value::Integer(i)) => Result::Ok(Number::Exact(i128::from(*i))),
+        Some(Value::Real(r)) => Result::Ok(Number::Real(*r)),
+        Some(Value::Big(b)) => b.to_string().parse().map(Number::Exact).map_err(|_| Failure::Unchecked),
+        Some(Value::Rational(_)) | Some(Value::Complex(_)) | Some(Value::Quantity(_)) | Some(Value::DateTime(_)) |
+        Some(Value::List(_)) => Result::Err(Failure::Unchecked),
+        None if name == "π" || name == "pi" => Result::Ok(Number::Real(::std::f64::consts::PI)),
+        None if name == "i" || name == "j" || name == "today" || name == "now" =>
+            Result::Err(Failure::Unchecked),
+        None if Unit::named(name).is_some() => Result::Err(Failure::Unchecked),
+        None => Result::Err(Failure::Error(ErrorKind::UnboundVariable))
+    }
+}
+
+pub fn negate(v: Number) -> Result<Number, Failure> {
+    match v {
+        Number::Exact(i) if fits_i32(i) => in_range(-i),
+        Number::Exact(i) => big(i.checked_neg()),
+        Number::Real(r) => Result::Ok(Number::Real(-r))
+    }
+}
+
+pub fn square_root(v: Number) -> Result<Number, Failure> {
+    if v.real() < 0.0 {
+        Result::Err(Failure::Unchecked)
+    } else {
+        finite(v.real().sqrt())
+    }
+}
+
+// Anything from 34! on is too big for an i128, and anything past 10000!
+// is far past the interpreter's limit on big integers.
+pub fn factorial(v: Number) -> Result<Number, Failure> {
+    match v {
+        Number::Exact(n) if n < 0 => Result::Err(Failure::Error(ErrorKind::Undefined)),
+        Number::Exact(n) if n > 10000 => Result::Err(Failure::Error(ErrorKind::Overflow)),
+        Number::Exact(n) if n > 33 => Result::Err(Failure::Unchecked),
+        Number::Exact(n) => Result::Ok(Number::Exact((1..=n).product())),
+        Number::Real(x) if x.fract() == 0.0 && x < 0.0 => Result::Err(Failure::Error(ErrorKind::Undefined)),
+        Number::Real(x) if x.fract() == 0.0 && x > 170.0 => Result::Err(Failure::Error(ErrorKind::Overflow)),
+        Number::Real(x) if x.fract() == 0.0 => {
+            let mut product = 1.0;
+            let mut k = 2.0;
+            while k <= x {
+                product *= k;
+                k += 1.0;
+            }
+            finite(product)
+        },
+        Number::Real(x) => finite(gamma(x + 1.0))
+    }
+}
+
+pub fn percent(v: Number) -> Result<Number, Failure> {
+    finite(v.real() / 100.0)
+}
+
+pub fn percent_change(op: &Operation, v1: Number, v2: Number) -> Result<Number, Failure> {
+    let factor = if *op == Operation::Plus { 100.0 + v2.real() } else { 100.0 - v2.real() };
+    finite(v1.real() * factor / 100.0)
+}
+
+// the built-in functions on real arguments, where they are simple
+pub fn call(name: &str, arguments: Vec<Number>) -> Result<Number, Failure> {
+    let v = arguments[0];
+    match (name, v) {
+        ("sqrt", _) => square_root(v),
+        ("abs", Number::Exact(i)) if fits_i32(i) => in_range(i.abs()),
+        ("abs", Number::Exact(i)) => big(i.checked_abs()),
+        ("abs", Number::Real(r)) => Result::Ok(Number::Real(r.abs())),
+        ("arg", _) if v.real() < 0.0 => Result::Ok(Number::Real(::std::f64::consts::PI)),
+        ("arg", _) => Result::Ok(Number::Real(0.0)),
+        ("conj", _) | ("re", _) => Result::Ok(v),
+        ("im", _) => Result::Ok(Number::Exact(0)),
+        ("exp", _) => finite(v.real().exp()),
+        ("ln", _) if v.real() == 0.0 => Result::Err(Failure::Error(ErrorKind::Undefined)),
+        ("ln", _) if v.real() < 0.0 => Result::Err(Failure::Unchecked),
+        ("ln", _) => finite(v.real().ln()),
+        // these take lists, and never a number
+        ("sum", _) | ("product", _) | ("mean", _) | ("median", _) | ("stddev", _) | ("count", _) |
+        ("transpose", _) | ("det", _) | ("inverse", _) | ("rank", _) =>
+            Result::Err(Failure::Error(ErrorKind::Units)),
+        _ => panic!("No reference for {}", name)
+    }
+}
+
+// i32 exponentiation by repeated multiplication, checking the range at
+// every step.  Any base other than 0, 1 and -1 overflows within 32 steps.
+fn exact_power(base: i128, exponent: i128) -> Result<Number, Failure> {
+    match base {
+        0 | 1 => in_range(if exponent == 0 { 1 } else { base }),
+        -1 => in_range(if exponent % 2 == 0 { 1 } else { -1 }),
+        _ => {
+            let mut product = 1;
+            for _ in 0..exponent {
+                product *= base;
+                in_range(product)?;
+            }
+            in_range(product)
+        }
+    }
+}
+
+fn power(v1: Number, v2: Number) -> Result<Number, Failure> {
+    if v1.real() == 0.0 && v2.real() < 0.0 {
+        return Result::Err(Failure::Error(ErrorKind::DivisionByZero));
+    }
+    match (v1, v2) {
+        (Number::Exact(_), Number::Exact(exponent)) if !fits_i32(exponent) =>
+            finite(v1.real().powf(v2.real())),
+        (Number::Exact(base), Number::Exact(exponent)) if exponent >= 0 && !fits_i32(base) =>
+            big(base.checked_pow(exponent as u32)),
+        (Number::Exact(base), Number::Exact(exponent)) if exponent >= 0 =>
+            exact_power(base, exponent),
+        (Number::Exact(base), Number::Exact(exponent)) =>
+            finite((base as f64).powi(exponent as i32)),
+        _ => finite(v1.real().powf(v2.real()))
+    }
+}
+
+fn comparison(op: &Operation, v1: Number, v2: Number) -> Result<Number, Failure> {
+    let (less, equal) = match (v1, v2) {
+        (Number::Exact(i1), Number::Exact(i2)) => (i1 < i2, i1 == i2),
+        _ => (v1.real() < v2.real(), v1.real() == v2.real())
+    };
+    let holds = match op {
+        Operation::Less => less,
+        Operation::LessEqual => less || equal,
+        Operation::Greater => !less && !equal,
+        Operation::GreaterEqual => !less,
+        Operation::Equal => equal,
+        _ => !equal
+    };
+    Result::Ok(Number::Exact(if holds { 1 } else { 0 }))
+}
+
+pub fn operate(op: &Operation, v1: Number, v2: Number) -> Result<Number, Failure> {
+    match (op, v1, v2) {
+        (Operation::MatrixTimes, _, _) => Result::Err(Failure::Error(ErrorKind::Units)),
+        (Operation::Power, _, _) => power(v1, v2),
+        (Operation::Less, _, _) | (Operation::LessEqual, _, _) |
+        (Operation::Greater, _, _) | (Operation::GreaterEqual, _, _) |
+        (Operation::Equal, _, _) | (Operation::NotEqual, _, _) =>
+            comparison(op, v1, v2),
+        (Operation::Div, _, _) if v2.real() == 0.0 => Result::Err(Failure::Error(ErrorKind::DivisionByZero)),
+        (_, Number::Exact(i1), Number::Exact(i2)) if !fits_i32(i1) || !fits_i32(i2) => big(match op {
+            Operation::Plus => i1.checked_add(i2),
+            Operation::Minus => i1.checked_sub(i2),
+            Operation::Times => i1.checked_mul(i2),
+            _ => i1.checked_div(i2)
+        }),
+        (_, Number::Exact(i1), Number::Exact(i2)) => match op {
+            Operation::Plus => in_range(i1 + i2),
+            Operation::Minus => in_range(i1 - i2),
+            Operation::Times => in_range(i1 * i2),
+            // both truncate toward zero
+            _ => in_range(i1 / i2)
+        },
+        _ => finite(match op {
+            Operation::Plus => v1.real() + v2.real(),
+            Operation::Minus => v1.real() - v2.real(),
+            Operation::Times => v1.real() * v2.real(),
+            _ => v1.real() / v2.real()
+        })
+    }
+}
+
+fn evaluate_wide(e: &Expression, bindings: &Bindings) -> Result<Number, Failure> {
+    match e {
+        Expression::Integer(i) => literal(*i),
+        Expression::Decimal(digits) => decimal(digits),
+        Expression::Imaginary(_) | Expression::Date(_) | Expression::Time(_) |
+        Expression::List(_) | Expression::Index(_, _) | Expression::Range(_, _, _) =>
+            Result::Err(Failure::Unchecked),
+        // a derivative is worked out before anything is evaluated, and
+        // solve may be of an equation, which is never evaluated as one
+        Expression::Call(name, _) if name == "diff" || name == "solve" => Result::Err(Failure::Unchecked),
+        Expression::Variable(name) => variable(name, bindings),
+        Expression::UnaryMinus(e) => negate(evaluate_wide(e, bindings)?),
+        Expression::SquareRoot(e) => square_root(evaluate_wide(e, bindings)?),
+        Expression::Factorial(e) => factorial(evaluate_wide(e, bindings)?),
+        Expression::Percent(e) => percent(evaluate_wide(e, bindings)?),
+        Expression::PercentChange(e1, op, e2) => {
+            let v1 = evaluate_wide(e1, bindings)?;
+            let v2 = evaluate_wide(e2, bindings)?;
+            percent_change(op, v1, v2)
+        },
+        Expression::BinaryOperation(e1, op, e2) => {
+            let v1 = evaluate_wide(e1, bindings)?;
+            let v2 = evaluate_wide(e2, bindings)?;
+            operate(op, v1, v2)
+        },
+        Expression::Call(name, arguments) => {
+            let mut values = vec![];
+            for argument in arguments {
+                values.push(evaluate_wide(argument, bindings)?);
+            }
+            call(name, values)
+        },
+        Expression::Convert(e, unit) => {
+            evaluate_wide(e, bindings)?;
+            evaluate_wide(unit, bindings)?;
+            Result::Err(Failure::Unchecked)
+        }
+    }
+}
+
+pub fn evaluate(e: &Expression, bindings: &Bindings) -> Result<Value, Failure> {
+    evaluate_wide(e, bindings).map(Number::to_value)
+}
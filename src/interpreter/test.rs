@@ -1,23 +1,35 @@
-use parser::{Expression, Operation};
-use super::Interpreter;
+extern crate rand;
+
+use lexer::Lexer;
+use parser::{Expression, Operation, Parser};
+use value::{Complex, ComplexForm, NumericMode, Value};
+use super::{call, Bindings, Error, ErrorKind, Interpreter, InternalInterpreterResult, OperationEvaluator};
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
 
 #[test]
 fn integers_interpret() {
-    assert_eq!(Result::Ok(42),
+    assert_eq!(Result::Ok(Value::Integer(42)),
                Expression::Integer(42).evaluate());
 }
 
+#[test]
+fn out_of_range_integer_fails_safely() {
+    assert_eq!(Result::Err(Error::overflow("integer literal overflow")),
+               Expression::Integer(2147483648).evaluate());
+}
+
 #[test]
 fn unary_minus_interprets() {
     let e = Expression::UnaryMinus(Box::new(Expression::Integer(5)));
-    assert_eq!(Result::Ok(-5), e.evaluate());
+    assert_eq!(Result::Ok(Value::Integer(-5)), e.evaluate());
 }
 
 fn assert_op(op: Operation, expected: i32) {
     let e = Expression::BinaryOperation(Box::new(Expression::Integer(6)),
                                         op,
                                         Box::new(Expression::Integer(2)));
-    assert_eq!(Result::Ok(expected), e.evaluate());
+    assert_eq!(Result::Ok(Value::Integer(expected)), e.evaluate());
 }
 
 #[test]
@@ -47,3 +59,474 @@ fn div_by_zero_fails_safely() {
                                         Box::new(Expression::Integer(0)));
     assert!(e.evaluate().is_err());
 }
+
+#[test]
+fn negation_overflow_fails_safely() {
+    // -(-2147483647 - 1)
+    let min = Expression::BinaryOperation(
+        Box::new(Expression::UnaryMinus(Box::new(Expression::Integer(2147483647)))),
+        Operation::Minus,
+        Box::new(Expression::Integer(1)));
+    let e = Expression::UnaryMinus(Box::new(min));
+    assert!(e.evaluate().is_err());
+}
+
+#[test]
+fn bound_variable_interprets() {
+    let mut bindings = Bindings::new();
+    bindings.insert("x".to_string(), Value::Integer(7));
+    let e = Expression::BinaryOperation(Box::new(Expression::Variable("x".to_string())),
+                                        Operation::Times,
+                                        Box::new(Expression::Integer(2)));
+    assert_eq!(Result::Ok(Value::Integer(14)), e.evaluate_in(&bindings));
+}
+
+#[test]
+fn unbound_variable_fails_safely() {
+    assert_eq!(Result::Err(Error::new(ErrorKind::UnboundVariable, "Unbound variable: x")),
+               Expression::Variable("x".to_string()).evaluate());
+}
+
+fn binary(e1: Expression, op: Operation, e2: Expression) -> Expression {
+    Expression::BinaryOperation(Box::new(e1), op, Box::new(e2))
+}
+
+fn int(i: u32) -> Expression {
+    Expression::Integer(i)
+}
+
+#[test]
+fn power_of_integers_is_exact() {
+    assert_op(Operation::Power, 36);
+    assert_eq!(Result::Ok(Value::Integer(1)), binary(int(0), Operation::Power, int(0)).evaluate());
+    assert_eq!(Result::Err(Error::overflow("exponentiation overflow")),
+               binary(int(2), Operation::Power, int(31)).evaluate());
+}
+
+#[test]
+fn negative_power_is_real() {
+    let e = binary(int(2), Operation::Power, Expression::UnaryMinus(Box::new(int(2))));
+    assert_eq!(Result::Ok(Value::Real(0.25)), e.evaluate());
+    let e = binary(int(0), Operation::Power, Expression::UnaryMinus(Box::new(int(1))));
+    assert_eq!(Result::Err(Error::division_by_zero()), e.evaluate());
+}
+
+#[test]
+fn square_root_is_real_unless_negative() {
+    assert_eq!(Result::Ok(Value::Real(3.0)),
+               Expression::SquareRoot(Box::new(int(9))).evaluate());
+    assert_eq!(Result::Ok(Value::Complex(Complex::new(0.0, 2.0))),
+               Expression::SquareRoot(Box::new(Expression::UnaryMinus(Box::new(int(4))))).evaluate());
+}
+
+#[test]
+fn comparisons_give_one_or_zero() {
+    assert_op(Operation::Greater, 1);
+    assert_op(Operation::GreaterEqual, 1);
+    assert_op(Operation::Less, 0);
+    assert_op(Operation::LessEqual, 0);
+    assert_op(Operation::Equal, 0);
+    assert_op(Operation::NotEqual, 1);
+}
+
+#[test]
+fn pi_is_a_constant_unless_bound() {
+    let pi = Expression::Variable("π".to_string());
+    assert_eq!(Result::Ok(Value::Real(::std::f64::consts::PI)), pi.evaluate());
+    let mut bindings = Bindings::new();
+    bindings.insert("π".to_string(), Value::Integer(3));
+    assert_eq!(Result::Ok(Value::Integer(3)), pi.evaluate_in(&bindings));
+}
+
+#[test]
+fn integers_mix_with_reals() {
+    let half = binary(int(2), Operation::Power, Expression::UnaryMinus(Box::new(int(1))));
+    assert_eq!(Result::Ok(Value::Real(2.5)), binary(int(2), Operation::Plus, half.clone()).evaluate());
+    assert_eq!(Result::Ok(Value::Real(4.0)), binary(int(2), Operation::Div, half.clone()).evaluate());
+    assert_eq!(Result::Ok(Value::Integer(1)), binary(half, Operation::Less, int(1)).evaluate());
+}
+
+#[test]
+fn real_overflow_fails_safely() {
+    // (√2) ^ 2147483647
+    let e = binary(Expression::SquareRoot(Box::new(int(2))), Operation::Power, int(2147483647));
+    assert_eq!(Result::Err(Error::overflow("real overflow")), e.evaluate());
+}
+
+fn factorial(e: Expression) -> Expression {
+    Expression::Factorial(Box::new(e))
+}
+
+fn neg(e: Expression) -> Expression {
+    Expression::UnaryMinus(Box::new(e))
+}
+
+#[test]
+fn factorials_are_exact() {
+    assert_eq!(Result::Ok(Value::Integer(1)), factorial(int(0)).evaluate());
+    assert_eq!(Result::Ok(Value::Integer(479001600)), factorial(int(12)).evaluate());
+    let big = factorial(int(25)).evaluate().unwrap();
+    assert_eq!("15511210043330985984000000", big.to_string());
+    // 25! / 24! is back in range
+    let quotient = binary(factorial(int(25)), Operation::Div, factorial(int(24)));
+    assert_eq!(Result::Ok(Value::Integer(25)), quotient.evaluate());
+}
+
+#[test]
+fn factorial_of_a_non_integer_is_gamma() {
+    let half = binary(int(2), Operation::Power, neg(int(1)));
+    let value = factorial(half).evaluate().unwrap().to_f64();
+    assert!((value - ::std::f64::consts::PI.sqrt() / 2.0).abs() < 1e-12, "0.5! = {}", value);
+}
+
+#[test]
+fn factorial_fails_safely() {
+    assert_eq!(Result::Err(Error::undefined("Factorial of a negative integer")),
+               factorial(neg(int(1))).evaluate());
+    assert_eq!(Result::Err(Error::overflow("factorial overflow")), factorial(int(100000)).evaluate());
+    assert_eq!(Result::Err(Error::overflow("factorial overflow")),
+               factorial(factorial(int(25))).evaluate());
+}
+
+#[test]
+fn percentages_are_relative_after_plus_or_minus() {
+    let percent_change = |op| Expression::PercentChange(Box::new(int(200)), op, Box::new(int(10)));
+    assert_eq!(Result::Ok(Value::Real(220.0)), percent_change(Operation::Plus).evaluate());
+    assert_eq!(Result::Ok(Value::Real(180.0)), percent_change(Operation::Minus).evaluate());
+    let percent = Expression::Percent(Box::new(int(10)));
+    assert_eq!(Result::Ok(Value::Real(5.0)), binary(int(50), Operation::Times, percent).evaluate());
+}
+
+fn evaluate_source(source: &str) -> InternalInterpreterResult {
+    let tokens = Lexer::tokenize(&mut source.chars()).unwrap();
+    Parser::parse(&tokens).unwrap().evaluate()
+}
+
+fn complex(re: f64, im: f64) -> InternalInterpreterResult {
+    Result::Ok(Value::Complex(Complex::new(re, im)))
+}
+
+#[test]
+fn complex_arithmetic_is_exact_where_it_can_be() {
+    assert_eq!(complex(3.0, 4.0), evaluate_source("3 + 4i"));
+    assert_eq!(complex(1.0, 7.0), evaluate_source("(1 + 2i)(3 + 1j)"));
+    assert_eq!(complex(0.5, -0.5), evaluate_source("1 / (1 + i)"));
+    assert_eq!(Result::Ok(Value::Real(-4.0)), evaluate_source("(2i)^2"));
+    assert_eq!(Result::Ok(Value::Real(25.0)), evaluate_source("(3 + 4i)(3 - 4i)"));
+    assert_eq!(complex(0.0, 1.0), evaluate_source("sqrt(-1)"));
+    assert_eq!(Result::Err(Error::division_by_zero()), evaluate_source("i / 0"));
+}
+
+#[test]
+fn built_in_functions_are_complex_aware() {
+    assert_eq!(Result::Ok(Value::Real(5.0)), evaluate_source("abs(3 + 4i)"));
+    assert_eq!(Result::Ok(Value::Integer(5)), evaluate_source("abs(-5)"));
+    assert_eq!(Result::Ok(Value::Real(::std::f64::consts::FRAC_PI_2)), evaluate_source("arg(2i)"));
+    assert_eq!(Result::Ok(Value::Real(::std::f64::consts::PI)), evaluate_source("arg(-1)"));
+    assert_eq!(complex(3.0, -4.0), evaluate_source("conj(3 + 4i)"));
+    assert_eq!(Result::Ok(Value::Real(3.0)), evaluate_source("re(3 + 4i)"));
+    assert_eq!(Result::Ok(Value::Real(4.0)), evaluate_source("im(3 + 4i)"));
+    assert_eq!(Result::Ok(Value::Integer(0)), evaluate_source("im(7)"));
+    let euler = evaluate_source("exp(π i)").unwrap().to_complex();
+    assert!((euler - Complex::new(-1.0, 0.0)).abs() < 1e-15, "e^πi = {}", euler);
+}
+
+#[test]
+fn complex_numbers_are_not_ordered() {
+    assert_eq!(Result::Ok(Value::Integer(1)), evaluate_source("sqrt(-4) == 2i"));
+    assert_eq!(Result::Ok(Value::Integer(1)), evaluate_source("i != 1"));
+    assert_eq!(Result::Err(Error::undefined("Complex numbers cannot be ordered")), evaluate_source("i < 2"));
+    assert_eq!(Result::Err(Error::undefined("Factorial of a complex number")), evaluate_source("i!"));
+}
+
+#[test]
+fn errors_carry_their_kind() {
+    let kind = |source: &str| evaluate_source(source).unwrap_err().kind;
+    assert_eq!(ErrorKind::Overflow, kind("2^31"));
+    assert_eq!(ErrorKind::DivisionByZero, kind("1 / 0"));
+    assert_eq!(ErrorKind::UnboundVariable, kind("EUR"));
+    assert_eq!(ErrorKind::Undefined, kind("ln(0)"));
+    assert_eq!(ErrorKind::Undefined, kind("inverse([[1, 2], [2, 4]])"));
+    assert_eq!(ErrorKind::Units, kind("1 m + 1 s"));
+    assert_eq!(ErrorKind::Units, kind("[1, 2] + [1, 2, 3]"));
+    assert_eq!(ErrorKind::Unsupported, kind("diff(x!, x, 1)"));
+    assert_eq!(ErrorKind::Unsupported, kind("solve(x^2 = 2, 3)"));
+}
+
+#[test]
+fn results_print_in_either_form() {
+    let result = |source: &str| Parser::parse(&Lexer::tokenize(&mut source.chars()).unwrap()).unwrap().evaluate_toplevel();
+    assert_eq!("3-4i", result("3 - 4i").to_string());
+    assert_eq!("-i", result("-sqrt(-1)").to_string());
+    assert_eq!("5∠0.9272952180016122", result("3 + 4i").in_form(ComplexForm::Polar).to_string());
+    assert_eq!("7", result("7").in_form(ComplexForm::Polar).to_string());
+}
+
+fn evaluate_to_string(source: &str) -> String {
+    match evaluate_source(source) {
+        Ok(v) => v.to_string(),
+        Err(e) => e.message
+    }
+}
+
+#[test]
+fn quantities_keep_the_units_they_are_written_in() {
+    assert_eq!("3.2 km", evaluate_to_string("3 km + 200 m"));
+    assert_eq!("2.8 km", evaluate_to_string("3 km - 200 m"));
+    assert_eq!("49.050000000000004 kg·m/s²", evaluate_to_string("5 kg * 9.81 m/s^2"));
+    assert_eq!("6 m²", evaluate_to_string("2 m * 3 m"));
+    assert_eq!("2 m", evaluate_to_string("√(4 m²)"));
+    assert_eq!("0.5 s^-1", evaluate_to_string("1 / 2 s"));
+    assert_eq!("-3 km", evaluate_to_string("-abs(-3 km)"));
+    assert_eq!("1", evaluate_to_string("3 m > 2 ft"));
+    assert_eq!("3 m", evaluate_to_string("0 + 3 m"));
+}
+
+#[test]
+fn dimensionless_quantities_are_numbers() {
+    assert_eq!(Result::Ok(Value::Real(1000.0)), evaluate_source("km/m"));
+    assert_eq!(Result::Ok(Value::Real(::std::f64::consts::PI)), evaluate_source("180°"));
+    assert_eq!(Result::Ok(Value::Real(180.0)), evaluate_source("arg(-1) / deg"));
+}
+
+#[test]
+fn conversion_needs_matching_dimensions() {
+    assert_eq!("26.8224 m/s", evaluate_to_string("60 mph to m/s"));
+    assert_eq!("PT90M", evaluate_to_string("1.5 h in min"));
+    assert_eq!("49.050000000000004 N", evaluate_to_string("5 kg * 9.81 m/s^2 to N"));
+    assert_eq!("Cannot convert mph to s", evaluate_to_string("60 mph to s"));
+    assert_eq!("Cannot convert a plain number to m", evaluate_to_string("3 to m"));
+    assert_eq!("Cannot convert to a multiple of m", evaluate_to_string("3 m to 2 m"));
+    assert_eq!("Cannot convert to a plain number", evaluate_to_string("3 m to 2"));
+}
+
+#[test]
+fn incompatible_units_are_errors() {
+    assert_eq!("Cannot add m and s", evaluate_to_string("1 m + 1 s"));
+    assert_eq!("Cannot subtract m and a plain number", evaluate_to_string("1 - 1 m"));
+    assert_eq!("Cannot compare kg and m", evaluate_to_string("1 kg < 1 m"));
+    assert_eq!("Cannot raise m to a non-integer power", evaluate_to_string("m^(1/2.0)"));
+    assert_eq!("Cannot take the square root of m", evaluate_to_string("√m"));
+    assert_eq!("Cannot give a complex number units", evaluate_to_string("2i m"));
+    assert_eq!("Cannot take the factorial of s", evaluate_to_string("s!"));
+}
+
+#[test]
+fn bindings_shadow_units() {
+    let mut bindings = Bindings::new();
+    bindings.insert("m".to_string(), Value::Integer(2));
+    let e = Parser::parse(&Lexer::tokenize(&mut "3 m".chars()).unwrap()).unwrap();
+    assert_eq!(Result::Ok(Value::Integer(6)), e.evaluate_in(&bindings));
+}
+
+#[test]
+fn dates_move_by_calendar_months_and_exact_durations() {
+    assert_eq!("P68D", evaluate_to_string("2026-12-25 - 2026-10-18"));
+    assert_eq!("2026-02-28", evaluate_to_string("2026-01-31 + 1 month"));
+    assert_eq!("2024-02-29", evaluate_to_string("2024-01-31 + 1 month"));
+    assert_eq!("2027-10-18", evaluate_to_string("2026-10-18 + 1 yr"));
+    assert_eq!("2025-02-28", evaluate_to_string("2024-02-29 + 1 year"));
+    assert_eq!("2020-02-29", evaluate_to_string("2024-02-29 - 4 year"));
+    assert_eq!("P3DT4H", evaluate_to_string("3d 4h"));
+    assert_eq!("2026-10-21T04:00:00", evaluate_to_string("2026-10-18 + 3d 4h"));
+    assert_eq!("2026-10-18T16:00:00", evaluate_to_string("2026-10-18 + 14:30 + 90 min"));
+    assert_eq!("01:30:00", evaluate_to_string("23:30 + 2 h"));
+    assert_eq!("PT18900S", evaluate_to_string("14:30 - 9:15"));
+    assert_eq!("1", evaluate_to_string("2026-10-18 < 2026-10-19"));
+    assert_eq!("1998", evaluate_to_string("2026 - 10 - 18"));
+}
+
+#[test]
+fn today_is_a_date_and_now_has_a_time() {
+    assert_eq!("P0D", evaluate_to_string("today - today"));
+    assert_eq!("1", evaluate_to_string("now - today < 1 d"));
+}
+
+#[test]
+fn dates_are_not_numbers() {
+    assert_eq!("Cannot multiply a date and a plain number", evaluate_to_string("2026-10-18 * 2"));
+    assert_eq!("Cannot add a date and m", evaluate_to_string("2026-10-18 + 1 m"));
+    assert_eq!("Cannot subtract a date and a time of day", evaluate_to_string("2026-10-18 - 14:30"));
+    assert_eq!("Cannot add months to a time of day", evaluate_to_string("14:30 + 1 month"));
+    assert_eq!("Cannot negate a date", evaluate_to_string("-2026-10-18"));
+    assert_eq!("Cannot take sqrt of a date", evaluate_to_string("sqrt(2026-10-18)"));
+    assert_eq!("Cannot convert a time of day to h", evaluate_to_string("12:00 in h"));
+    assert_eq!("No such date: 2026-02-30", evaluate_to_string("2026-02-30"));
+    assert_eq!("date overflow", evaluate_to_string("9999-12-31 + 1 d"));
+}
+
+#[test]
+fn lists_combine_element_by_element() {
+    assert_eq!("[2, 4, 6]", evaluate_to_string("[1, 2, 3] * 2"));
+    assert_eq!("[11, 22, 33]", evaluate_to_string("[1, 2, 3] + [10, 20, 30]"));
+    assert_eq!("[1, 0]", evaluate_to_string("[1, 2] < [2, 1]"));
+    assert_eq!("[[2, 4], [6, 8]]", evaluate_to_string("[[1, 2], [3, 4]] * 2"));
+    assert_eq!("[1, 2, 3]", evaluate_to_string("sqrt([1, 4, 9])"));
+    assert_eq!("[-1, -2]", evaluate_to_string("-[1, 2]"));
+    assert_eq!("[110, 120]", evaluate_to_string("100 + [10, 20]%"));
+    assert_eq!("[1000 m, 500 m]", evaluate_to_string("[1 km, 500 m] to m"));
+    assert_eq!("Cannot combine lists of lengths 2 and 3", evaluate_to_string("[1, 2] + [1, 2, 3]"));
+    assert_eq!("Cannot convert to a list", evaluate_to_string("1 m to [m]"));
+    assert_eq!("Division by zero", evaluate_to_string("[1, 2] / [1, 0]"));
+}
+
+#[test]
+fn ranges_include_their_end() {
+    assert_eq!("[1, 2, 3, 4, 5]", evaluate_to_string("1..5"));
+    assert_eq!("[1, 4, 7, 10]", evaluate_to_string("1..10 step 3"));
+    assert_eq!("[10, 8, 6, 4, 2]", evaluate_to_string("10..1 step -2"));
+    assert_eq!("[]", evaluate_to_string("5..1"));
+    assert_eq!("11", evaluate_to_string("count(0..1 step 0.1)"));
+    assert_eq!("[2026-01-30, 2026-01-31, 2026-02-01]", evaluate_to_string("2026-01-30..2026-02-01 step 1 d"));
+    assert_eq!("Division by zero", evaluate_to_string("1..10 step 0"));
+    assert_eq!("range overflow", evaluate_to_string("1..2000000000"));
+    assert_eq!("Cannot step from a plain number to a plain number by m", evaluate_to_string("1..10 step 1 m"));
+}
+
+#[test]
+fn indexes_count_from_zero_or_back_from_the_end() {
+    assert_eq!("1", evaluate_to_string("[1, 2, 3][0]"));
+    assert_eq!("3", evaluate_to_string("[1, 2, 3][-1]"));
+    assert_eq!("4", evaluate_to_string("[[1, 2], [3, 4]][1][1]"));
+    assert_eq!("No element 3 in a list of length 3", evaluate_to_string("[1, 2, 3][3]"));
+    assert_eq!("Cannot index with a non-integer", evaluate_to_string("[1, 2, 3][1.5]"));
+    assert_eq!("Cannot index a plain number", evaluate_to_string("5[0]"));
+}
+
+#[test]
+fn aggregates_summarize_lists() {
+    assert_eq!("5050", evaluate_to_string("sum(1..100)"));
+    assert_eq!("3628800", evaluate_to_string("product(1..10)"));
+    assert_eq!("2.5", evaluate_to_string("mean([1, 2, 3, 4])"));
+    assert_eq!("2", evaluate_to_string("median([3, 1, 2])"));
+    assert_eq!("2.5", evaluate_to_string("median([4, 1, 3, 2])"));
+    assert_eq!("2.138089935299395", evaluate_to_string("stddev([2, 4, 4, 4, 5, 5, 7, 9])"));
+    assert_eq!("0", evaluate_to_string("count([])"));
+    assert_eq!("1.5 km", evaluate_to_string("sum([1 km, 500 m])"));
+    assert_eq!("[4, 6]", evaluate_to_string("sum([[1, 2], [3, 4]])"));
+    assert_eq!("Too few values for mean", evaluate_to_string("mean([])"));
+    assert_eq!("Too few values for stddev", evaluate_to_string("stddev([1])"));
+    assert_eq!("Complex numbers cannot be ordered", evaluate_to_string("median([1, 2i])"));
+    assert_eq!("Cannot take mean of a plain number", evaluate_to_string("mean(5)"));
+}
+
+#[test]
+fn matrices_multiply_rows_by_columns() {
+    assert_eq!("[[19, 22], [43, 50]]", evaluate_to_string("[[1, 2], [3, 4]] @ [[5, 6], [7, 8]]"));
+    assert_eq!("[[1, 4], [9, 16]]", evaluate_to_string("[[1, 2], [3, 4]] * [[1, 2], [3, 4]]"));
+    assert_eq!("[3, 7]", evaluate_to_string("[[1, 2], [3, 4]] @ [1, 1]"));
+    assert_eq!("[4, 6]", evaluate_to_string("[1, 1] @ [[1, 2], [3, 4]]"));
+    assert_eq!("11", evaluate_to_string("[1, 2] @ [3, 4]"));
+    assert_eq!("[5 m]", evaluate_to_string("[[1 m, 2 m]] @ [1, 2]"));
+    assert_eq!("[[1, 4], [2, 5], [3, 6]]", evaluate_to_string("transpose([[1, 2, 3], [4, 5, 6]])"));
+    assert_eq!("[[1], [2]]", evaluate_to_string("transpose([1, 2])"));
+    assert_eq!("Cannot multiply a 1×3 matrix by a 1×2 matrix", evaluate_to_string("[[1, 2, 3]] @ [[1, 2]]"));
+    assert_eq!("Cannot multiply a vector of length 2 by a vector of length 3", evaluate_to_string("[1, 2] @ [1, 2, 3]"));
+    assert_eq!("Cannot take the matrix product of a plain number", evaluate_to_string("2 @ [1]"));
+    assert_eq!("Cannot take the matrix product of rows of lengths 2 and 1", evaluate_to_string("[[1, 2], [3]] @ [1]"));
+    assert_eq!("Cannot take transpose of a mix of lists and numbers", evaluate_to_string("transpose([[1], 2])"));
+}
+
+#[test]
+fn whole_matrices_reduce_exactly() {
+    assert_eq!("-2", evaluate_to_string("det([[1, 2], [3, 4]])"));
+    assert_eq!("0", evaluate_to_string("det([[2, 0, 1], [1, 3, 2], [3, 3, 3]])"));
+    assert_eq!("-1", evaluate_to_string("det([[0, 1], [1, 0]])"));
+    assert_eq!("2", evaluate_to_string("rank([[1, 2, 3], [4, 5, 6], [7, 8, 9]])"));
+    assert_eq!("1", evaluate_to_string("rank([[0, 0], [1, 2], [2, 4]])"));
+    assert_eq!("[[1, -1], [-1, 2]]", evaluate_to_string("inverse([[2, 1], [1, 1]])"));
+    assert_eq!("[[-2, 1], [1.5, -0.5]]", evaluate_to_string("inverse([[1, 2], [3, 4]])"));
+    assert_eq!("[2, 1]", evaluate_to_string("solve([[1, 1], [1, -1]], [3, 1])"));
+    assert_eq!("[[1, 0], [0, 1]]", evaluate_to_string("solve([[1, 1], [1, -1]], [[1, 1], [1, -1]])"));
+    assert_eq!("31022420086661971968000000", evaluate_to_string("det([[25!, 0], [0, 2]])"));
+}
+
+#[test]
+fn fractions_from_matrices_stay_exact() {
+    let rational = |source: &str| Parser::parse(&Lexer::tokenize(&mut source.chars()).unwrap()).unwrap()
+        .evaluate_toplevel()
+        .in_mode(NumericMode::Rational)
+        .to_string();
+    assert_eq!("[[-2, 1], [3/2, -1/2]]", rational("inverse([[1, 2], [3, 4]])"));
+    assert_eq!("[1/3, 2/3]", rational("solve([[3, 0], [0, 3]], [1, 2])"));
+    assert_eq!("-1/2", rational("det(inverse([[1, 2], [3, 4]]))"));
+    assert_eq!("[[1, 2], [3, 4]]", rational("inverse(inverse([[1, 2], [3, 4]]))"));
+    assert_eq!("3", rational("inverse([[1, 2], [3, 4]])[1][0] * 2"));
+    assert_eq!("1/8", rational("inverse([[2]])[0][0] ^ 3"));
+    assert_eq!("4", rational("inverse([[2]])[0][0] ^ -2"));
+    assert_eq!("2", rational("abs(det(inverse([[1, 2], [3, 4]]))) * 4"));
+    assert_eq!("1", rational("inverse([[1, 2], [3, 4]])[1][0] == 1.5"));
+    assert_eq!("2", rational("inverse([[1, 2], [3, 4]])[1][0] + 0.5"));
+    assert_eq!("Division by zero", evaluate_to_string("inverse([[3]])[0][0] / 0"));
+    assert_eq!("Cannot index with a non-integer", evaluate_to_string("[1, 2][inverse([[2]])[0][0]]"));
+}
+
+#[test]
+fn real_matrices_reduce_with_pivoting() {
+    assert_eq!("0.5", evaluate_to_string("det([[0.5, 1], [1, 3]])"));
+    assert_eq!("[8, -1]", evaluate_to_string("solve([[0.5, 1], [1, 3]], [3, 5])"));
+    assert_eq!("[[0.6, -0.7], [-0.2, 0.4]]", evaluate_to_string("inverse([[4.0, 7], [2, 6]])"));
+    assert_eq!("1", evaluate_to_string("rank([[0.1, 0.2], [0.3, 0.6]])"));
+}
+
+#[test]
+fn linear_algebra_reports_what_does_not_fit() {
+    assert_eq!("Cannot take det of a 1×3 matrix", evaluate_to_string("det([[1, 2, 3]])"));
+    assert_eq!("Cannot take det of a vector of length 2", evaluate_to_string("det([1, 2])"));
+    assert_eq!("Cannot take det of a plain number", evaluate_to_string("det(5)"));
+    assert_eq!("Cannot take rank of a matrix of m", evaluate_to_string("rank([[1 m]])"));
+    assert_eq!("Cannot take inverse of a complex matrix", evaluate_to_string("inverse([[1, i], [0, 1]])"));
+    assert_eq!("Cannot invert a singular matrix", evaluate_to_string("inverse([[1, 2], [2, 4]])"));
+    assert_eq!("Cannot solve with a singular matrix", evaluate_to_string("solve([[1, 2], [2, 4]], [1, 2])"));
+    assert_eq!("Cannot solve a 2×2 matrix for a vector of length 3", evaluate_to_string("solve([[1, 0], [0, 1]], [1, 2, 3])"));
+}
+
+fn random_matrix(rng: &mut StdRng, n: usize, whole: bool) -> Value {
+    Value::List((0..n).map(|_| Value::List((0..n).map(|_| {
+        let i = rng.gen_range(-3, 4);
+        if whole { Value::Integer(i) } else { Value::Real(f64::from(i)) }
+    }).collect())).collect())
+}
+
+fn assert_close(expected: &Value, actual: &Value) {
+    match (expected, actual) {
+        (Value::List(l1), Value::List(l2)) => l1.iter().zip(l2).for_each(|(v1, v2)| assert_close(v1, v2)),
+        _ => assert!((expected.to_f64() - actual.to_f64()).abs() < 1e-6, "{} is not {}", actual, expected)
+    }
+}
+
+#[test]
+fn exact_and_real_elimination_agree() {
+    let mut rng = StdRng::seed_from_u64(48);
+    for _ in 0..500 {
+        let n = rng.gen_range(1, 6);
+        let seed = rng.gen();
+        let whole = random_matrix(&mut StdRng::seed_from_u64(seed), n, true);
+        let real = random_matrix(&mut StdRng::seed_from_u64(seed), n, false);
+        for name in &["det", "rank"] {
+            let exact = call(name, vec![whole.clone()]).unwrap();
+            assert!(matches!(exact, Value::Integer(_)), "{}({}) is {}", name, whole, exact);
+            assert_close(&exact, &call(name, vec![real.clone()]).unwrap());
+        }
+        match (call("inverse", vec![whole.clone()]), call("inverse", vec![real.clone()])) {
+            (Ok(exact), Ok(approximate)) => {
+                assert_close(&exact, &approximate);
+                let identity = Value::List((0..n).map(|i| Value::List((0..n).map(|j| {
+                    Value::Integer(if i == j { 1 } else { 0 })
+                }).collect())).collect());
+                assert_eq!(identity, Operation::MatrixTimes.operation(whole.clone(), exact).unwrap());
+            },
+            (Err(_), Err(_)) => assert_eq!(Value::Integer(0), call("det", vec![whole.clone()]).unwrap()),
+            results => panic!("inverses of {} differ: {:?}", whole, results)
+        }
+    }
+}
+
+#[test]
+fn logarithms_are_natural() {
+    assert_eq!("2", evaluate_to_string("ln(exp(2))"));
+    assert_eq!("0", evaluate_to_string("ln(1)"));
+    assert_eq!("3.141592653589793i", evaluate_to_string("ln(-1)"));
+    assert_eq!("Undefined result", evaluate_to_string("ln(0)"));
+    assert_eq!("Cannot take ln of m", evaluate_to_string("ln(1 m)"));
+}
@@ -1,20 +1,86 @@
 extern crate rand;
 
-use super::Lexer;
-use common::fuzzer::Fuzzer;
+use super::{Lexer, Token};
+use common::corpus::Persist;
+use common::fuzzer::{replay_corpus, run_fuzzer, Fuzzer, Shrink};
 
-use self::rand::{thread_rng, Rng};
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
 
 const MAX_NUM_CHARACTERS: u32 = 50;
 
+impl Shrink for Token {
+    fn shrink(&self) -> Vec<Token> {
+        match self {
+            Token::Integer(i) => i.shrink().into_iter().map(Token::Integer).collect(),
+            Token::Imaginary(i) => i.shrink().into_iter().map(Token::Imaginary).collect(),
+            Token::Decimal(_) | Token::Date(_) | Token::Time(_) => vec![Token::Integer(0)],
+            Token::Superscript(i) => i.shrink().into_iter().map(Token::Superscript).collect(),
+            Token::Identifier(name) if name != "x" => vec![Token::Identifier("x".to_string())],
+            _ => vec![]
+        }
+    }
+}
+
+fn superscript(digit: char) -> char {
+    "⁰¹²³⁴⁵⁶⁷⁸⁹".chars().nth(digit.to_digit(10).unwrap() as usize).unwrap()
+}
+
+// spelled as source, separated by spaces so lexing gives them back
+impl Persist for Vec<Token> {
+    fn encode(&self) -> String {
+        let spelled: Vec<String> = self.iter().map(|token| {
+            match token {
+                Token::Integer(i) => i.to_string(),
+                Token::Decimal(digits) | Token::Date(digits) | Token::Time(digits) => digits.clone(),
+                Token::Imaginary(i) => format!("{}i", i),
+                Token::Identifier(name) => name.clone(),
+                Token::Plus => "+".to_string(),
+                Token::Minus => "-".to_string(),
+                Token::Times => "*".to_string(),
+                Token::Div => "/".to_string(),
+                Token::MatrixTimes => "@".to_string(),
+                Token::LeftParen => "(".to_string(),
+                Token::RightParen => ")".to_string(),
+                Token::Power => "^".to_string(),
+                Token::Superscript(i) => i.to_string().chars().map(superscript).collect(),
+                Token::SquareRoot => "√".to_string(),
+                Token::Less => "<".to_string(),
+                Token::LessEqual => "<=".to_string(),
+                Token::Greater => ">".to_string(),
+                Token::GreaterEqual => ">=".to_string(),
+                Token::Equal => "==".to_string(),
+                Token::NotEqual => "!=".to_string(),
+                Token::Factorial => "!".to_string(),
+                Token::Percent => "%".to_string(),
+                Token::Comma => ",".to_string(),
+                Token::To => "to".to_string(),
+                Token::LeftBracket => "[".to_string(),
+                Token::RightBracket => "]".to_string(),
+                Token::Range => "..".to_string(),
+                Token::Step => "step".to_string()
+            }
+        }).collect();
+        spelled.join(" ")
+    }
+
+    fn decode(text: &str) -> Result<Vec<Token>, String> {
+        Lexer::tokenize(&mut text.chars())
+    }
+}
+
 struct LexerFuzzer<R: Rng> {
     random: R
 }
 
-impl<ActualR: Rng> Fuzzer for LexerFuzzer<ActualR> {
+impl<ActualR: Rng + SeedableRng> Fuzzer for LexerFuzzer<ActualR> {
     type Item = Vec<char>;
     type R = ActualR;
 
+    fn new(random: ActualR) -> LexerFuzzer<ActualR> {
+        LexerFuzzer { random }
+    }
+
     fn gen_one(&mut self) -> Vec<char> {
         let rng = self.random_generator();
         let num_characters = rng.gen_range(0, MAX_NUM_CHARACTERS + 1);
@@ -28,8 +94,22 @@ impl<ActualR: Rng> Fuzzer for LexerFuzzer<ActualR> {
         characters
     }
 
+    // lexing the characters and lexing their UTF-8 encoding agree
     fn run_test(&mut self, item: Vec<char>) {
-        let _ = Lexer::tokenize(&mut item.into_iter());
+        let text: String = item.iter().collect();
+        let tokens = Lexer::tokenize(&mut item.into_iter());
+        let streamed = Lexer::from_reader(text.as_bytes())
+            .map(|token| token.map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(tokens, streamed);
+    }
+
+    fn corpus_name(&self) -> Option<&'static str> {
+        Some("lexer")
+    }
+
+    fn shrink(&self, item: &Vec<char>) -> Vec<Vec<char>> {
+        item.shrink()
     }
     
     fn random_generator(&mut self) -> &mut ActualR {
@@ -39,8 +119,10 @@ impl<ActualR: Rng> Fuzzer for LexerFuzzer<ActualR> {
     
 #[test]
 fn run_lexer_fuzzer() {
-    let mut fuzzer = LexerFuzzer {
-        random: thread_rng()
-    };
-    fuzzer.run_tests();
+    run_fuzzer::<LexerFuzzer<StdRng>>();
+}
+
+#[test]
+fn replay_lexer_corpus() {
+    replay_corpus::<LexerFuzzer<StdRng>>();
 }
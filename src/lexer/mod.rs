@@ -1,16 +1,92 @@
+use std::cell::Cell;
 use std::fmt;
+use std::io::Read;
+use std::rc::Rc;
 
-use super::common::PushbackIterator;
+use super::common::{Position, PushbackIterator};
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+pub use self::utf8::Utf8Chars;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Token {
     Integer(u32),
+    // digits on both sides of a point, as written, like 9.81
+    Decimal(String),
+    // an integer with an i or j suffix, like 4i
+    Imaginary(u32),
+    // a day, as written, like 2026-10-18
+    Date(String),
+    // a time of day, as written, like 14:30 or 09:15:30.5
+    Time(String),
+    Identifier(String),
     Plus,
     Minus,
     Times,
     Div,
+    // @, the matrix product
+    MatrixTimes,
     LeftParen,
-    RightParen
+    RightParen,
+    Power,
+    // a run of superscript digits, raising what comes before to that power
+    Superscript(u32),
+    SquareRoot,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    // = or ==, which mean the same
+    Equal,
+    NotEqual,
+    // postfix; != is always not-equal, so 3! = 6 needs a space
+    Factorial,
+    Percent,
+    // separates the arguments of a function call, or the elements of a list
+    Comma,
+    // to or in, converting to a unit
+    To,
+    LeftBracket,
+    RightBracket,
+    // .., between the ends of a range
+    Range,
+    // step, giving the distance between a range's elements
+    Step
+}
+
+// the digit a superscript character stands for
+pub fn superscript_digit(c: char) -> Option<u32> {
+    match c {
+        '\u{2070}' => Some(0),
+        '\u{b9}' => Some(1),
+        '\u{b2}' => Some(2),
+        '\u{b3}' => Some(3),
+        '\u{2074}'..='\u{2079}' => Some(c as u32 - 0x2070),
+        _ => None
+    }
+}
+
+// identifiers are letters, digits and underscores, except that pi always
+// stands alone, so 2πr is three tokens
+fn is_letter(c: char) -> bool {
+    (c.is_alphabetic() || c == '_') && c != 'π'
+}
+
+// a degree sign can start a name, as in 90°, but not continue one
+fn starts_identifier(c: char) -> bool {
+    is_letter(c) || c == '°'
+}
+
+fn continues_identifier(c: char) -> bool {
+    is_letter(c) || (c.is_alphanumeric() && superscript_digit(c).is_none() && c != 'π')
+}
+
+// names that are operators rather than identifiers
+fn keyword(name: &str) -> Option<Token> {
+    match name {
+        "to" | "in" => Some(Token::To),
+        "step" => Some(Token::Step),
+        _ => None
+    }
 }
 
 impl fmt::Display for Token {
@@ -19,84 +95,326 @@ impl fmt::Display for Token {
     }
 }
 
-pub struct Lexer<'a> {
-    around: PushbackIterator<'a, char>
+// what a lossless token is; trivia is everything the parser never sees
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SyntaxKind {
+    Token(Token),
+    Whitespace,
+    Newline,
+    Comment
+}
+
+// a token together with the exact source text it was lexed from.
+// Concatenating the text of every syntax token reproduces the input.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SyntaxToken {
+    pub kind: SyntaxKind,
+    pub text: String
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LexErrorKind {
+    UnknownCharacter(char),
+    IntegerTooLarge,
+    InvalidUtf8,
+    Io(String)
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    // where the offending token, or the undecodable input, starts
+    pub position: Position
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            LexErrorKind::UnknownCharacter(c) =>
+                write!(f, "Unknown character: {}", c),
+            LexErrorKind::IntegerTooLarge =>
+                write!(f, "Integer literal too large (maximum is {})", u32::MAX),
+            LexErrorKind::InvalidUtf8 =>
+                write!(f, "Invalid UTF-8 at byte {}", self.position.offset),
+            LexErrorKind::Io(ref message) =>
+                write!(f, "Could not read input: {}", message)
+        }
+    }
+}
+
+// Reads tokens one at a time, as an iterator; it only reads as far into
+// its input as the token it is producing.
+pub struct Lexer<I: Iterator<Item = char>> {
+    around: PushbackIterator<I>,
+    // characters consumed since the start of the current token
+    text: String,
+    // where the current token starts
+    start: Position,
+    // set by a reader when its input cannot be decoded
+    input_error: Rc<Cell<Option<LexErrorKind>>>
 }
 
 #[derive(Debug, Eq, PartialEq)]
-enum LexerResult {
-    Ok(Token),
+enum LexerResult<A = Token> {
+    Ok(A),
     UnknownCharacter(char),
+    IntegerTooLarge,
     OutOfTokens
 }
 
-impl<'a> Lexer<'a> {
-    pub fn tokenize(input: &mut Iterator<Item = char>) -> Result<Vec<Token>, String> {
+impl Lexer<&mut dyn Iterator<Item = char>> {
+    pub fn tokenize(input: &mut dyn Iterator<Item = char>) -> Result<Vec<Token>, String> {
+        Lexer::new(input)
+            .map(|token| token.map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    // like tokenize, but keeps whitespace, newlines, and comments
+    pub fn tokenize_lossless(input: &mut dyn Iterator<Item = char>) -> Result<Vec<SyntaxToken>, String> {
         let mut lexer = Lexer::new(input);
         let mut tokens = vec![];
-
-        loop {
-            match lexer.next_token() {
-                LexerResult::Ok(token) => tokens.push(token),
-                LexerResult::UnknownCharacter(c) =>
-                    return Result::Err(format!("Unknown character: {}", c)),
-                LexerResult::OutOfTokens =>
-                    return Result::Ok(tokens)
-            }
+        while let Some(token) = lexer.next_lossless() {
+            tokens.push(token.map_err(|e| e.to_string())?);
         }
+        Result::Ok(tokens)
     }
+}
 
-    fn new(input: &mut Iterator<Item = char>) -> Lexer {
+impl<R: Read> Lexer<Utf8Chars<R>> {
+    // lexes UTF-8 text from the reader, reading only as much as needed
+    pub fn from_reader(reader: R) -> Lexer<Utf8Chars<R>> {
+        let input_error = Rc::new(Cell::new(None));
+        let chars = Utf8Chars::new(reader, input_error.clone());
+        let mut lexer = Lexer::new(chars);
+        lexer.input_error = input_error;
+        lexer
+    }
+}
+
+impl<I: Iterator<Item = char>> Lexer<I> {
+    pub fn new(input: I) -> Lexer<I> {
         Lexer {
-            around: PushbackIterator::new(input)
+            around: PushbackIterator::new(input),
+            text: String::new(),
+            start: Position::start(),
+            input_error: Rc::new(Cell::new(None))
         }
-    }    
+    }
+
+    // the next token, keeping whitespace, newlines, and comments
+    pub fn next_lossless(&mut self) -> Option<Result<SyntaxToken, LexError>> {
+        let result = self.next_syntax_token();
+        self.finish_token(result)
+    }
+
+    // A token read just before the input failed may have been cut short,
+    // so the failure is reported in its place.
+    fn finish_token<A>(&mut self, result: LexerResult<A>) -> Option<Result<A, LexError>> {
+        if let Some(kind) = self.input_error.take() {
+            return Some(Result::Err(LexError { kind, position: self.around.position() }));
+        }
+        let kind = match result {
+            LexerResult::Ok(token) => return Some(Result::Ok(token)),
+            LexerResult::UnknownCharacter(c) => LexErrorKind::UnknownCharacter(c),
+            LexerResult::IntegerTooLarge => LexErrorKind::IntegerTooLarge,
+            LexerResult::OutOfTokens => return None
+        };
+        Some(Result::Err(LexError { kind, position: self.start }))
+    }
 
     fn next_char(&mut self) -> Option<char> {
-        self.around.next()
+        let result = self.around.next();
+        if let Some(c) = result {
+            self.text.push(c);
+        }
+        result
+    }
+
+    // consumes the next character only if it satisfies the predicate
+    fn next_char_if<P: Fn(char) -> bool>(&mut self, predicate: P) -> Option<char> {
+        match self.around.peek() {
+            Some(&c) if predicate(c) => self.next_char(),
+            _ => None
+        }
+    }
+
+    // consumes up to, but not including, the end of the line
+    fn skip_comment(&mut self) {
+        while self.next_char_if(|c| c != '\n').is_some() {}
+    }
+
+    // consumes whitespace other than newlines
+    fn skip_whitespace(&mut self) {
+        while self.next_char_if(|c| c.is_whitespace() && c != '\n').is_some() {}
     }
     
-    fn read_integer(&mut self, start_digit: u32) -> u32 {
-        let mut result: u32 = start_digit;
-        let mut current_char = self.next_char();
+    // None if the literal does not fit in a u32; its digits are still consumed
+    fn read_integer(&mut self, start_digit: u32) -> Option<u32> {
+        let mut result = Some(start_digit);
+        while let Some(c) = self.next_char_if(|c| c.is_ascii_digit()) {
+            let d = c.to_digit(10).unwrap();
+            result = result
+                .and_then(|r| r.checked_mul(10))
+                .and_then(|r| r.checked_add(d));
+        }
+        result
+    } // read_integer
 
-        loop {
-            match current_char {
-                Some(c) => {
-                    match c.to_digit(10) {
-                        Some(d) => {
-                            result *= 10;
-                            result += d;
-                            current_char = self.next_char();
-                        },
-                        None => {
-                            self.around.push(c);
-                            return result;
-                        }
-                    }
-                },
-                None => {
-                    return result;
+    // consumes a point and the digits after it, if there are any, giving
+    // the whole literal; the point alone is left for whatever comes next
+    fn read_fraction(&mut self, start: usize) -> Option<String> {
+        let has_fraction = self.around.peek_nth(0) == Some(&'.') &&
+                           self.around.peek_nth(1).is_some_and(|d| d.is_ascii_digit());
+        if !has_fraction {
+            return None;
+        }
+        self.next_char();
+        while self.next_char_if(|c| c.is_ascii_digit()).is_some() {}
+        Some(self.text[start..].to_string())
+    }
+
+    // true if the next characters are the given pattern, in which 0
+    // stands for any digit, and no digit follows them
+    fn looking_at(&mut self, pattern: &str) -> bool {
+        let length = pattern.chars().count();
+        pattern.chars().enumerate().all(|(n, p)| match self.around.peek_nth(n) {
+            Some(c) if p == '0' => c.is_ascii_digit(),
+            Some(c) => *c == p,
+            None => false
+        }) && !self.around.peek_nth(length).is_some_and(|c| c.is_ascii_digit())
+    }
+
+    // consumes the rest of a date after the four digits of its year, or of
+    // a time after the one or two digits of its hour, giving the whole
+    // literal.  Anything else is left for whatever comes next, so
+    // 2026 - 10 - 18 is still a subtraction, and so is 2026-10-18.5.
+    fn read_date_or_time(&mut self, start: usize) -> Option<Token> {
+        let digits = self.text.len() - start;
+        if digits == 4 && self.looking_at("-00-00") &&
+           !(self.around.peek_nth(6) == Some(&'.') && self.around.peek_nth(7).is_some_and(|c| c.is_ascii_digit())) {
+            for _ in 0..6 {
+                self.next_char();
+            }
+            return Some(Token::Date(self.text[start..].to_string()));
+        }
+        if (digits == 1 || digits == 2) && self.looking_at(":00") {
+            for _ in 0..3 {
+                self.next_char();
+            }
+            if self.looking_at(":00") {
+                for _ in 0..3 {
+                    self.next_char();
                 }
-            } // match
-        } // loop
-    } // read_integer
+                self.read_fraction(start);
+            }
+            return Some(Token::Time(self.text[start..].to_string()));
+        }
+        None
+    }
+
+    // consumes an i or j directly after a literal, unless it starts a
+    // longer name: 4i and 4j are imaginary, but 4in is 4 followed by in
+    fn read_imaginary_suffix(&mut self) -> bool {
+        let is_suffix = match self.around.peek_nth(0) {
+            Some('i') | Some('j') => !self.around.peek_nth(1).is_some_and(|c| continues_identifier(*c)),
+            _ => false
+        };
+        if is_suffix {
+            self.next_char();
+        }
+        is_suffix
+    }
+
+    fn read_identifier(&mut self, start: char) -> String {
+        let mut result = start.to_string();
+        while let Some(c) = self.next_char_if(continues_identifier) {
+            result.push(c);
+        }
+        result
+    } // read_identifier
+
+    // like read_integer, for superscript digits
+    fn read_superscript(&mut self, start_digit: u32) -> Option<u32> {
+        let mut result = Some(start_digit);
+        while let Some(c) = self.next_char_if(|c| superscript_digit(c).is_some()) {
+            let d = superscript_digit(c).unwrap();
+            result = result
+                .and_then(|r| r.checked_mul(10))
+                .and_then(|r| r.checked_add(d));
+        }
+        result
+    }
+
+    // the two character operator if the next character completes it,
+    // otherwise the one character one
+    fn either(&mut self, second: char, long: Token, short: LexerResult) -> LexerResult {
+        match self.next_char_if(|c| c == second) {
+            Some(_) => LexerResult::Ok(long),
+            None => short
+        }
+    }
 
     fn next_token(&mut self) -> LexerResult {
         loop {
+            self.start = self.around.position();
             match self.next_char() {
                 Some('+') => return LexerResult::Ok(Token::Plus),
-                Some('-') => return LexerResult::Ok(Token::Minus),
-                Some('*') => return LexerResult::Ok(Token::Times),
-                Some('/') => return LexerResult::Ok(Token::Div),
+                Some('-') | Some('\u{2212}') => return LexerResult::Ok(Token::Minus),
+                Some('*') | Some('×') | Some('·') | Some('\u{22c5}') =>
+                    return LexerResult::Ok(Token::Times),
+                Some('/') | Some('÷') => return LexerResult::Ok(Token::Div),
+                Some('@') => return LexerResult::Ok(Token::MatrixTimes),
                 Some('(') => return LexerResult::Ok(Token::LeftParen),
                 Some(')') => return LexerResult::Ok(Token::RightParen),
+                Some('^') => return LexerResult::Ok(Token::Power),
+                Some('√') => return LexerResult::Ok(Token::SquareRoot),
+                Some('<') =>
+                    return self.either('=', Token::LessEqual, LexerResult::Ok(Token::Less)),
+                Some('>') =>
+                    return self.either('=', Token::GreaterEqual, LexerResult::Ok(Token::Greater)),
+                Some('=') =>
+                    return self.either('=', Token::Equal, LexerResult::Ok(Token::Equal)),
+                Some('!') =>
+                    return self.either('=', Token::NotEqual, LexerResult::Ok(Token::Factorial)),
+                Some('%') => return LexerResult::Ok(Token::Percent),
+                Some(',') => return LexerResult::Ok(Token::Comma),
+                Some('[') => return LexerResult::Ok(Token::LeftBracket),
+                Some(']') => return LexerResult::Ok(Token::RightBracket),
+                Some('.') =>
+                    return self.either('.', Token::Range, LexerResult::UnknownCharacter('.')),
+                Some('≤') => return LexerResult::Ok(Token::LessEqual),
+                Some('≥') => return LexerResult::Ok(Token::GreaterEqual),
+                Some('≠') => return LexerResult::Ok(Token::NotEqual),
+                Some('#') => self.skip_comment(),
                 Some(w) if w.is_whitespace() => {},
+                Some('π') => return LexerResult::Ok(Token::Identifier("π".to_string())),
+                Some(l) if starts_identifier(l) => {
+                    let name = self.read_identifier(l);
+                    return LexerResult::Ok(keyword(&name).unwrap_or(Token::Identifier(name)));
+                },
+                Some(s) if superscript_digit(s).is_some() =>
+                    return match self.read_superscript(superscript_digit(s).unwrap()) {
+                        Some(i) => LexerResult::Ok(Token::Superscript(i)),
+                        None => LexerResult::IntegerTooLarge
+                    },
                 Some(o) => {
                     match o.to_digit(10) {
-                        Some(d) =>
-                            return LexerResult::Ok(
-                                Token::Integer(self.read_integer(d))),
+                        Some(d) => {
+                            let start = self.text.len() - o.len_utf8();
+                            let integer = self.read_integer(d);
+                            if let Some(literal) = self.read_date_or_time(start) {
+                                return LexerResult::Ok(literal);
+                            }
+                            if let Some(decimal) = self.read_fraction(start) {
+                                return LexerResult::Ok(Token::Decimal(decimal));
+                            }
+                            let imaginary = self.read_imaginary_suffix();
+                            return match integer {
+                                Some(i) if imaginary => LexerResult::Ok(Token::Imaginary(i)),
+                                Some(i) => LexerResult::Ok(Token::Integer(i)),
+                                None => LexerResult::IntegerTooLarge
+                            }
+                        },
                         None => return LexerResult::UnknownCharacter(o)
                     }
                 },
@@ -104,8 +422,54 @@ impl<'a> Lexer<'a> {
             } // match
         } // loop
     } // next_token
+
+    fn next_syntax_token(&mut self) -> LexerResult<SyntaxToken> {
+        self.text.clear();
+        self.start = self.around.position();
+        let kind = match self.around.peek().cloned() {
+            Some('\n') => {
+                self.next_char();
+                SyntaxKind::Newline
+            },
+            Some('#') => {
+                self.skip_comment();
+                SyntaxKind::Comment
+            },
+            Some(w) if w.is_whitespace() => {
+                self.skip_whitespace();
+                SyntaxKind::Whitespace
+            },
+            Some(_) => {
+                match self.next_token() {
+                    LexerResult::Ok(token) => SyntaxKind::Token(token),
+                    LexerResult::UnknownCharacter(c) =>
+                        return LexerResult::UnknownCharacter(c),
+                    LexerResult::IntegerTooLarge =>
+                        return LexerResult::IntegerTooLarge,
+                    LexerResult::OutOfTokens =>
+                        return LexerResult::OutOfTokens
+                }
+            },
+            None => return LexerResult::OutOfTokens
+        };
+        LexerResult::Ok(SyntaxToken {
+            kind,
+            text: self.text.split_off(0)
+        })
+    } // next_syntax_token
 }
 
+impl<I: Iterator<Item = char>> Iterator for Lexer<I> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Result<Token, LexError>> {
+        let result = self.next_token();
+        self.finish_token(result)
+    }
+}
+
+mod utf8;
+
 #[cfg(test)]
 mod test;
 #[cfg(test)]
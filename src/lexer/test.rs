@@ -1,11 +1,14 @@
-use super::{Lexer, LexerResult, Token};
+use std::io::{self, Read};
+
+use common::Position;
+use super::{LexError, LexErrorKind, Lexer, LexerResult, SyntaxKind, SyntaxToken, Token};
 
 #[test]
 fn read_integer_works_on_empty() {
     let mut it = vec![].into_iter();
     let mut lexer = Lexer::new(&mut it);
     let received = lexer.read_integer(5);
-    assert_eq!(5, received);
+    assert_eq!(Some(5), received);
 }
 
 #[test]
@@ -13,7 +16,22 @@ fn read_integer_works_on_nonempty() {
     let mut it = vec!['6', '7'].into_iter();
     let mut lexer = Lexer::new(&mut it);
     let received = lexer.read_integer(5);
-    assert_eq!(567, received);
+    assert_eq!(Some(567), received);
+}
+
+#[test]
+fn read_integer_works_at_max() {
+    let mut it = "294967295".chars();
+    let mut lexer = Lexer::new(&mut it);
+    assert_eq!(Some(4294967295), lexer.read_integer(4));
+}
+
+#[test]
+fn read_integer_fails_past_max() {
+    let mut it = "2949672960+".chars();
+    let mut lexer = Lexer::new(&mut it);
+    assert_eq!(None, lexer.read_integer(4));
+    assert_eq!(LexerResult::Ok(Token::Plus), lexer.next_token());
 }
 
 #[test]
@@ -46,10 +64,10 @@ fn read_all_tokens() {
 
 #[test]
 fn read_tokens_bad_character() {
-    let mut it = vec!['+', 'f', '-'].into_iter();
+    let mut it = vec!['+', '$', '-'].into_iter();
     let mut lexer = Lexer::new(&mut it);
     assert_eq!(LexerResult::Ok(Token::Plus), lexer.next_token());
-    assert_eq!(LexerResult::UnknownCharacter('f'), lexer.next_token());
+    assert_eq!(LexerResult::UnknownCharacter('$'), lexer.next_token());
     assert_eq!(LexerResult::Ok(Token::Minus), lexer.next_token());
     assert_eq!(LexerResult::OutOfTokens, lexer.next_token());
 }
@@ -77,3 +95,257 @@ fn full_tokenization_is_ok() {
     assert_eq!(Result::Ok(expected),
                Lexer::tokenize(&mut it));
 }
+
+#[test]
+fn comments_are_skipped() {
+    let mut it = "1 # comment\n+ 2".chars();
+    assert_eq!(Result::Ok(vec![Token::Integer(1),
+                               Token::Plus,
+                               Token::Integer(2)]),
+               Lexer::tokenize(&mut it));
+}
+
+#[test]
+fn lossless_tokenization_keeps_trivia() {
+    let mut it = " 12+ # hi\n3".chars();
+    let expected = vec![
+        SyntaxToken { kind: SyntaxKind::Whitespace, text: " ".to_string() },
+        SyntaxToken { kind: SyntaxKind::Token(Token::Integer(12)), text: "12".to_string() },
+        SyntaxToken { kind: SyntaxKind::Token(Token::Plus), text: "+".to_string() },
+        SyntaxToken { kind: SyntaxKind::Whitespace, text: " ".to_string() },
+        SyntaxToken { kind: SyntaxKind::Comment, text: "# hi".to_string() },
+        SyntaxToken { kind: SyntaxKind::Newline, text: "\n".to_string() },
+        SyntaxToken { kind: SyntaxKind::Token(Token::Integer(3)), text: "3".to_string() }];
+    assert_eq!(Result::Ok(expected), Lexer::tokenize_lossless(&mut it));
+}
+
+#[test]
+fn identifiers_are_read() {
+    let mut it = "x1 + _rate*2y".chars();
+    assert_eq!(Result::Ok(vec![Token::Identifier("x1".to_string()),
+                               Token::Plus,
+                               Token::Identifier("_rate".to_string()),
+                               Token::Times,
+                               Token::Integer(2),
+                               Token::Identifier("y".to_string())]),
+               Lexer::tokenize(&mut it));
+}
+
+#[test]
+fn lexer_is_an_iterator_that_continues_past_errors() {
+    let lexer = Lexer::new("1\n $+".chars());
+    assert_eq!(vec![Result::Ok(Token::Integer(1)),
+                    Result::Err(LexError {
+                        kind: LexErrorKind::UnknownCharacter('$'),
+                        position: Position { line: 2, column: 2, offset: 3 }
+                    }),
+                    Result::Ok(Token::Plus)],
+               lexer.collect::<Vec<_>>());
+}
+
+#[test]
+fn reader_is_decoded_as_utf8() {
+    let lexer = Lexer::from_reader("x\u{e9} * 2".as_bytes());
+    assert_eq!(Result::Ok(vec![Token::Identifier("x\u{e9}".to_string()),
+                               Token::Times,
+                               Token::Integer(2)]),
+               lexer.collect::<Result<Vec<_>, _>>());
+}
+
+#[test]
+fn reader_is_read_lazily() {
+    let lexer = Lexer::from_reader(io::repeat(b'+'));
+    assert_eq!(3, lexer.take(3).filter(|token| *token == Result::Ok(Token::Plus)).count());
+}
+
+#[test]
+fn invalid_utf8_is_reported_where_it_starts() {
+    let mut lexer = Lexer::from_reader(&b"1 +\n\xff2"[..]);
+    assert_eq!(Some(Result::Ok(Token::Integer(1))), lexer.next());
+    assert_eq!(Some(Result::Ok(Token::Plus)), lexer.next());
+    let error = LexError {
+        kind: LexErrorKind::InvalidUtf8,
+        position: Position { line: 2, column: 1, offset: 4 }
+    };
+    assert_eq!("Invalid UTF-8 at byte 4", error.to_string());
+    assert_eq!(Some(Result::Err(error)), lexer.next());
+    assert_eq!(None, lexer.next());
+}
+
+#[test]
+fn truncated_utf8_is_invalid() {
+    let mut lexer = Lexer::from_reader(&b"12\xe2\x88"[..]);
+    assert_eq!(Some(LexErrorKind::InvalidUtf8),
+               lexer.next().and_then(|token| token.err()).map(|e| e.kind));
+}
+
+struct FailingReader;
+
+impl Read for FailingReader {
+    fn read(&mut self, _buffer: &mut [u8]) -> io::Result<usize> {
+        Result::Err(io::Error::other("disk on fire"))
+    }
+}
+
+#[test]
+fn read_errors_are_reported() {
+    let mut lexer = Lexer::from_reader(FailingReader);
+    assert_eq!(Some(LexErrorKind::Io("disk on fire".to_string())),
+               lexer.next().and_then(|token| token.err()).map(|e| e.kind));
+}
+
+fn tokens(source: &str) -> Result<Vec<Token>, String> {
+    Lexer::tokenize(&mut source.chars())
+}
+
+#[test]
+fn unicode_operators_are_read() {
+    assert_eq!(Result::Ok(vec![Token::Integer(3), Token::Times,
+                               Token::Integer(4), Token::Div,
+                               Token::Integer(2), Token::Minus,
+                               Token::Integer(1), Token::Times,
+                               Token::Integer(5), Token::Times,
+                               Token::Integer(6)]),
+               tokens("3 × 4 ÷ 2 \u{2212} 1 · 5 \u{22c5} 6"));
+}
+
+#[test]
+fn comparisons_are_read() {
+    assert_eq!(Result::Ok(vec![Token::Less, Token::LessEqual, Token::LessEqual,
+                               Token::Greater, Token::GreaterEqual, Token::GreaterEqual,
+                               Token::Equal, Token::NotEqual, Token::NotEqual]),
+               tokens("< <= ≤ > >= ≥ == != ≠"));
+    assert_eq!(Result::Ok(vec![Token::Less, Token::Minus, Token::Integer(1)]),
+               tokens("<-1"));
+}
+
+#[test]
+fn lone_equals_is_equality() {
+    assert_eq!(Result::Ok(vec![Token::Integer(1), Token::Equal, Token::Integer(1)]), tokens("1 = 1"));
+    assert_eq!(tokens("1 == 1"), tokens("1 = 1"));
+}
+
+#[test]
+fn factorial_and_percent_are_read() {
+    assert_eq!(Result::Ok(vec![Token::Integer(3),
+                               Token::Factorial,
+                               Token::Factorial,
+                               Token::Integer(10),
+                               Token::Percent]),
+               tokens("3!! 10%"));
+    // maximal munch makes this a comparison
+    assert_eq!(Result::Ok(vec![Token::Integer(3), Token::NotEqual, Token::Integer(6)]),
+               tokens("3!=6"));
+}
+
+#[test]
+fn superscripts_are_read_as_one_exponent() {
+    assert_eq!(Result::Ok(vec![Token::Identifier("x".to_string()),
+                               Token::Superscript(2),
+                               Token::Identifier("y".to_string()),
+                               Token::Superscript(10),
+                               Token::Integer(3),
+                               Token::Superscript(3)]),
+               tokens("x²y¹⁰3³"));
+}
+
+#[test]
+fn root_and_pi_are_read() {
+    assert_eq!(Result::Ok(vec![Token::SquareRoot,
+                               Token::Integer(2),
+                               Token::Identifier("π".to_string()),
+                               Token::Identifier("r".to_string()),
+                               Token::Power,
+                               Token::Identifier("pi".to_string())]),
+               tokens("√2πr^pi"));
+}
+
+#[test]
+fn pi_never_continues_a_name() {
+    assert_eq!(Result::Ok(vec![Token::Identifier("r".to_string()), Token::Identifier("π".to_string())]),
+               tokens("rπ"));
+    assert_eq!(Result::Ok(vec![Token::Integer(2),
+                               Token::Identifier("π".to_string()),
+                               Token::Identifier("r".to_string())]),
+               tokens("2πr"));
+}
+
+#[test]
+fn imaginary_suffix_is_part_of_the_literal() {
+    let x = Token::Identifier("x".to_string());
+    assert_eq!(Result::Ok(vec![Token::Integer(3), Token::Plus, Token::Imaginary(4),
+                               Token::Minus, Token::Imaginary(2), Token::Superscript(2)]),
+               tokens("3+4i-2j²"));
+    assert_eq!(Result::Ok(vec![Token::Integer(4), Token::To,
+                               Token::Integer(4), Token::Identifier("i2".to_string()),
+                               Token::Integer(4), Token::Identifier("i".to_string()), x]),
+               tokens("4in 4i2 4 i x"));
+}
+
+#[test]
+fn calls_are_read() {
+    assert_eq!(Result::Ok(vec![Token::Identifier("abs".to_string()),
+                               Token::LeftParen,
+                               Token::Integer(1),
+                               Token::Comma,
+                               Token::Imaginary(2),
+                               Token::RightParen]),
+               tokens("abs(1, 2i)"));
+}
+
+#[test]
+fn decimals_need_digits_after_the_point() {
+    assert_eq!(Result::Ok(vec![Token::Decimal("9.81".to_string()),
+                               Token::Decimal("007.50".to_string()),
+                               Token::Decimal("99999999999.5".to_string())]),
+               tokens("9.81 007.50 99999999999.5"));
+    assert_eq!(Result::Ok(vec![Token::Decimal("2.5".to_string()), Token::Identifier("i".to_string())]),
+               tokens("2.5i"));
+    assert!(tokens("1.").is_err());
+    assert!(tokens(".5").is_err());
+}
+
+#[test]
+fn units_are_names_and_to_is_a_keyword() {
+    let name = |s: &str| Token::Identifier(s.to_string());
+    assert_eq!(Result::Ok(vec![Token::Integer(60), name("mph"), Token::To, name("m"), Token::Div, name("s")]),
+               tokens("60 mph to m/s"));
+    assert_eq!(Result::Ok(vec![Token::Integer(90), name("°"), name("x"), name("°"), Token::To, name("into")]),
+               tokens("90° x° in into"));
+    assert_eq!(Result::Ok(vec![name("µm"), name("Ω"), name("°C")]), tokens("µm Ω °C"));
+}
+
+#[test]
+fn dates_and_times_are_single_tokens() {
+    assert_eq!(Result::Ok(vec![Token::Date("2026-10-18".to_string()),
+                               Token::Plus,
+                               Token::Time("9:30".to_string()),
+                               Token::Time("14:30:15.5".to_string())]),
+               tokens("2026-10-18 + 9:30 14:30:15.5"));
+    assert_eq!(Result::Ok(vec![Token::Integer(2026), Token::Minus, Token::Integer(10), Token::Minus, Token::Integer(18)]),
+               tokens("2026 - 10 - 18"));
+    assert_eq!(Result::Ok(vec![Token::Integer(20261), Token::Minus, Token::Integer(10), Token::Minus, Token::Integer(18)]),
+               tokens("20261-10-18"));
+    assert_eq!(Result::Ok(vec![Token::Integer(2026), Token::Minus, Token::Integer(10), Token::Minus,
+                               Token::Decimal("18.5".to_string())]),
+               tokens("2026-10-18.5"));
+    assert!(tokens("123:45").is_err());
+}
+
+#[test]
+fn brackets_ranges_and_steps_are_tokens() {
+    assert_eq!(Result::Ok(vec![Token::LeftBracket, Token::Integer(1), Token::Comma, Token::Decimal("2.5".to_string()),
+                               Token::RightBracket, Token::LeftBracket, Token::Integer(0), Token::RightBracket]),
+               tokens("[1, 2.5][0]"));
+    assert_eq!(Result::Ok(vec![Token::Integer(1), Token::Range, Token::Integer(10), Token::Step, Token::Integer(2)]),
+               tokens("1..10 step 2"));
+    assert_eq!(Result::Ok(vec![Token::Date("2026-01-01".to_string()), Token::Range, Token::Date("2026-02-01".to_string())]),
+               tokens("2026-01-01..2026-02-01"));
+    assert!(tokens("1 . 2").is_err());
+}
+
+#[test]
+fn at_sign_is_the_matrix_product() {
+    assert_eq!(Result::Ok(vec![Token::Identifier("a".to_string()), Token::MatrixTimes, Token::Identifier("b".to_string())]),
+               tokens("a@b"));
+}
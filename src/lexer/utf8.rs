@@ -0,0 +1,74 @@
+use std::cell::Cell;
+use std::io::{BufReader, Bytes, Read};
+use std::rc::Rc;
+use std::str;
+
+use super::LexErrorKind;
+
+// Decodes characters from a reader as they are needed.  The stream ends at
+// the first invalid sequence or read error, which is left in the shared
+// slot for the lexer to report.  Interrupted reads are retried.
+pub struct Utf8Chars<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    error: Rc<Cell<Option<LexErrorKind>>>,
+    failed: bool
+}
+
+impl<R: Read> Utf8Chars<R> {
+    pub(super) fn new(reader: R, error: Rc<Cell<Option<LexErrorKind>>>) -> Utf8Chars<R> {
+        Utf8Chars {
+            bytes: BufReader::new(reader).bytes(),
+            error,
+            failed: false
+        }
+    }
+
+    fn fail(&mut self, kind: LexErrorKind) -> Option<char> {
+        self.error.set(Some(kind));
+        self.failed = true;
+        None
+    }
+}
+
+// how many bytes a sequence starting with this byte takes
+fn sequence_length(first: u8) -> Option<usize> {
+    match first {
+        0x00..=0x7F => Some(1),
+        0xC2..=0xDF => Some(2),
+        0xE0..=0xEF => Some(3),
+        0xF0..=0xF4 => Some(4),
+        _ => None
+    }
+}
+
+impl<R: Read> Iterator for Utf8Chars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.failed {
+            return None;
+        }
+        let mut buffer = [0; 4];
+        buffer[0] = match self.bytes.next() {
+            Some(Ok(byte)) => byte,
+            Some(Err(e)) => return self.fail(LexErrorKind::Io(e.to_string())),
+            None => return None
+        };
+        let length = match sequence_length(buffer[0]) {
+            Some(length) => length,
+            None => return self.fail(LexErrorKind::InvalidUtf8)
+        };
+        for slot in buffer.iter_mut().take(length).skip(1) {
+            *slot = match self.bytes.next() {
+                Some(Ok(byte)) => byte,
+                Some(Err(e)) => return self.fail(LexErrorKind::Io(e.to_string())),
+                None => return self.fail(LexErrorKind::InvalidUtf8)
+            };
+        }
+        match str::from_utf8(&buffer[..length]) {
+            Ok(decoded) => decoded.chars().next(),
+            Err(_) => self.fail(LexErrorKind::InvalidUtf8)
+        }
+    }
+}
+
@@ -0,0 +1,13 @@
+pub mod common;
+pub mod value;
+pub mod currency;
+pub mod lexer;
+pub mod parser;
+pub mod interpreter;
+pub mod syntax;
+pub mod formatter;
+pub mod visit;
+pub mod fold;
+pub mod optimizer;
+pub mod calculus;
+pub mod vm;
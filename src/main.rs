@@ -1,19 +1,39 @@
 use std::env;
+use std::fs::File;
+use std::io::{self, Read};
 
-mod common;
-mod lexer;
-mod parser;
-mod interpreter;
+extern crate calculator;
 
-use lexer::Lexer;
-use parser::Parser;
-use interpreter::Interpreter;
+use calculator::calculus::derivative_call;
+use calculator::currency::RateTable;
+use calculator::formatter;
+use calculator::lexer::Lexer;
+use calculator::parser::{Expression, Parser};
+use calculator::interpreter::{Bindings, Interpreter};
+use calculator::value::{ComplexForm, NumericMode, Value};
 
-fn handle_string(input: &String) {
+// An amount of money says which day's rates it was converted at.  A
+// derivative is printed before it is evaluated, which needs a point,
+// as in diff(x^2, x, 3), since nothing is bound here.
+fn handle_string(input: &str, form: ComplexForm, mode: NumericMode, rates: Option<&RateTable>) {
+    let bindings = rates.map_or_else(Bindings::new, |table| table.bindings());
     match Lexer::tokenize(&mut input.chars()) {
         Ok(tokens) => match Parser::parse(&tokens) {
             Ok(expression) => {
-                println!("Interpreter result: {}", expression.evaluate_toplevel());
+                match expression {
+                    Expression::Call(ref name, ref arguments) if name == "diff" => {
+                        if let Ok(derivative) = derivative_call(arguments) {
+                            println!("Derivative: {}", derivative);
+                        }
+                    },
+                    _ => {}
+                }
+                let result = expression.evaluate_toplevel_in(&bindings).in_form(form).in_mode(mode);
+                match (result.value(), rates) {
+                    (Some(Value::Quantity(q)), Some(table)) if q.unit.is_currency() =>
+                        println!("Interpreter result: {} (rates of {})", result, table.date()),
+                    _ => println!("Interpreter result: {}", result)
+                }
             },
             Err(parse_error) => println!("Parse error: {}", parse_error)
         },
@@ -21,11 +41,67 @@ fn handle_string(input: &String) {
     }
 }
 
+// formats the named file, or standard input if there is no name, as it
+// is read
+fn handle_fmt(path: Option<&String>) {
+    let formatted = match path {
+        Some(p) => match File::open(p) {
+            Ok(file) => formatter::format_reader(file),
+            Err(io_error) => {
+                println!("Could not read script: {}", io_error);
+                return;
+            }
+        },
+        None => formatter::format_reader(io::stdin().lock())
+    };
+    match formatted {
+        Ok(formatted) => print!("{}", formatted),
+        Err(error) => println!("Format error: {}", error)
+    }
+}
+
+fn read_rates(path: &str) -> Result<RateTable, String> {
+    let mut source = String::new();
+    File::open(path).and_then(|mut f| f.read_to_string(&mut source)).map_err(|e| e.to_string())?;
+    RateTable::parse(&source)
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() == 2 {
-        handle_string(&args[1]);
+    if args.len() >= 2 && args.len() <= 3 && args[1] == "fmt" {
+        handle_fmt(args.get(2));
+        return;
+    }
+    let mut form = ComplexForm::Rectangular;
+    let mut mode = NumericMode::Real;
+    let mut rates = None;
+    let mut next = 1;
+    loop {
+        match args.get(next).map(|a| a.as_str()) {
+            Some("--polar") => {
+                form = ComplexForm::Polar;
+                next += 1;
+            },
+            Some("--rational") => {
+                mode = NumericMode::Rational;
+                next += 1;
+            },
+            Some("--rates") if next + 1 < args.len() => {
+                match read_rates(&args[next + 1]) {
+                    Ok(table) => rates = Some(table),
+                    Err(error) => {
+                        println!("Could not read rates: {}", error);
+                        return;
+                    }
+                }
+                next += 2;
+            },
+            _ => break
+        }
+    }
+    if args.len() == next + 1 {
+        handle_string(&args[next], form, mode, rates.as_ref());
     } else {
-        println!("Needs an arithmetic expression (single argument)");
+        println!("Needs an arithmetic expression (single argument, optionally after --polar, --rational or --rates FILE), or fmt [FILE]");
     }
 }
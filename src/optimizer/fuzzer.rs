@@ -0,0 +1,76 @@
+extern crate rand;
+
+use common::fuzzer::{replay_corpus, run_fuzzer, Fuzzer};
+use fold::Folder;
+use interpreter::{Bindings, Interpreter};
+use interpreter::fuzzer::rand_bindings;
+use parser::Expression;
+use parser::fuzzer::{rand_expression, shrink_paired, EXPRESSION_MAX_DEPTH};
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
+use super::optimize;
+
+const MAX_INTEGER: u32 = 3;
+
+// shrinks literals so identities and successful folds actually come up
+struct SmallIntegers;
+
+impl Folder for SmallIntegers {
+    fn fold_integer(&mut self, i: u32) -> Expression {
+        Expression::Integer(i % (MAX_INTEGER + 1))
+    }
+}
+
+struct OptimizerFuzzer<R: Rng> {
+    random: R
+}
+
+impl<ActualR: Rng + SeedableRng> Fuzzer for OptimizerFuzzer<ActualR> {
+    type Item = (Expression, Bindings);
+    type R = ActualR;
+
+    fn new(random: ActualR) -> OptimizerFuzzer<ActualR> {
+        OptimizerFuzzer { random }
+    }
+
+    fn gen_one(&mut self) -> (Expression, Bindings) {
+        let e = rand_expression(self.random_generator(), EXPRESSION_MAX_DEPTH);
+        let small = self.random.gen();
+        let bindings = rand_bindings(self.random_generator());
+        if small {
+            (SmallIntegers.fold_expression(e), bindings)
+        } else {
+            (e, bindings)
+        }
+    }
+
+    fn run_test(&mut self, (item, bindings): (Expression, Bindings)) {
+        let expected = item.evaluate_in(&bindings);
+        let printed = item.to_string();
+        let optimized = optimize(item);
+        assert_eq!(expected, optimized.evaluate_in(&bindings),
+                   "{} optimized to {} with {:?}", printed, optimized, bindings);
+    }
+
+    fn corpus_name(&self) -> Option<&'static str> {
+        Some("optimizer")
+    }
+
+    fn shrink(&self, item: &(Expression, Bindings)) -> Vec<(Expression, Bindings)> {
+        shrink_paired(item)
+    }
+
+    fn random_generator(&mut self) -> &mut ActualR {
+        &mut self.random
+    }
+}
+
+#[test]
+fn run_optimizer_fuzzer() {
+    run_fuzzer::<OptimizerFuzzer<StdRng>>();
+}
+
+#[test]
+fn replay_optimizer_corpus() {
+    replay_corpus::<OptimizerFuzzer<StdRng>>();
+}
@@ -0,0 +1,197 @@
+use super::calculus::solves_equation;
+use super::fold::{self, Folder};
+use super::interpreter::Interpreter;
+use super::parser::{Expression, Operation};
+use super::value::Value;
+use super::visit::Visitor;
+
+// Simplifies an expression without changing what it evaluates to,
+// including which error it raises.  Constant subtrees are folded only
+// when they evaluate successfully, so division by zero and overflow still
+// happen at runtime.  Identities are only removed when the operand they
+// leave behind fails or succeeds exactly as the whole would: x * 0 is
+// left alone, since x may fail.  Nor are they removed from anything that
+// may be a date, as x * 1 and --x fail for one; variables other than
+// today and now are taken to be numbers or quantities.  Derivatives are
+// left alone.
+pub struct Optimizer;
+
+pub fn optimize(e: Expression) -> Expression {
+    Optimizer.fold_expression(e)
+}
+
+// the source form of a value, if it has one.  Reals, big integers,
+// fractions, complex numbers, quantities, dates and lists have none.
+fn literal(value: Value) -> Option<Expression> {
+    let value = match value {
+        Value::Integer(i) => i,
+        Value::Real(_) | Value::Big(_) | Value::Rational(_) | Value::Complex(_) | Value::Quantity(_) |
+        Value::DateTime(_) | Value::List(_) => return Option::None
+    };
+    if value >= 0 {
+        Option::Some(Expression::Integer(value as u32))
+    } else if value != i32::MIN {
+        Option::Some(Expression::UnaryMinus(Box::new(Expression::Integer((-value) as u32))))
+    } else {
+        // -2147483648 is the negation of an out-of-range literal
+        Option::None
+    }
+}
+
+fn constant_value(e: &Expression) -> Option<Value> {
+    match e {
+        Expression::Integer(_) => e.evaluate().ok(),
+        Expression::UnaryMinus(inner) => match **inner {
+            Expression::Integer(_) => e.evaluate().ok(),
+            _ => Option::None
+        },
+        _ => Option::None
+    }
+}
+
+fn is_constant(e: &Expression) -> bool {
+    constant_value(e).is_some()
+}
+
+fn is_constant_value(e: &Expression, value: i32) -> bool {
+    constant_value(e) == Option::Some(Value::Integer(value))
+}
+
+struct VariableFinder {
+    found: bool
+}
+
+impl Visitor for VariableFinder {
+    fn visit_variable(&mut self, _name: &str) {
+        self.found = true;
+    }
+}
+
+fn has_variables(e: &Expression) -> bool {
+    let mut finder = VariableFinder { found: false };
+    finder.visit_expression(e);
+    finder.found
+}
+
+struct DateFinder {
+    found: bool
+}
+
+impl Visitor for DateFinder {
+    fn visit_date(&mut self, _text: &str) {
+        self.found = true;
+    }
+
+    fn visit_time(&mut self, _text: &str) {
+        self.found = true;
+    }
+
+    fn visit_variable(&mut self, name: &str) {
+        self.found |= name == "today" || name == "now";
+    }
+}
+
+fn may_be_date(e: &Expression) -> bool {
+    let mut finder = DateFinder { found: false };
+    finder.visit_expression(e);
+    finder.found
+}
+
+fn holds_min(value: &Value) -> bool {
+    match value {
+        Value::Integer(i) => *i == i32::MIN,
+        Value::List(values) => values.iter().any(holds_min),
+        _ => false
+    }
+}
+
+// whether negating this twice is the same as leaving it alone.  A variable
+// might be bound to i32::MIN, whose negation overflows, and so might an
+// element of a list.
+fn never_evaluates_to_min(e: &Expression) -> bool {
+    if has_variables(e) || may_be_date(e) {
+        return false;
+    }
+    match e.evaluate() {
+        Ok(value) => !holds_min(&value),
+        Err(_) => true
+    }
+}
+
+// replaces an expression with its value if it evaluates successfully.
+// Anything naming a variable is left alone, even if it is a constant like
+// π, since a binding could shadow it.
+fn fold_constant(e: Expression) -> Expression {
+    if has_variables(&e) {
+        return e;
+    }
+    match e.evaluate().ok().and_then(literal) {
+        Some(folded) => folded,
+        None => e
+    }
+}
+
+fn is_commutative(op: &Operation) -> bool {
+    matches!(op, Operation::Plus | Operation::Times)
+}
+
+fn simplify_binary_operation(e1: Expression, op: Operation, e2: Expression) -> Expression {
+    // canonical order puts a constant on the right.  A constant never
+    // fails, so evaluating the other operand first cannot change the error.
+    let (e1, e2) = if is_commutative(&op) && is_constant(&e1) && !is_constant(&e2) {
+        (e2, e1)
+    } else {
+        (e1, e2)
+    };
+
+    match op {
+        Operation::Plus | Operation::Minus if is_constant_value(&e2, 0) => e1,
+        Operation::Times | Operation::Div if is_constant_value(&e2, 1) && !may_be_date(&e1) => e1,
+        _ => Expression::BinaryOperation(Box::new(e1), op, Box::new(e2))
+    }
+}
+
+impl Folder for Optimizer {
+    // diff and the equation solver work on their arguments as written, so
+    // they are left as they are.  So is solve(A, b) if simplifying A would
+    // leave an equation, as (0 == x) - 0 does, making it solve for x.
+    fn fold_call(&mut self, name: String, arguments: Vec<Expression>) -> Expression {
+        if name == "diff" || solves_equation(&name, &arguments) {
+            return Expression::Call(name, arguments);
+        }
+        match fold::walk_call(self, name.clone(), arguments.clone()) {
+            Expression::Call(ref folded_name, ref folded) if solves_equation(folded_name, folded) =>
+                Expression::Call(name, arguments),
+            folded => folded
+        }
+    }
+
+    fn fold_unary_minus(&mut self, e: Expression) -> Expression {
+        match self.fold_expression(e) {
+            Expression::UnaryMinus(inner) if !is_constant(&inner) &&
+                                             never_evaluates_to_min(&inner) => *inner,
+            folded => fold_constant(Expression::UnaryMinus(Box::new(folded)))
+        }
+    }
+
+    fn fold_binary_operation(&mut self,
+                             e1: Expression,
+                             op: Operation,
+                             e2: Expression) -> Expression {
+        match fold::walk_binary_operation(self, e1, op, e2) {
+            Expression::BinaryOperation(new_e1, new_op, new_e2) => {
+                if is_constant(&new_e1) && is_constant(&new_e2) {
+                    fold_constant(Expression::BinaryOperation(new_e1, new_op, new_e2))
+                } else {
+                    simplify_binary_operation(*new_e1, new_op, *new_e2)
+                }
+            },
+            other => other
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
+#[cfg(test)]
+mod fuzzer;
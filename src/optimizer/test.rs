@@ -0,0 +1,150 @@
+use interpreter::Interpreter;
+use parser::{Expression, Operation};
+use super::optimize;
+
+fn int(i: u32) -> Expression {
+    Expression::Integer(i)
+}
+
+fn neg(e: Expression) -> Expression {
+    Expression::UnaryMinus(Box::new(e))
+}
+
+fn binop(e1: Expression, op: Operation, e2: Expression) -> Expression {
+    Expression::BinaryOperation(Box::new(e1), op, Box::new(e2))
+}
+
+// 1 / 0, which can never be folded
+fn failing() -> Expression {
+    binop(int(1), Operation::Div, int(0))
+}
+
+fn assert_optimizes(e: Expression, expected: Expression) {
+    let original = e.evaluate();
+    let optimized = optimize(e);
+    assert_eq!(expected, optimized);
+    assert_eq!(original, optimized.evaluate());
+}
+
+#[test]
+fn constants_fold() {
+    assert_optimizes(binop(int(2), Operation::Times, binop(int(3), Operation::Plus, int(4))),
+                     int(14));
+}
+
+#[test]
+fn negative_results_fold_to_negated_literals() {
+    assert_optimizes(binop(int(2), Operation::Minus, int(5)), neg(int(3)));
+}
+
+#[test]
+fn division_by_zero_is_not_folded() {
+    assert_optimizes(binop(binop(int(2), Operation::Plus, int(2)), Operation::Div, int(0)),
+                     binop(int(4), Operation::Div, int(0)));
+}
+
+#[test]
+fn overflow_is_not_folded() {
+    assert_optimizes(binop(int(2147483647), Operation::Plus, int(1)),
+                     binop(int(2147483647), Operation::Plus, int(1)));
+}
+
+#[test]
+fn negation_overflow_is_not_folded() {
+    // -(-2147483647 - 1)
+    let min = binop(neg(int(2147483647)), Operation::Minus, int(1));
+    let e = neg(min);
+    let expected = neg(binop(neg(int(2147483647)), Operation::Minus, int(1)));
+    assert_optimizes(e, expected);
+}
+
+#[test]
+fn identities_are_removed() {
+    assert_optimizes(binop(failing(), Operation::Times, int(1)), failing());
+    assert_optimizes(binop(int(1), Operation::Times, failing()), failing());
+    assert_optimizes(binop(failing(), Operation::Plus, int(0)), failing());
+    assert_optimizes(binop(int(0), Operation::Plus, failing()), failing());
+    assert_optimizes(binop(failing(), Operation::Minus, int(0)), failing());
+    assert_optimizes(binop(failing(), Operation::Div, int(1)), failing());
+}
+
+#[test]
+fn double_negation_is_removed() {
+    assert_optimizes(neg(neg(failing())), failing());
+}
+
+#[test]
+fn times_zero_is_kept() {
+    assert_optimizes(binop(failing(), Operation::Times, int(0)),
+                     binop(failing(), Operation::Times, int(0)));
+}
+
+#[test]
+fn commutative_operations_put_constants_right() {
+    assert_optimizes(binop(int(2), Operation::Plus, failing()),
+                     binop(failing(), Operation::Plus, int(2)));
+    assert_optimizes(binop(int(2), Operation::Times, failing()),
+                     binop(failing(), Operation::Times, int(2)));
+}
+
+#[test]
+fn noncommutative_operations_keep_order() {
+    assert_optimizes(binop(int(2), Operation::Minus, failing()),
+                     binop(int(2), Operation::Minus, failing()));
+}
+
+fn var(name: &str) -> Expression {
+    Expression::Variable(name.to_string())
+}
+
+#[test]
+fn identities_are_removed_around_variables() {
+    assert_optimizes(binop(int(1), Operation::Times, binop(var("x"), Operation::Plus, int(0))),
+                     var("x"));
+}
+
+#[test]
+fn double_negation_of_variable_is_kept() {
+    // x may be bound to -2147483648
+    assert_optimizes(neg(neg(var("x"))), neg(neg(var("x"))));
+}
+
+#[test]
+fn dates_are_never_simplified_as_numbers() {
+    let date = Expression::Date("2026-10-18".to_string());
+    let e = binop(date, Operation::Times, int(1));
+    assert_eq!(e, optimize(e.clone()));
+    let e = binop(var("today"), Operation::Div, int(1));
+    assert_eq!(e, optimize(e.clone()));
+}
+
+#[test]
+fn double_negation_of_a_list_holding_min_is_kept() {
+    let min = binop(neg(int(2147483647)), Operation::Minus, int(1));
+    let e = neg(neg(Expression::List(vec![min])));
+    assert_eq!(e.evaluate(), optimize(e.clone()).evaluate());
+}
+
+#[test]
+fn derivatives_are_left_as_written() {
+    let x = Expression::Variable("x".to_string());
+    let diff = Expression::Call("diff".to_string(), vec![binop(x.clone(), Operation::Times, int(1)),
+                                                         binop(int(0), Operation::Plus, x)]);
+    assert_optimizes(diff.clone(), diff);
+}
+
+#[test]
+fn equations_are_left_as_written() {
+    let equation = binop(binop(var("x"), Operation::Times, int(1)),
+                         Operation::Equal,
+                         binop(int(0), Operation::Plus, int(2)));
+    let solve = Expression::Call("solve".to_string(), vec![equation, var("x")]);
+    assert_optimizes(solve.clone(), solve);
+}
+
+#[test]
+fn simplifying_never_makes_an_equation_to_solve() {
+    let comparison = binop(binop(int(0), Operation::Equal, var("x")), Operation::Minus, int(0));
+    let solve = Expression::Call("solve".to_string(), vec![comparison, var("x")]);
+    assert_optimizes(solve.clone(), solve);
+}
@@ -1,12 +1,318 @@
 extern crate rand;
 
-use common::fuzzer::Fuzzer;
-use lexer::Token;
-use super::Parser;
-use self::rand::{thread_rng, Rng};
+use common::corpus::Persist;
+use common::fuzzer::{replay_corpus, run_fuzzer, Fuzzer, Shrink};
+use lexer::{Lexer, Token};
+use super::{Expression, Operation, Parser, FUNCTIONS};
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
 
 const MAX_NUM_TOKENS: u32 = 50;
 
+pub const EXPRESSION_MAX_DEPTH: u32 = 10;
+
+// π is bound only some of the time, and is a constant otherwise
+pub const VARIABLE_NAMES: [&str; 4] = ["x", "y", "z", "π"];
+
+pub fn rand_variable_name<R: Rng>(rng: &mut R) -> String {
+    VARIABLE_NAMES[rng.gen_range(0, VARIABLE_NAMES.len())].to_string()
+}
+
+// never bound, so they always evaluate to units; ° is a plain number
+pub const UNIT_NAMES: [&str; 6] = ["m", "km", "s", "h", "mph", "°"];
+
+pub fn rand_unit_name<R: Rng>(rng: &mut R) -> String {
+    UNIT_NAMES[rng.gen_range(0, UNIT_NAMES.len())].to_string()
+}
+
+// magnitudes are spread evenly over bit widths, so small values and
+// values around i32::MAX both come up often
+pub fn rand_integer_expression<R: Rng>(rng: &mut R) -> Expression {
+    let shift = rng.gen_range(0, 32);
+    Expression::Integer(rng.gen::<u32>() >> shift)
+}
+
+pub fn rand_decimal_expression<R: Rng>(rng: &mut R) -> Expression {
+    let fraction = rng.gen_range(0, 1000);
+    match rand_integer_expression(rng) {
+        Expression::Integer(i) => Expression::Decimal(format!("{}.{}", i, fraction)),
+        _ => panic!("not an integer")
+    }
+}
+
+// days near the ends of months, so that adding months clamps
+pub fn rand_date_expression<R: Rng>(rng: &mut R) -> Expression {
+    Expression::Date(format!("{:04}-{:02}-{:02}", rng.gen_range(0, 10000), rng.gen_range(1, 13), rng.gen_range(26, 32)))
+}
+
+pub fn rand_time_expression<R: Rng>(rng: &mut R) -> Expression {
+    Expression::Time(format!("{}:{:02}", rng.gen_range(0, 24), rng.gen_range(0, 60)))
+}
+
+// Mostly integers, with the occasional variable.  Imaginary numbers,
+// units and dates are rare, as the reference evaluator cannot check
+// anything they reach.
+pub fn rand_leaf_expression<R: Rng>(rng: &mut R) -> Expression {
+    match rng.gen_range(0, 66) {
+        0..=15 => Expression::Variable(rand_variable_name(rng)),
+        16 => Expression::Imaginary(rng.gen_range(0, 10)),
+        17 => Expression::Variable(rand_unit_name(rng)),
+        64 => rand_date_expression(rng),
+        65 => rand_time_expression(rng),
+        18..=21 => rand_decimal_expression(rng),
+        _ => rand_integer_expression(rng)
+    }
+}
+
+// arithmetic is more common than comparison, and the matrix product,
+// which fails on numbers, is rare
+pub fn rand_operation<R: Rng>(rng: &mut R) -> Operation {
+    match rng.gen_range(0, 8) {
+        0 => Operation::Plus,
+        1 => Operation::Minus,
+        2 if rng.gen_range(0, 8) == 0 => Operation::MatrixTimes,
+        2 => Operation::Times,
+        3 => Operation::Div,
+        4 | 5 => Operation::Power,
+        _ => match rng.gen_range(0, 6) {
+            0 => Operation::Less,
+            1 => Operation::LessEqual,
+            2 => Operation::Greater,
+            3 => Operation::GreaterEqual,
+            4 => Operation::Equal,
+            _ => Operation::NotEqual
+        }
+    }
+}
+
+// tries each subexpression in place of the whole, then shrinks the
+// children in place
+impl Shrink for Expression {
+    fn shrink(&self) -> Vec<Expression> {
+        match self {
+            Expression::Integer(i) =>
+                i.shrink().into_iter().map(Expression::Integer).collect(),
+            Expression::Decimal(digits) =>
+                digits.split('.').next().unwrap().parse().map(Expression::Integer).into_iter().collect(),
+            Expression::Date(_) | Expression::Time(_) => vec![Expression::Integer(0)],
+            Expression::Imaginary(i) => {
+                let mut result = vec![Expression::Integer(*i)];
+                result.extend(i.shrink().into_iter().map(Expression::Imaginary));
+                result
+            },
+            Expression::Variable(name) if name != "x" =>
+                vec![Expression::Variable("x".to_string())],
+            Expression::Variable(_) => vec![],
+            Expression::UnaryMinus(e) => {
+                let mut result = vec![(**e).clone()];
+                result.extend(e.shrink().into_iter()
+                              .map(|simpler| Expression::UnaryMinus(Box::new(simpler))));
+                result
+            },
+            Expression::SquareRoot(e) => {
+                let mut result = vec![(**e).clone()];
+                result.extend(e.shrink().into_iter()
+                              .map(|simpler| Expression::SquareRoot(Box::new(simpler))));
+                result
+            },
+            Expression::Factorial(e) => {
+                let mut result = vec![(**e).clone()];
+                result.extend(e.shrink().into_iter()
+                              .map(|simpler| Expression::Factorial(Box::new(simpler))));
+                result
+            },
+            Expression::Percent(e) => {
+                let mut result = vec![(**e).clone()];
+                result.extend(e.shrink().into_iter()
+                              .map(|simpler| Expression::Percent(Box::new(simpler))));
+                result
+            },
+            Expression::PercentChange(e1, op, e2) => {
+                let mut result = vec![(**e1).clone(), (**e2).clone()];
+                for simpler in e1.shrink() {
+                    result.push(Expression::PercentChange(Box::new(simpler), *op, e2.clone()));
+                }
+                for simpler in e2.shrink() {
+                    result.push(Expression::PercentChange(e1.clone(), *op, Box::new(simpler)));
+                }
+                result
+            },
+            Expression::BinaryOperation(e1, op, e2) => {
+                let mut result = vec![(**e1).clone(), (**e2).clone()];
+                for simpler in e1.shrink() {
+                    result.push(Expression::BinaryOperation(Box::new(simpler), *op, e2.clone()));
+                }
+                for simpler in e2.shrink() {
+                    result.push(Expression::BinaryOperation(e1.clone(), *op, Box::new(simpler)));
+                }
+                result
+            },
+            Expression::Call(name, arguments) => {
+                let mut result = arguments.clone();
+                for (i, argument) in arguments.iter().enumerate() {
+                    for simpler in argument.shrink() {
+                        let mut simpler_arguments = arguments.clone();
+                        simpler_arguments[i] = simpler;
+                        result.push(Expression::Call(name.clone(), simpler_arguments));
+                    }
+                }
+                result
+            },
+            Expression::Convert(e, unit) => {
+                let mut result = vec![(**e).clone()];
+                for simpler in e.shrink() {
+                    result.push(Expression::Convert(Box::new(simpler), unit.clone()));
+                }
+                for simpler in unit.shrink() {
+                    result.push(Expression::Convert(e.clone(), Box::new(simpler)));
+                }
+                result
+            },
+            Expression::List(elements) => {
+                let mut result = elements.clone();
+                for i in 0..elements.len() {
+                    let mut fewer = elements.clone();
+                    fewer.remove(i);
+                    result.push(Expression::List(fewer));
+                }
+                for (i, element) in elements.iter().enumerate() {
+                    for simpler in element.shrink() {
+                        let mut simpler_elements = elements.clone();
+                        simpler_elements[i] = simpler;
+                        result.push(Expression::List(simpler_elements));
+                    }
+                }
+                result
+            },
+            Expression::Index(e, position) => {
+                let mut result = vec![(**e).clone(), (**position).clone()];
+                for simpler in e.shrink() {
+                    result.push(Expression::Index(Box::new(simpler), position.clone()));
+                }
+                for simpler in position.shrink() {
+                    result.push(Expression::Index(e.clone(), Box::new(simpler)));
+                }
+                result
+            },
+            Expression::Range(start, end, step) => {
+                let mut result = vec![(**start).clone(), (**end).clone()];
+                if let Some(step) = step {
+                    result.push((**step).clone());
+                    result.push(Expression::Range(start.clone(), end.clone(), None));
+                }
+                for simpler in start.shrink() {
+                    result.push(Expression::Range(Box::new(simpler), end.clone(), step.clone()));
+                }
+                for simpler in end.shrink() {
+                    result.push(Expression::Range(start.clone(), Box::new(simpler), step.clone()));
+                }
+                for simpler in step.iter().flat_map(|step| step.shrink()) {
+                    result.push(Expression::Range(start.clone(), end.clone(), Some(Box::new(simpler))));
+                }
+                result
+            }
+        }
+    }
+}
+
+// stored as source text, which parses back to the same tree
+impl Persist for Expression {
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    fn decode(text: &str) -> Result<Expression, String> {
+        let tokens = Lexer::tokenize(&mut text.chars())?;
+        Parser::parse(&tokens)
+    }
+}
+
+// shrinks the expression of a pair, keeping what goes with it
+pub fn shrink_paired<A: Clone>(item: &(Expression, A)) -> Vec<(Expression, A)> {
+    item.0.shrink().into_iter().map(|e| (e, item.1.clone())).collect()
+}
+
+// a small integer or a variable, so ranges are mostly short enough to
+// build quickly
+fn rand_range_end<R: Rng>(rng: &mut R) -> Expression {
+    if rng.gen() {
+        Expression::Integer(rng.gen_range(0, 20))
+    } else {
+        Expression::Variable(rand_variable_name(rng))
+    }
+}
+
+// a list literal, an element of one, or a range
+fn rand_list_expression<R: Rng>(rng: &mut R, depth_remaining: u32) -> Expression {
+    match rng.gen_range(0, 3) {
+        0 => {
+            let length = rng.gen_range(0, 4);
+            Expression::List((0..length).map(|_| rand_expression(rng, depth_remaining)).collect())
+        },
+        1 => {
+            let list = rand_list_expression(rng, depth_remaining.saturating_sub(1));
+            Expression::Index(Box::new(list), Box::new(rand_expression(rng, depth_remaining)))
+        },
+        _ => {
+            let step = if rng.gen() { Some(Box::new(rand_expression(rng, depth_remaining))) } else { None };
+            Expression::Range(Box::new(rand_range_end(rng)), Box::new(rand_range_end(rng)), step)
+        }
+    }
+}
+
+pub fn rand_expression<R: Rng>(rng: &mut R, depth_remaining: u32) -> Expression {
+    if depth_remaining == 0 {
+        rand_leaf_expression(rng)
+    } else {
+        match rng.gen_range(0, 3) {
+            0 => rand_leaf_expression(rng),
+            1 => {
+                if rng.gen_range(0, 8) == 0 {
+                    let (name, least, most) = FUNCTIONS[rng.gen_range(0, FUNCTIONS.len())];
+                    let arguments = (0..rng.gen_range(least, most + 1)).map(|_| rand_expression(rng, depth_remaining - 1)).collect();
+                    return Expression::Call(name.to_string(), arguments);
+                }
+                let e = Box::new(rand_expression(rng, depth_remaining - 1));
+                match rng.gen_range(0, 8) {
+                    0 => Expression::SquareRoot(e),
+                    1 => Expression::Factorial(e),
+                    2 => Expression::Percent(e),
+                    _ => Expression::UnaryMinus(e)
+                }
+            },
+            2 if rng.gen_range(0, 16) == 0 => rand_list_expression(rng, depth_remaining - 1),
+            // mostly to a unit, which is what conversion needs
+            2 if rng.gen_range(0, 16) == 0 => {
+                let new_bound = depth_remaining - 1;
+                let e = rand_expression(rng, new_bound);
+                let unit = if rng.gen() {
+                    Expression::Variable(rand_unit_name(rng))
+                } else {
+                    rand_expression(rng, new_bound)
+                };
+                Expression::Convert(Box::new(e), Box::new(unit))
+            },
+            2 if rng.gen_range(0, 8) == 0 => {
+                let new_bound = depth_remaining - 1;
+                let left = rand_expression(rng, new_bound);
+                let op = if rng.gen() { Operation::Plus } else { Operation::Minus };
+                let right = rand_expression(rng, new_bound);
+                Expression::PercentChange(Box::new(left), op, Box::new(right))
+            },
+            2 => {
+                let new_bound = depth_remaining - 1;
+                let left = rand_expression(rng, new_bound);
+                let op = rand_operation(rng);
+                let right = rand_expression(rng, new_bound);
+                Expression::BinaryOperation(Box::new(left),
+                                            op,
+                                            Box::new(right))
+            },
+            _ => panic!("Expression out of range")
+        }
+    }
+}
+
 struct ParserFuzzer<R: Rng> {
     random: R
 }
@@ -17,24 +323,62 @@ impl<R: Rng> ParserFuzzer<R> {
     }
     
     fn rand_token(&mut self) -> Token {
-        let rng = self.random_generator();
-        match rng.gen_range(0, 7) {
+        let rng = &mut self.random;
+        match rng.gen_range(0, 32) {
+            27 => Token::LeftBracket,
+            28 => Token::RightBracket,
+            29 => Token::Range,
+            30 => Token::Step,
+            31 => Token::MatrixTimes,
             0 => Token::Integer(rng.gen()),
+            25 => match rand_date_expression(rng) {
+                Expression::Date(text) => Token::Date(text),
+                _ => panic!("not a date")
+            },
+            26 => match rand_time_expression(rng) {
+                Expression::Time(text) => Token::Time(text),
+                _ => panic!("not a time")
+            },
+            22 => match rand_decimal_expression(rng) {
+                Expression::Decimal(digits) => Token::Decimal(digits),
+                _ => panic!("not a decimal")
+            },
+            23 => Token::Identifier(rand_unit_name(rng)),
+            24 => Token::To,
+            7 => Token::Identifier(rand_variable_name(rng)),
+            19 => Token::Identifier(FUNCTIONS[rng.gen_range(0, FUNCTIONS.len())].0.to_string()),
+            20 => Token::Imaginary(rng.gen()),
+            21 => Token::Comma,
             1 => Token::Plus,
             2 => Token::Minus,
             3 => Token::Times,
             4 => Token::Div,
             5 => Token::LeftParen,
             6 => Token::RightParen,
+            8 => Token::Power,
+            9 => Token::Superscript(rng.gen_range(0, 10)),
+            10 => Token::SquareRoot,
+            11 => Token::Less,
+            12 => Token::LessEqual,
+            13 => Token::Greater,
+            14 => Token::GreaterEqual,
+            15 => Token::Equal,
+            16 => Token::NotEqual,
+            17 => Token::Factorial,
+            18 => Token::Percent,
             _ => panic!("Generated value out of expected range")
         }
     }
 }
 
-impl<ActualR: Rng> Fuzzer for ParserFuzzer<ActualR> {
+impl<ActualR: Rng + SeedableRng> Fuzzer for ParserFuzzer<ActualR> {
     type Item = Vec<Token>;
     type R = ActualR;
 
+    fn new(random: ActualR) -> ParserFuzzer<ActualR> {
+        ParserFuzzer { random }
+    }
+
     fn gen_one(&mut self) -> Vec<Token> {
         let num_tokens = self.num_tokens();
         let mut tokens = vec![];
@@ -47,8 +391,21 @@ impl<ActualR: Rng> Fuzzer for ParserFuzzer<ActualR> {
         tokens
     }
 
+    // anything that parses strictly has no juxtaposition in it, so it
+    // must parse the same way when multiplication may be implicit
     fn run_test(&mut self, item: Vec<Token>) {
-        let _ = Parser::parse(&item);
+        let lenient = Parser::parse(&item);
+        if let Ok(strict) = Parser::parse_strict(&item) {
+            assert_eq!(Ok(strict), lenient);
+        }
+    }
+
+    fn corpus_name(&self) -> Option<&'static str> {
+        Some("parser")
+    }
+
+    fn shrink(&self, item: &Vec<Token>) -> Vec<Vec<Token>> {
+        item.shrink()
     }
 
     fn random_generator(&mut self) -> &mut ActualR {
@@ -58,8 +415,60 @@ impl<ActualR: Rng> Fuzzer for ParserFuzzer<ActualR> {
 
 #[test]
 fn run_parser_fuzzer() {
-    let mut fuzzer = ParserFuzzer {
-        random: thread_rng()
-    };
-    fuzzer.run_tests();
+    run_fuzzer::<ParserFuzzer<StdRng>>();
+}
+
+// checks that printing an expression gives source that parses back to it
+struct PrinterFuzzer<R: Rng> {
+    random: R
+}
+
+impl<ActualR: Rng + SeedableRng> Fuzzer for PrinterFuzzer<ActualR> {
+    type Item = Expression;
+    type R = ActualR;
+
+    fn new(random: ActualR) -> PrinterFuzzer<ActualR> {
+        PrinterFuzzer { random }
+    }
+
+    fn gen_one(&mut self) -> Expression {
+        rand_expression(self.random_generator(), EXPRESSION_MAX_DEPTH)
+    }
+
+    fn run_test(&mut self, item: Expression) {
+        let printed = item.to_string();
+        let tokens = Lexer::tokenize(&mut printed.chars())
+            .unwrap_or_else(|e| panic!("{} did not lex: {}", printed, e));
+        match Parser::parse(&tokens) {
+            Ok(parsed) => assert_eq!(item, parsed, "printed as {}", printed),
+            Err(e) => panic!("{} did not parse: {}", printed, e)
+        }
+    }
+
+    fn corpus_name(&self) -> Option<&'static str> {
+        Some("printer")
+    }
+
+    fn shrink(&self, item: &Expression) -> Vec<Expression> {
+        item.shrink()
+    }
+
+    fn random_generator(&mut self) -> &mut ActualR {
+        &mut self.random
+    }
+}
+
+#[test]
+fn run_printer_fuzzer() {
+    run_fuzzer::<PrinterFuzzer<StdRng>>();
+}
+
+#[test]
+fn replay_parser_corpus() {
+    replay_corpus::<ParserFuzzer<StdRng>>();
+}
+
+#[test]
+fn replay_printer_corpus() {
+    replay_corpus::<PrinterFuzzer<StdRng>>();
 }
@@ -3,52 +3,391 @@ use std::fmt;
 
 use super::lexer::Token;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Expression {
     Integer(u32),
+    // a literal with a decimal point, as written
+    Decimal(String),
+    // an integer times the imaginary unit
+    Imaginary(u32),
+    // a day or a time of day, as written
+    Date(String),
+    Time(String),
+    Variable(String),
     UnaryMinus(Box<Expression>),
-    BinaryOperation(Box<Expression>, Operation, Box<Expression>)
+    SquareRoot(Box<Expression>),
+    Factorial(Box<Expression>),
+    // a hundredth of its operand
+    Percent(Box<Expression>),
+    // e1 + e2% or e1 - e2%: e1 raised or lowered by e2 percent of itself
+    PercentChange(Box<Expression>, Operation, Box<Expression>),
+    BinaryOperation(Box<Expression>, Operation, Box<Expression>),
+    // a built-in function applied to its arguments
+    Call(String, Vec<Expression>),
+    // the first converted to the unit the second evaluates to
+    Convert(Box<Expression>, Box<Expression>),
+    // [e1, e2, ...]
+    List(Vec<Expression>),
+    // the element of the first at the position the second evaluates to
+    Index(Box<Expression>, Box<Expression>),
+    // from the first to the second inclusive, by the step if there is one
+    // and by 1 otherwise
+    Range(Box<Expression>, Box<Expression>, Option<Box<Expression>>)
 }
 
+// The built-in functions and the fewest and most arguments each takes.
+// One of these names followed by ( is a call; anywhere else it is a
+// variable.
+pub const FUNCTIONS: [(&str, usize, usize); 20] = [
+    ("sqrt", 1, 1),
+    ("abs", 1, 1),
+    ("arg", 1, 1),
+    ("conj", 1, 1),
+    ("re", 1, 1),
+    ("im", 1, 1),
+    ("exp", 1, 1),
+    ("ln", 1, 1),
+    ("sum", 1, 1),
+    ("product", 1, 1),
+    ("mean", 1, 1),
+    ("median", 1, 1),
+    ("stddev", 1, 1),
+    ("count", 1, 1),
+    ("transpose", 1, 1),
+    ("det", 1, 1),
+    ("inverse", 1, 1),
+    ("rank", 1, 1),
+    ("solve", 2, 3),
+    ("diff", 2, 3)
+];
+
+pub fn arity(name: &str) -> Option<(usize, usize)> {
+    FUNCTIONS.iter().find(|(function, _, _)| *function == name).map(|(_, least, most)| (*least, *most))
+}
+
+// fails unless name is a function taking this many arguments
+pub fn check_call(name: &str, count: usize) -> Result<(), String> {
+    match arity(name) {
+        Some((least, most)) if least <= count && count <= most => Result::Ok(()),
+        Some((least, most)) if least < most =>
+            Result::Err(format!("{} takes {} to {} arguments, not {}", name, least, most, count)),
+        Some((expected, _)) => Result::Err(format!("{} takes {} argument{}, not {}",
+                                                   name,
+                                                   expected,
+                                                   if expected == 1 { "" } else { "s" },
+                                                   count)),
+        None => Result::Err(format!("Unknown function: {}", name))
+    }
+}
+
+// binding strength of to, the loosest of all
+const CONVERSION_PRECEDENCE: u32 = 0;
+
+// binding strength of .., between comparison and addition
+const RANGE_PRECEDENCE: u32 = 2;
+
+// binding strength of prefix minus and square root; tighter than any
+// binary operation except exponentiation
+const UNARY_PRECEDENCE: u32 = 5;
+
+// binding strength of postfix !, % and indexing, the tightest of all
+const POSTFIX_PRECEDENCE: u32 = 7;
+
+impl Expression {
+    // Prints with canonical spacing, parenthesizing only when this binds
+    // more loosely than its context requires.  Binary operations other than
+    // exponentiation associate left, so a right operand of the same
+    // precedence needs parentheses to keep its shape.  Superscripts print
+    // as ^.  A percentage on the right of + or - is parenthesized, as
+    // otherwise it would read back as a percent change.
+    fn fmt_in_context(&self, f: &mut fmt::Formatter, context: u32) -> fmt::Result {
+        match self {
+            Expression::Integer(i) => write!(f, "{}", i),
+            Expression::Decimal(digits) => f.write_str(digits),
+            Expression::Imaginary(i) => write!(f, "{}i", i),
+            Expression::Date(text) | Expression::Time(text) => f.write_str(text),
+            Expression::Variable(name) => f.write_str(name),
+            Expression::UnaryMinus(e) =>
+                Expression::fmt_prefix(f, "-", e, context),
+            Expression::SquareRoot(e) =>
+                Expression::fmt_prefix(f, "√", e, context),
+            Expression::Factorial(e) =>
+                Expression::fmt_postfix(f, e, "!", context),
+            Expression::Percent(e) =>
+                Expression::fmt_postfix(f, e, "%", context),
+            Expression::PercentChange(e1, op, e2) => {
+                let needs_parens = op.precedence() < context;
+                if needs_parens {
+                    f.write_str("(")?;
+                }
+                e1.fmt_in_context(f, op.precedence())?;
+                write!(f, " {} ", op)?;
+                e2.fmt_in_context(f, POSTFIX_PRECEDENCE)?;
+                f.write_str("%")?;
+                if needs_parens {
+                    f.write_str(")")?;
+                }
+                Result::Ok(())
+            },
+            Expression::BinaryOperation(e1, op, e2) => {
+                let own = op.precedence();
+                let needs_parens = own < context;
+                if needs_parens {
+                    f.write_str("(")?;
+                }
+                let (left, right) = op.operand_precedences();
+                let right = match (op, &**e2) {
+                    (Operation::Plus, Expression::Percent(_)) |
+                    (Operation::Minus, Expression::Percent(_)) => POSTFIX_PRECEDENCE + 1,
+                    _ => right
+                };
+                e1.fmt_in_context(f, left)?;
+                write!(f, " {} ", op)?;
+                e2.fmt_in_context(f, right)?;
+                if needs_parens {
+                    f.write_str(")")?;
+                }
+                Result::Ok(())
+            },
+            Expression::Call(name, arguments) => {
+                write!(f, "{}(", name)?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    argument.fmt_in_context(f, 0)?;
+                }
+                f.write_str(")")
+            },
+            Expression::Convert(e, unit) => {
+                let needs_parens = CONVERSION_PRECEDENCE < context;
+                if needs_parens {
+                    f.write_str("(")?;
+                }
+                e.fmt_in_context(f, CONVERSION_PRECEDENCE)?;
+                f.write_str(" to ")?;
+                unit.fmt_in_context(f, CONVERSION_PRECEDENCE + 1)?;
+                if needs_parens {
+                    f.write_str(")")?;
+                }
+                Result::Ok(())
+            },
+            Expression::List(elements) => {
+                f.write_str("[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    element.fmt_in_context(f, 0)?;
+                }
+                f.write_str("]")
+            },
+            Expression::Index(e, index) => {
+                e.fmt_in_context(f, POSTFIX_PRECEDENCE)?;
+                f.write_str("[")?;
+                index.fmt_in_context(f, 0)?;
+                f.write_str("]")
+            },
+            Expression::Range(start, end, step) => {
+                let needs_parens = RANGE_PRECEDENCE < context;
+                if needs_parens {
+                    f.write_str("(")?;
+                }
+                start.fmt_in_context(f, RANGE_PRECEDENCE + 1)?;
+                f.write_str("..")?;
+                end.fmt_in_context(f, RANGE_PRECEDENCE + 1)?;
+                if let Some(step) = step {
+                    f.write_str(" step ")?;
+                    step.fmt_in_context(f, RANGE_PRECEDENCE + 1)?;
+                }
+                if needs_parens {
+                    f.write_str(")")?;
+                }
+                Result::Ok(())
+            }
+        }
+    }
+
+    fn fmt_prefix(f: &mut fmt::Formatter, symbol: &str, e: &Expression, context: u32) -> fmt::Result {
+        let needs_parens = UNARY_PRECEDENCE < context;
+        if needs_parens {
+            f.write_str("(")?;
+        }
+        f.write_str(symbol)?;
+        e.fmt_in_context(f, UNARY_PRECEDENCE)?;
+        if needs_parens {
+            f.write_str(")")?;
+        }
+        Result::Ok(())
+    }
+
+    fn fmt_postfix(f: &mut fmt::Formatter, e: &Expression, symbol: &str, context: u32) -> fmt::Result {
+        let needs_parens = POSTFIX_PRECEDENCE < context;
+        if needs_parens {
+            f.write_str("(")?;
+        }
+        e.fmt_in_context(f, POSTFIX_PRECEDENCE)?;
+        f.write_str(symbol)?;
+        if needs_parens {
+            f.write_str(")")?;
+        }
+        Result::Ok(())
+    }
+}
+
+// prints as source text that parses back to the same expression
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        <Expression as fmt::Debug>::fmt(self, f)
+        self.fmt_in_context(f, 0)
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+// Comparisons evaluate to 1 when they hold and 0 otherwise.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Operation {
     Plus,
     Minus,
     Times,
-    Div
+    Div,
+    // the matrix product, where * multiplies lists element by element
+    MatrixTimes,
+    Power,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual
+}
+
+impl Operation {
+    // binding strength; higher binds tighter
+    pub fn precedence(&self) -> u32 {
+        match self {
+            Operation::Less | Operation::LessEqual |
+            Operation::Greater | Operation::GreaterEqual |
+            Operation::Equal | Operation::NotEqual => 1,
+            Operation::Plus | Operation::Minus => 3,
+            Operation::Times | Operation::Div | Operation::MatrixTimes => 4,
+            Operation::Power => 6
+        }
+    }
+
+    // the context each operand is printed in.  Exponentiation associates
+    // right, and its right operand may be negated without parentheses.
+    fn operand_precedences(&self) -> (u32, u32) {
+        let own = self.precedence();
+        match self {
+            Operation::Power => (own + 1, UNARY_PRECEDENCE),
+            _ => (own, own + 1)
+        }
+    }
 }
 
 impl fmt::Display for Operation {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        <Operation as fmt::Debug>::fmt(self, f)
+        f.write_str(match self {
+            Operation::Plus => "+",
+            Operation::Minus => "-",
+            Operation::Times => "*",
+            Operation::Div => "/",
+            Operation::MatrixTimes => "@",
+            Operation::Power => "^",
+            Operation::Less => "<",
+            Operation::LessEqual => "<=",
+            Operation::Greater => ">",
+            Operation::GreaterEqual => ">=",
+            Operation::Equal => "==",
+            Operation::NotEqual => "!="
+        })
+    }
+}
+
+// how the parser reads the forms that calculators disagree on
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ParseOptions {
+    // 2x is 2 * x, rather than an error
+    pub implicit_multiplication: bool,
+    // 200 + 10% is 220, rather than 200 + 0.1
+    pub relative_percent: bool
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            implicit_multiplication: true,
+            relative_percent: true
+        }
+    }
+}
+
+// a number times a unit, such as 3 h, one part of a sum like 3d 4h
+fn is_measure(e: &Expression) -> bool {
+    match e {
+        Expression::BinaryOperation(number, Operation::Times, unit) =>
+            matches!(**number, Expression::Integer(_) | Expression::Decimal(_)) &&
+            matches!(**unit, Expression::Variable(_)),
+        _ => false
     }
 }
 
 pub struct Parser<'a> {
-    tokens: &'a [Token]
+    tokens: &'a [Token],
+    options: ParseOptions
 }
 
 type ParseResult<A> = Result<A, String>;
 
+type SubParser<'a> = fn(&Parser<'a>, usize) -> ParseResult<(Expression, usize)>;
+
+type ExpressionMaker = fn(Box<Expression>, Box<Expression>) -> Expression;
+
 impl<'a> Parser<'a> {
-    fn new(tokens: &[Token]) -> Parser {
-        Parser { tokens: tokens }
+    fn new(tokens: &[Token]) -> Parser<'_> {
+        Parser { tokens, options: ParseOptions::default() }
     }
 
+    // Multiplication may be implicit, as in 2x, 2π, 2(3 + 4) or
+    // (1 + 2)(3 + 4).  Juxtaposition binds tighter than * and /, so 1/2x
+    // is 1/(2x), but looser than powers and prefix operators, so 2x² is
+    // 2(x²) and -2x is (-2)x.  Only a variable, ( or √ can start the
+    // right-hand factor, so 2 -3 is still a subtraction and 2 3 an error.
+    // Numbers with units written one after another are added, so 3d 4h
+    // is 3d + 4h, and 2 * 1h 30min doubles all of it.
+    // One of the FUNCTIONS followed by ( is a call, never a product, so
+    // abs(x) applies abs while x(2) multiplies.
+    //
+    // A percentage written bare on the right of + or - is relative to the
+    // left operand, so 200 + 10% is 220 and 200 - 10% is 180.  Anywhere
+    // else, including 200 + (10%), it is just a hundredth: 50 * 10% is 5.
+    //
+    // Conversion with to (or in) binds loosest of all, so 60 mph to m/s
+    // converts the whole of 60 mph to the whole of m/s.
+    //
+    // A range binds looser than arithmetic but tighter than comparison, so
+    // 1..n + 1 ends at n + 1, and cannot be chained.  Indexing with [ ]
+    // is postfix, binding as tightly as !.
     pub fn parse(tokens: &[Token]) -> ParseResult<Expression> {
         Parser::new(tokens).parse_toplevel_expression()
     }
 
+    // as parse, but every multiplication must be written out
+    pub fn parse_strict(tokens: &[Token]) -> ParseResult<Expression> {
+        let options = ParseOptions {
+            implicit_multiplication: false,
+            ..ParseOptions::default()
+        };
+        Parser::parse_with(tokens, options)
+    }
+
+    pub fn parse_with(tokens: &[Token], options: ParseOptions) -> ParseResult<Expression> {
+        Parser { tokens, options }.parse_toplevel_expression()
+    }
+
     fn get_token(&self, pos: usize) -> ParseResult<&Token> {
         match self.tokens.get(pos) {
             Some(token) => Result::Ok(token),
-            None => Result::Err(format!("Out of tokens"))
+            None => Result::Err("Out of tokens".to_string())
         }
     }
 
@@ -58,7 +397,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_toplevel_expression(&self) -> ParseResult<Expression> {
-        let (e, pos) = try!(self.parse_expression(0));
+        let (e, pos) = self.parse_expression(0)?;
         if pos == self.tokens.len() {
             Result::Ok(e)
         } else {
@@ -67,15 +406,15 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_expression(&self, pos1: usize) -> ParseResult<(Expression, usize)> {
-        self.parse_additive_expression(pos1)
+        self.parse_conversion_expression(pos1)
     }
 
     // given a token representing an operator, it will give a function
     // that will make a binop holding that operator
     fn parse_arithmetic_expression(&self, start_pos: usize,
-                                   base_parse: fn(&Parser<'a>, usize) -> ParseResult<(Expression, usize)>,
-                                   map: &HashMap<Token, fn(Box<Expression>, Box<Expression>) -> Expression>) -> ParseResult<(Expression, usize)> {
-        let (temp_result, temp_cur_pos) = try!(base_parse(self, start_pos));
+                                   base_parse: SubParser<'a>,
+                                   map: &HashMap<Token, ExpressionMaker>) -> ParseResult<(Expression, usize)> {
+        let (temp_result, temp_cur_pos) = base_parse(self, start_pos)?;
         let mut result = temp_result;
         let mut cur_pos = temp_cur_pos;
         
@@ -93,7 +432,12 @@ impl<'a> Parser<'a> {
                                 // had something valid
                                 Ok((right_expression, next_pos)) => {
                                     // update value and position
-                                    result = make_expression(Box::new(result), Box::new(right_expression));
+                                    result = match self.percent_change(token, right_expression, next_pos) {
+                                        Ok((op, percentage)) =>
+                                            Expression::PercentChange(Box::new(result), op, Box::new(percentage)),
+                                        Err(right_expression) =>
+                                            make_expression(Box::new(result), Box::new(right_expression))
+                                    };
                                     cur_pos = next_pos;
                                 },
                                 // had something invalid.  Bail with what we have so far.
@@ -116,6 +460,86 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Splits the right operand of + or - into the operation and the
+    // percentage when it is a bare percentage, ending at end, that makes a
+    // percent change.  Otherwise gives the operand back.
+    fn percent_change(&self, operator: &Token, right: Expression, end: usize) -> Result<(Operation, Expression), Expression> {
+        let op = match operator {
+            Token::Plus => Operation::Plus,
+            Token::Minus => Operation::Minus,
+            _ => return Result::Err(right)
+        };
+        match right {
+            Expression::Percent(percentage) if self.options.relative_percent &&
+                                               self.tokens[end - 1] == Token::Percent =>
+                Result::Ok((op, *percentage)),
+            other => Result::Err(other)
+        }
+    }
+
+    fn parse_conversion_expression(&self, start_pos: usize) -> ParseResult<(Expression, usize)> {
+        // convExp \in ConversionExpression ::= compExp ('to' compExp)*
+        fn make_convert(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
+            Expression::Convert(e1, e2)
+        }
+        let mut map: HashMap<Token, ExpressionMaker> = HashMap::new();
+        map.insert(Token::To, make_convert);
+        self.parse_arithmetic_expression(start_pos,
+                                         Parser::parse_comparison_expression,
+                                         &map)
+    }
+
+    fn parse_comparison_expression(&self, start_pos: usize) -> ParseResult<(Expression, usize)> {
+        // compExp \in ComparisonExpression ::= range (('<' | '<=' | '>' | '>=' | '==' | '!=') range)*
+        fn make_less(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
+            Expression::BinaryOperation(e1, Operation::Less, e2)
+        }
+        fn make_less_equal(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
+            Expression::BinaryOperation(e1, Operation::LessEqual, e2)
+        }
+        fn make_greater(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
+            Expression::BinaryOperation(e1, Operation::Greater, e2)
+        }
+        fn make_greater_equal(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
+            Expression::BinaryOperation(e1, Operation::GreaterEqual, e2)
+        }
+        fn make_equal(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
+            Expression::BinaryOperation(e1, Operation::Equal, e2)
+        }
+        fn make_not_equal(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
+            Expression::BinaryOperation(e1, Operation::NotEqual, e2)
+        }
+        let mut map: HashMap<Token, ExpressionMaker> = HashMap::new();
+        map.insert(Token::Less, make_less);
+        map.insert(Token::LessEqual, make_less_equal);
+        map.insert(Token::Greater, make_greater);
+        map.insert(Token::GreaterEqual, make_greater_equal);
+        map.insert(Token::Equal, make_equal);
+        map.insert(Token::NotEqual, make_not_equal);
+        self.parse_arithmetic_expression(start_pos,
+                                         Parser::parse_range_expression,
+                                         &map)
+    }
+
+    fn parse_range_expression(&self, pos1: usize) -> ParseResult<(Expression, usize)> {
+        // range \in RangeExpression ::= addExp ('..' addExp ('step' addExp)?)?
+        let (start, pos2) = self.parse_additive_expression(pos1)?;
+        if self.get_token(pos2) != Ok(&Token::Range) {
+            return Result::Ok((start, pos2));
+        }
+        // as with other operators, a missing operand leaves the rest unparsed
+        let (end, pos3) = match self.parse_additive_expression(pos2 + 1) {
+            Ok(parsed) => parsed,
+            Err(_) => return Result::Ok((start, pos2))
+        };
+        if self.get_token(pos3) == Ok(&Token::Step) {
+            if let Ok((step, pos4)) = self.parse_additive_expression(pos3 + 1) {
+                return Result::Ok((Expression::Range(Box::new(start), Box::new(end), Some(Box::new(step))), pos4));
+            }
+        }
+        Result::Ok((Expression::Range(Box::new(start), Box::new(end), None), pos3))
+    }
+
     fn parse_additive_expression(&self, start_pos: usize) -> ParseResult<(Expression, usize)> {
         // addExp \in AdditiveExpression ::= multExp (('+' | '-') multExp)*
         fn make_plus(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
@@ -124,7 +548,7 @@ impl<'a> Parser<'a> {
         fn make_minus(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
             Expression::BinaryOperation(e1, Operation::Minus, e2)
         }
-        let mut map: HashMap<Token, fn(Box<Expression>, Box<Expression>) -> Expression> = HashMap::new();
+        let mut map: HashMap<Token, ExpressionMaker> = HashMap::new();
         map.insert(Token::Plus, make_plus);
         map.insert(Token::Minus, make_minus);
         self.parse_arithmetic_expression(start_pos,
@@ -133,44 +557,183 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_multiplicative_expression(&self, start_pos: usize) -> ParseResult<(Expression, usize)> {
-        // multExp \in MultiplicativeExpression ::= primary (('*' | '/') primary)*
+        // multExp \in MultiplicativeExpression ::= implicit (('*' | '/' | '@') implicit)*
         fn make_times(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
             Expression::BinaryOperation(e1, Operation::Times, e2)
         }
         fn make_div(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
             Expression::BinaryOperation(e1, Operation::Div, e2)
         }
-        let mut map: HashMap<Token, fn(Box<Expression>, Box<Expression>) -> Expression> = HashMap::new();
+        fn make_matrix_times(e1: Box<Expression>, e2: Box<Expression>) -> Expression {
+            Expression::BinaryOperation(e1, Operation::MatrixTimes, e2)
+        }
+        let mut map: HashMap<Token, ExpressionMaker> = HashMap::new();
         map.insert(Token::Times, make_times);
         map.insert(Token::Div, make_div);
+        map.insert(Token::MatrixTimes, make_matrix_times);
         self.parse_arithmetic_expression(start_pos,
-                                         Parser::parse_primary,
+                                         Parser::parse_implicit_expression,
                                          &map)
     }
-    
+
+    fn parse_implicit_expression(&self, pos1: usize) -> ParseResult<(Expression, usize)> {
+        // implicit \in ImplicitExpression ::= measure+ | product
+        // measure \in Measure ::= number variable, a product like 3 h
+        let (mut sum, mut pos2) = self.parse_product(pos1)?;
+        if !is_measure(&sum) {
+            return Result::Ok((sum, pos2));
+        }
+        while let Ok(Token::Integer(_)) | Ok(Token::Decimal(_)) = self.get_token(pos2) {
+            match self.parse_product(pos2) {
+                Ok((measure, pos3)) if is_measure(&measure) => {
+                    sum = Expression::BinaryOperation(Box::new(sum), Operation::Plus, Box::new(measure));
+                    pos2 = pos3;
+                },
+                _ => break
+            }
+        }
+        Result::Ok((sum, pos2))
+    }
+
+    fn parse_product(&self, pos1: usize) -> ParseResult<(Expression, usize)> {
+        // product \in ImplicitProduct ::= unary factor*
+        // factor \in Factor ::= unary, starting with a variable, '(' or '√'
+        let (mut product, mut pos2) = self.parse_unary_expression(pos1)?;
+        if !self.options.implicit_multiplication {
+            return Result::Ok((product, pos2));
+        }
+        while let Ok(Token::Identifier(_)) | Ok(Token::LeftParen) | Ok(Token::SquareRoot) = self.get_token(pos2) {
+            // as with other operators, a missing factor leaves the rest unparsed
+            match self.parse_unary_expression(pos2) {
+                Ok((factor, pos3)) => {
+                    product = Expression::BinaryOperation(Box::new(product),
+                                                          Operation::Times,
+                                                          Box::new(factor));
+                    pos2 = pos3;
+                },
+                Err(_) => break
+            }
+        }
+        Result::Ok((product, pos2))
+    }
+
+    fn parse_unary_expression(&self, pos1: usize) -> ParseResult<(Expression, usize)> {
+        // unary \in UnaryExpression ::= ('-' | '√') unary | power
+        match self.get_token(pos1)? {
+            Token::Minus => {
+                let (e, pos2) = self.parse_unary_expression(pos1 + 1)?;
+                Result::Ok((Expression::UnaryMinus(Box::new(e)), pos2))
+            },
+            Token::SquareRoot => {
+                let (e, pos2) = self.parse_unary_expression(pos1 + 1)?;
+                Result::Ok((Expression::SquareRoot(Box::new(e)), pos2))
+            },
+            _ => self.parse_power_expression(pos1)
+        }
+    }
+
+    fn parse_power_expression(&self, pos1: usize) -> ParseResult<(Expression, usize)> {
+        // power \in PowerExpression ::= postfix ('^' unary)?
+        // postfix \in PostfixExpression ::= primary (superscript | '!' | '%' | '[' conversion ']')*
+        let (mut base, mut pos2) = self.parse_primary(pos1)?;
+        loop {
+            if self.get_token(pos2) == Ok(&Token::LeftBracket) {
+                let (index, pos3) = self.parse_expression(pos2 + 1)?;
+                match self.get_token(pos3)? {
+                    Token::RightBracket => {},
+                    other => return Parser::unexpected_token(other, "right bracket")
+                }
+                base = Expression::Index(Box::new(base), Box::new(index));
+                pos2 = pos3 + 1;
+                continue;
+            }
+            base = match self.get_token(pos2) {
+                Ok(Token::Superscript(i)) =>
+                    Expression::BinaryOperation(Box::new(base),
+                                                Operation::Power,
+                                                Box::new(Expression::Integer(*i))),
+                Ok(Token::Factorial) => Expression::Factorial(Box::new(base)),
+                Ok(Token::Percent) => Expression::Percent(Box::new(base)),
+                _ => break
+            };
+            pos2 += 1;
+        }
+        if let Ok(Token::Power) = self.get_token(pos2) {
+            // as with other operators, a missing operand leaves the ^ unparsed
+            if let Ok((exponent, pos3)) = self.parse_unary_expression(pos2 + 1) {
+                return Result::Ok((Expression::BinaryOperation(Box::new(base),
+                                                               Operation::Power,
+                                                               Box::new(exponent)),
+                                   pos3));
+            }
+        }
+        Result::Ok((base, pos2))
+    }
+
     fn parse_primary(&self, pos1: usize) -> ParseResult<(Expression, usize)> {
-        match try!(self.get_token(pos1)) {
+        match self.get_token(pos1)? {
             Token::LeftParen => {
-                let (expression, pos2) = try!(self.parse_expression(pos1 + 1));
-                match try!(self.get_token(pos2)) {
+                let (expression, pos2) = self.parse_expression(pos1 + 1)?;
+                match self.get_token(pos2)? {
                     Token::RightParen => Result::Ok((expression, pos2 + 1)),
                     other => Parser::unexpected_token(other, "right paren"),
                 }
             },
             Token::Integer(ref i) => Result::Ok((Expression::Integer(*i), pos1 + 1)),
-            Token::Minus => {
-                let (primary, pos2) = try!(self.parse_primary(pos1 + 1));
-                Result::Ok(
-                    (Expression::UnaryMinus(
-                        Box::new(primary)),
-                     pos2))
+            Token::Decimal(ref digits) => Result::Ok((Expression::Decimal(digits.clone()), pos1 + 1)),
+            Token::Imaginary(ref i) => Result::Ok((Expression::Imaginary(*i), pos1 + 1)),
+            Token::Date(ref text) => Result::Ok((Expression::Date(text.clone()), pos1 + 1)),
+            Token::Time(ref text) => Result::Ok((Expression::Time(text.clone()), pos1 + 1)),
+            Token::LeftBracket => self.parse_list(pos1 + 1),
+            Token::Identifier(ref name) => match self.get_token(pos1 + 1) {
+                Ok(Token::LeftParen) if arity(name).is_some() => self.parse_call(name, pos1 + 2),
+                _ => Result::Ok((Expression::Variable(name.clone()), pos1 + 1))
             },
-            other => Parser::unexpected_token(&other, "left paren; integer; unary minus"),
+            other => Parser::unexpected_token(other, "left paren; left bracket; integer; variable; unary minus; square root"),
+        }
+    }
+
+    fn parse_call(&self, name: &str, pos1: usize) -> ParseResult<(Expression, usize)> {
+        // call \in Call ::= function '(' (conversion (',' conversion)*)? ')'
+        let mut arguments = vec![];
+        let mut pos2 = pos1;
+        if self.get_token(pos2)? != &Token::RightParen {
+            loop {
+                let (argument, pos3) = self.parse_expression(pos2)?;
+                arguments.push(argument);
+                pos2 = pos3;
+                match self.get_token(pos2)? {
+                    Token::Comma => pos2 += 1,
+                    Token::RightParen => break,
+                    other => return Parser::unexpected_token(other, "comma; right paren")
+                }
+            }
+        }
+        check_call(name, arguments.len())?;
+        Result::Ok((Expression::Call(name.to_string(), arguments), pos2 + 1))
+    }
+
+    fn parse_list(&self, pos1: usize) -> ParseResult<(Expression, usize)> {
+        // list \in List ::= '[' (conversion (',' conversion)*)? ']'
+        let mut elements = vec![];
+        let mut pos2 = pos1;
+        if self.get_token(pos2)? != &Token::RightBracket {
+            loop {
+                let (element, pos3) = self.parse_expression(pos2)?;
+                elements.push(element);
+                pos2 = pos3;
+                match self.get_token(pos2)? {
+                    Token::Comma => pos2 += 1,
+                    Token::RightBracket => break,
+                    other => return Parser::unexpected_token(other, "comma; right bracket")
+                }
+            }
         }
+        Result::Ok((Expression::List(elements), pos2 + 1))
     }
 }
 
 #[cfg(test)]
 mod test;
 #[cfg(test)]
-mod fuzzer;
+pub mod fuzzer;
@@ -1,13 +1,14 @@
-use super::{Parser, Expression, Operation};
-use lexer::Token;
+use super::{Parser, ParseOptions, Expression, Operation};
+use common::fuzzer::Shrink;
+use lexer::{Lexer, Token};
 
 fn assert_parse(tokens: &[Token], expected_raw: Option<Expression>) {
     let parser = Parser::new(tokens);
     match (expected_raw, parser.parse_toplevel_expression()) {
         (Some(expected), Ok(received)) => assert_eq!(expected, received),
         (None, Err(_)) => {},
-        (Some(expected), Err(error)) => assert!(false, "Expected {}, but got error {}", expected, error),
-        (None, Ok(received)) => assert!(false, "Expected parse failure, but got {}", received)
+        (Some(expected), Err(error)) => panic!("Expected {}, but got error {}", expected, error),
+        (None, Ok(received)) => panic!("Expected parse failure, but got {}", received)
     }
 }
 
@@ -23,6 +24,12 @@ fn toplevel_parses_integer() {
                  Option::Some(Expression::Integer(42)));
 }
 
+#[test]
+fn toplevel_parses_variable() {
+    assert_parse(&[Token::Identifier("x".to_string())],
+                 Option::Some(Expression::Variable("x".to_string())));
+}
+
 #[test]
 fn toplevel_parses_unary_minus() {
     assert_parse(&[Token::Minus, Token::Integer(5)],
@@ -150,3 +157,399 @@ fn missing_right_paren_is_error() {
                    Token::Integer(1)],
                  None);
 }
+
+fn assert_prints(e: Expression, expected: &str) {
+    assert_eq!(expected, e.to_string());
+}
+
+#[test]
+fn display_uses_infix_syntax() {
+    assert_prints(Expression::BinaryOperation(Box::new(Expression::Integer(1)),
+                                              Operation::Plus,
+                                              Box::new(Expression::Integer(2))),
+                  "1 + 2");
+}
+
+#[test]
+fn display_parenthesizes_looser_operand() {
+    // (1 + 2) * 3
+    let nested = Expression::BinaryOperation(Box::new(Expression::Integer(1)),
+                                             Operation::Plus,
+                                             Box::new(Expression::Integer(2)));
+    assert_prints(Expression::BinaryOperation(Box::new(nested),
+                                              Operation::Times,
+                                              Box::new(Expression::Integer(3))),
+                  "(1 + 2) * 3");
+}
+
+#[test]
+fn display_parenthesizes_right_operand_of_same_precedence() {
+    // 1 - (2 + 3)
+    let nested = Expression::BinaryOperation(Box::new(Expression::Integer(2)),
+                                             Operation::Plus,
+                                             Box::new(Expression::Integer(3)));
+    assert_prints(Expression::BinaryOperation(Box::new(Expression::Integer(1)),
+                                              Operation::Minus,
+                                              Box::new(nested)),
+                  "1 - (2 + 3)");
+}
+
+#[test]
+fn display_omits_parens_for_left_operand_of_same_precedence() {
+    // (1 - 2) - 3
+    let nested = Expression::BinaryOperation(Box::new(Expression::Integer(1)),
+                                             Operation::Minus,
+                                             Box::new(Expression::Integer(2)));
+    assert_prints(Expression::BinaryOperation(Box::new(nested),
+                                              Operation::Minus,
+                                              Box::new(Expression::Integer(3))),
+                  "1 - 2 - 3");
+}
+
+#[test]
+fn display_parenthesizes_under_unary_minus() {
+    let nested = Expression::BinaryOperation(Box::new(Expression::Integer(1)),
+                                             Operation::Times,
+                                             Box::new(Expression::Integer(2)));
+    assert_prints(Expression::UnaryMinus(Box::new(nested)), "-(1 * 2)");
+    assert_prints(Expression::UnaryMinus(Box::new(
+        Expression::UnaryMinus(Box::new(Expression::Integer(1))))),
+                  "--1");
+}
+
+#[test]
+fn expression_shrinks_to_subexpressions_first() {
+    // -(4 + x)
+    let e = Expression::UnaryMinus(Box::new(
+        Expression::BinaryOperation(Box::new(Expression::Integer(4)),
+                                    Operation::Plus,
+                                    Box::new(Expression::Variable("x".to_string())))));
+    let shrinks = e.shrink();
+    assert_eq!(Expression::BinaryOperation(Box::new(Expression::Integer(4)),
+                                           Operation::Plus,
+                                           Box::new(Expression::Variable("x".to_string()))),
+               shrinks[0]);
+    assert_eq!(Expression::UnaryMinus(Box::new(Expression::Integer(4))), shrinks[1]);
+    assert_eq!(Expression::UnaryMinus(Box::new(Expression::Variable("x".to_string()))),
+               shrinks[2]);
+    assert!(shrinks.contains(&Expression::UnaryMinus(Box::new(
+        Expression::BinaryOperation(Box::new(Expression::Integer(2)),
+                                    Operation::Plus,
+                                    Box::new(Expression::Variable("x".to_string())))))));
+}
+
+fn parse_source(source: &str) -> Expression {
+    let tokens = Lexer::tokenize(&mut source.chars()).unwrap();
+    Parser::parse(&tokens).unwrap()
+}
+
+fn binary(e1: Expression, op: Operation, e2: Expression) -> Expression {
+    Expression::BinaryOperation(Box::new(e1), op, Box::new(e2))
+}
+
+fn int(i: u32) -> Expression {
+    Expression::Integer(i)
+}
+
+fn var(name: &str) -> Expression {
+    Expression::Variable(name.to_string())
+}
+
+fn neg(e: Expression) -> Expression {
+    Expression::UnaryMinus(Box::new(e))
+}
+
+fn sqrt(e: Expression) -> Expression {
+    Expression::SquareRoot(Box::new(e))
+}
+
+#[test]
+fn power_associates_right() {
+    assert_eq!(binary(int(2), Operation::Power, binary(int(3), Operation::Power, int(2))),
+               parse_source("2 ^ 3 ^ 2"));
+}
+
+#[test]
+fn power_binds_tighter_than_unary_minus() {
+    assert_eq!(neg(binary(int(2), Operation::Power, int(2))), parse_source("-2 ^ 2"));
+    assert_eq!(binary(int(2), Operation::Power, neg(int(1))), parse_source("2 ^ -1"));
+}
+
+#[test]
+fn superscripts_are_powers() {
+    assert_eq!(binary(var("x"), Operation::Power, int(2)), parse_source("x²"));
+    assert_eq!(binary(int(2), Operation::Power, binary(int(3), Operation::Power, int(10))),
+               parse_source("2^3¹⁰"));
+    assert_eq!(binary(binary(int(1), Operation::Plus, var("x")), Operation::Power, int(3)),
+               parse_source("(1 + x)³"));
+}
+
+#[test]
+fn square_root_is_prefix() {
+    assert_eq!(binary(sqrt(var("x")), Operation::Times, int(2)), parse_source("√x × 2"));
+    assert_eq!(sqrt(binary(var("x"), Operation::Power, int(2))), parse_source("√x²"));
+    assert_eq!(neg(sqrt(int(4))), parse_source("−√4"));
+}
+
+#[test]
+fn comparisons_bind_loosest() {
+    assert_eq!(binary(binary(int(1), Operation::Plus, int(2)),
+                      Operation::LessEqual,
+                      binary(int(3), Operation::Times, int(4))),
+               parse_source("1 + 2 ≤ 3 · 4"));
+}
+
+#[test]
+fn display_parenthesizes_powers() {
+    assert_prints(binary(neg(int(2)), Operation::Power, int(2)), "(-2) ^ 2");
+    assert_prints(binary(binary(int(2), Operation::Power, int(3)), Operation::Power, int(2)),
+                  "(2 ^ 3) ^ 2");
+    assert_prints(binary(int(2), Operation::Power, binary(int(3), Operation::Power, int(2))),
+                  "2 ^ 3 ^ 2");
+    assert_prints(binary(int(2), Operation::Power, neg(int(1))), "2 ^ -1");
+    assert_prints(neg(binary(var("x"), Operation::Power, int(2))), "-x ^ 2");
+}
+
+#[test]
+fn display_parenthesizes_under_square_root() {
+    assert_prints(sqrt(binary(int(1), Operation::Plus, int(2))), "√(1 + 2)");
+    assert_prints(binary(sqrt(var("x")), Operation::Power, int(2)), "(√x) ^ 2");
+    assert_prints(binary(var("π"), Operation::NotEqual, int(3)), "π != 3");
+}
+
+fn times(e1: Expression, e2: Expression) -> Expression {
+    binary(e1, Operation::Times, e2)
+}
+
+#[test]
+fn juxtaposition_is_multiplication() {
+    assert_eq!(times(int(2), binary(int(3), Operation::Plus, int(4))), parse_source("2(3+4)"));
+    assert_eq!(times(binary(int(1), Operation::Plus, int(2)), binary(int(3), Operation::Plus, int(4))),
+               parse_source("(1+2)(3+4)"));
+    assert_eq!(times(int(3), var("x")), parse_source("3x"));
+    assert_eq!(times(int(2), var("π")), parse_source("2π"));
+    assert_eq!(times(times(int(2), var("x")), var("y")), parse_source("2x y"));
+    assert_eq!(times(int(2), sqrt(int(3))), parse_source("2√3"));
+}
+
+#[test]
+fn juxtaposition_binds_tighter_than_division() {
+    assert_eq!(binary(int(1), Operation::Div, times(int(2), var("x"))), parse_source("1/2x"));
+    assert_eq!(times(binary(int(1), Operation::Div, int(2)), var("x")), parse_source("1/2*x"));
+    assert_eq!(binary(int(1), Operation::Plus, times(int(2), var("x"))), parse_source("1 + 2x"));
+}
+
+#[test]
+fn juxtaposition_binds_looser_than_powers_and_prefixes() {
+    assert_eq!(times(int(2), binary(var("x"), Operation::Power, int(2))), parse_source("2x²"));
+    assert_eq!(times(int(2), binary(var("x"), Operation::Power, int(2))), parse_source("2x^2"));
+    assert_eq!(times(neg(int(2)), var("x")), parse_source("-2x"));
+    assert_eq!(times(sqrt(int(2)), var("x")), parse_source("√2x"));
+}
+
+#[test]
+fn only_some_factors_can_be_juxtaposed() {
+    assert_eq!(binary(int(2), Operation::Minus, int(3)), parse_source("2 -3"));
+    assert!(Parser::parse(&Lexer::tokenize(&mut "2 3".chars()).unwrap()).is_err());
+    assert!(Parser::parse(&Lexer::tokenize(&mut "x 2".chars()).unwrap()).is_err());
+}
+
+#[test]
+fn strict_parsing_rejects_juxtaposition() {
+    for source in &["2(3+4)", "(1+2)(3+4)", "3x", "2π", "2√3"] {
+        let tokens = Lexer::tokenize(&mut source.chars()).unwrap();
+        assert!(Parser::parse_strict(&tokens).is_err(), "{} parsed", source);
+    }
+    let tokens = Lexer::tokenize(&mut "1/2*x".chars()).unwrap();
+    assert_eq!(Parser::parse(&tokens), Parser::parse_strict(&tokens));
+}
+
+fn factorial(e: Expression) -> Expression {
+    Expression::Factorial(Box::new(e))
+}
+
+fn percent(e: Expression) -> Expression {
+    Expression::Percent(Box::new(e))
+}
+
+fn percent_change(e1: Expression, op: Operation, e2: Expression) -> Expression {
+    Expression::PercentChange(Box::new(e1), op, Box::new(e2))
+}
+
+#[test]
+fn postfix_operators_bind_tighter_than_prefixes() {
+    assert_eq!(factorial(factorial(int(3))), parse_source("3!!"));
+    assert_eq!(neg(factorial(int(3))), parse_source("-3!"));
+    assert_eq!(binary(int(2), Operation::Power, factorial(int(3))), parse_source("2^3!"));
+    assert_eq!(factorial(binary(int(3), Operation::Power, int(2))), parse_source("3²!"));
+    assert_eq!(times(int(2), factorial(var("x"))), parse_source("2x!"));
+    assert_eq!(binary(factorial(int(3)), Operation::NotEqual, int(6)), parse_source("3! != 6"));
+    assert_eq!(binary(int(3), Operation::NotEqual, int(6)), parse_source("3!=6"));
+}
+
+#[test]
+fn bare_percentage_after_plus_or_minus_is_relative() {
+    assert_eq!(percent_change(int(200), Operation::Plus, int(10)), parse_source("200 + 10%"));
+    assert_eq!(percent_change(int(200), Operation::Minus, int(10)), parse_source("200 - 10%"));
+    assert_eq!(binary(int(200), Operation::Plus, percent(int(10))), parse_source("200 + (10%)"));
+    assert_eq!(binary(int(50), Operation::Times, percent(int(10))), parse_source("50 * 10%"));
+    assert_eq!(binary(int(200), Operation::Plus, times(percent(int(10)), var("x"))),
+               parse_source("200 + 10% x"));
+    assert_eq!(percent_change(binary(int(1), Operation::Plus, int(2)), Operation::Plus, int(3)),
+               parse_source("1 + 2 + 3%"));
+}
+
+#[test]
+fn plain_percentages_are_hundredths() {
+    let options = ParseOptions {
+        relative_percent: false,
+        ..ParseOptions::default()
+    };
+    let tokens = Lexer::tokenize(&mut "200 + 10%".chars()).unwrap();
+    assert_eq!(Ok(binary(int(200), Operation::Plus, percent(int(10)))), Parser::parse_with(&tokens, options));
+}
+
+#[test]
+fn display_keeps_percentages_apart() {
+    assert_prints(percent_change(int(200), Operation::Plus, int(10)), "200 + 10%");
+    assert_prints(binary(int(200), Operation::Plus, percent(int(10))), "200 + (10%)");
+    assert_prints(percent_change(int(1), Operation::Minus, binary(int(2), Operation::Plus, int(3))),
+                  "1 - (2 + 3)%");
+    assert_prints(factorial(neg(int(3))), "(-3)!");
+    assert_prints(neg(factorial(int(3))), "-3!");
+    for source in &["200 + 10%", "200 + (10%)", "(-3)!!", "2 ^ 3!", "x% ^ 2"] {
+        let e = parse_source(source);
+        assert_eq!(e, parse_source(&e.to_string()), "{} printed as {}", source, e);
+    }
+}
+
+fn imaginary(i: u32) -> Expression {
+    Expression::Imaginary(i)
+}
+
+fn call(name: &str, arguments: Vec<Expression>) -> Expression {
+    Expression::Call(name.to_string(), arguments)
+}
+
+#[test]
+fn imaginary_literals_are_primaries() {
+    assert_eq!(binary(int(3), Operation::Plus, imaginary(4)), parse_source("3 + 4i"));
+    assert_eq!(neg(binary(imaginary(2), Operation::Power, int(2))), parse_source("-2j²"));
+    assert_eq!(times(imaginary(2), var("x")), parse_source("2i x"));
+    assert!(Parser::parse(&Lexer::tokenize(&mut "2 3i".chars()).unwrap()).is_err());
+}
+
+#[test]
+fn function_names_before_parentheses_are_calls() {
+    assert_eq!(call("abs", vec![binary(int(3), Operation::Plus, imaginary(4))]), parse_source("abs(3 + 4i)"));
+    assert_eq!(times(int(2), call("sqrt", vec![int(2)])), parse_source("2sqrt(2)"));
+    assert_eq!(binary(call("re", vec![var("z")]), Operation::Power, int(2)), parse_source("re(z)²"));
+    assert_eq!(times(var("x"), int(2)), parse_source("x(2)"));
+    assert_eq!(binary(var("abs"), Operation::Plus, int(1)), parse_source("abs + 1"));
+    let tokens = Lexer::tokenize(&mut "exp(1)".chars()).unwrap();
+    assert_eq!(Parser::parse(&tokens), Parser::parse_strict(&tokens));
+}
+
+#[test]
+fn calls_check_their_arguments() {
+    let parse = |source: &str| Parser::parse(&Lexer::tokenize(&mut source.chars()).unwrap());
+    assert_eq!(Err("abs takes 1 argument, not 2".to_string()), parse("abs(1, 2)"));
+    assert_eq!(Err("exp takes 1 argument, not 0".to_string()), parse("exp()"));
+    assert_eq!(Err("solve takes 2 to 3 arguments, not 4".to_string()), parse("solve(x = 1, x, 0..1, 2)"));
+    assert!(parse("abs(1").is_err());
+    assert!(parse("abs(1 2)").is_err());
+}
+
+#[test]
+fn display_writes_calls_and_imaginary_literals() {
+    assert_prints(call("conj", vec![binary(int(1), Operation::Minus, imaginary(1))]), "conj(1 - 1i)");
+    assert_prints(binary(imaginary(2), Operation::Power, int(2)), "2i ^ 2");
+    for source in &["-abs(x)!", "im(3i)²", "(2i)!"] {
+        let e = parse_source(source);
+        assert_eq!(e, parse_source(&e.to_string()), "{} printed as {}", source, e);
+    }
+}
+
+fn convert(e: Expression, unit: Expression) -> Expression {
+    Expression::Convert(Box::new(e), Box::new(unit))
+}
+
+#[test]
+fn conversion_binds_loosest() {
+    let speed = binary(var("m"), Operation::Div, var("s"));
+    assert_eq!(convert(times(int(60), var("mph")), speed.clone()), parse_source("60 mph to m/s"));
+    assert_eq!(convert(times(int(60), var("mph")), speed), parse_source("60 mph in m/s"));
+    assert_eq!(convert(binary(var("x"), Operation::Less, var("y")), var("m")), parse_source("x < y to m"));
+    assert_eq!(convert(convert(var("x"), var("km")), var("m")), parse_source("x to km to m"));
+    assert_eq!(call("abs", vec![convert(var("x"), var("m"))]), parse_source("abs(x to m)"));
+    assert_eq!(times(Expression::Decimal("9.81".to_string()), var("m")), parse_source("9.81 m"));
+}
+
+#[test]
+fn display_writes_conversions_and_decimals() {
+    assert_prints(convert(times(Expression::Decimal("2.50".to_string()), var("km")), var("m")), "2.50 * km to m");
+    assert_prints(times(int(2), convert(var("x"), var("m"))), "2 * (x to m)");
+    assert_prints(convert(var("x"), convert(var("y"), var("m"))), "x to (y to m)");
+    for source in &["1 in inch", "(x to m)²", "-(3.5 to s)!"] {
+        let e = parse_source(source);
+        assert_eq!(e, parse_source(&e.to_string()), "{} printed as {}", source, e);
+    }
+}
+
+#[test]
+fn durations_written_together_add_up() {
+    let three_days = times(int(3), var("d"));
+    let four_hours = times(int(4), var("h"));
+    assert_eq!(binary(three_days.clone(), Operation::Plus, four_hours.clone()), parse_source("3d 4h"));
+    assert_eq!(times(times(int(2), var("x")), var("h")), parse_source("2 x h"));
+    let date = Expression::Date("2026-10-18".to_string());
+    assert_eq!(binary(date.clone(), Operation::Plus, binary(three_days, Operation::Plus, four_hours)),
+               parse_source("2026-10-18 + 3d 4h"));
+    assert_prints(binary(date, Operation::Minus, Expression::Time("9:30".to_string())), "2026-10-18 - 9:30");
+}
+
+fn range(start: Expression, end: Expression, step: Option<Expression>) -> Expression {
+    Expression::Range(Box::new(start), Box::new(end), step.map(Box::new))
+}
+
+#[test]
+fn ranges_bind_between_comparison_and_addition() {
+    let n_plus_1 = binary(var("n"), Operation::Plus, int(1));
+    assert_eq!(range(int(1), n_plus_1.clone(), None), parse_source("1..n + 1"));
+    assert_eq!(range(int(10), int(1), Some(neg(int(2)))), parse_source("10..1 step -2"));
+    assert_eq!(binary(range(int(1), int(3), None), Operation::Less, int(2)), parse_source("1..3 < 2"));
+    assert_eq!(convert(range(int(1), int(3), None), var("m")), parse_source("1..3 to m"));
+    assert!(Parser::parse(&Lexer::tokenize(&mut "1..2..3".chars()).unwrap()).is_err());
+}
+
+#[test]
+fn lists_and_indexing_parse() {
+    let list = Expression::List(vec![int(1), binary(int(2), Operation::Plus, int(3))]);
+    assert_eq!(list.clone(), parse_source("[1, 2 + 3]"));
+    assert_eq!(Expression::List(vec![]), parse_source("[]"));
+    let index = Expression::Index(Box::new(list), Box::new(neg(int(1))));
+    assert_eq!(neg(index.clone()), parse_source("-[1, 2 + 3][-1]"));
+    assert_eq!(call("sum", vec![range(int(1), int(10), None)]), parse_source("sum(1..10)"));
+    assert!(Parser::parse(&Lexer::tokenize(&mut "[1, 2".chars()).unwrap()).is_err());
+}
+
+#[test]
+fn display_writes_lists_and_ranges() {
+    assert_prints(range(int(1), int(10), Some(int(2))), "1..10 step 2");
+    assert_prints(times(int(2), range(int(1), int(3), None)), "2 * (1..3)");
+    assert_prints(range(range(int(1), int(2), None), int(3), None), "(1..2)..3");
+    for source in &["[1, [2, 3]][1][0]", "(1..3)[0]", "[x, -y]²", "sum([1, 2] * 2..5)"] {
+        let e = parse_source(source);
+        assert_eq!(e, parse_source(&e.to_string()), "{} printed as {}", source, e);
+    }
+}
+
+#[test]
+fn matrix_product_binds_like_times() {
+    let a_at_b = binary(var("a"), Operation::MatrixTimes, var("b"));
+    assert_eq!(binary(a_at_b.clone(), Operation::Times, int(2)), parse_source("a @ b * 2"));
+    assert_eq!(binary(int(1), Operation::Plus, a_at_b), parse_source("1 + a @ b"));
+    assert_prints(binary(var("a"), Operation::MatrixTimes, binary(var("b"), Operation::MatrixTimes, var("c"))),
+                  "a @ (b @ c)");
+    assert_eq!(call("solve", vec![var("a"), var("b")]), parse_source("solve(a, b)"));
+}
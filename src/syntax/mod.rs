@@ -0,0 +1,84 @@
+use std::fmt;
+
+use super::lexer::{Lexer, SyntaxKind, SyntaxToken, Token};
+
+// A lossless concrete syntax tree for a script.  A script is a sequence
+// of lines, each holding at most one expression and an optional trailing
+// comment.  Nothing is discarded: printing the tree gives back the exact
+// source it was built from.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SyntaxTree {
+    pub lines: Vec<Line>
+}
+
+// every token on a single line, including the terminating newline (if any)
+#[derive(Debug, Eq, PartialEq)]
+pub struct Line {
+    pub tokens: Vec<SyntaxToken>
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> Result<SyntaxTree, String> {
+        SyntaxTree::read(Lexer::new(source.chars()))
+    }
+
+    // builds the tree a token at a time, as the lexer reads them
+    pub fn read<I: Iterator<Item = char>>(mut lexer: Lexer<I>) -> Result<SyntaxTree, String> {
+        let mut lines = vec![];
+        let mut current = vec![];
+
+        while let Some(token) = lexer.next_lossless() {
+            let token = token.map_err(|e| e.to_string())?;
+            let is_newline = token.kind == SyntaxKind::Newline;
+            current.push(token);
+            if is_newline {
+                lines.push(Line { tokens: current });
+                current = vec![];
+            }
+        }
+        if !current.is_empty() {
+            lines.push(Line { tokens: current });
+        }
+
+        Result::Ok(SyntaxTree { lines })
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            write!(f, "{}", line)?;
+        }
+        Result::Ok(())
+    }
+}
+
+impl Line {
+    // the tokens the parser cares about, with all trivia removed
+    pub fn significant_tokens(&self) -> Vec<Token> {
+        self.tokens.iter().filter_map(|token| {
+            match token.kind {
+                SyntaxKind::Token(ref t) => Option::Some(t.clone()),
+                _ => Option::None
+            }
+        }).collect()
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.tokens.iter()
+            .find(|token| token.kind == SyntaxKind::Comment)
+            .map(|token| token.text.as_str())
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in &self.tokens {
+            f.write_str(&token.text)?;
+        }
+        Result::Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
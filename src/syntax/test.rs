@@ -0,0 +1,32 @@
+use super::SyntaxTree;
+use lexer::Token;
+
+fn assert_roundtrips(source: &str) {
+    let tree = SyntaxTree::parse(source).unwrap();
+    assert_eq!(source, tree.to_string());
+}
+
+#[test]
+fn empty_source_roundtrips() {
+    assert_roundtrips("");
+}
+
+#[test]
+fn whitespace_and_comments_roundtrip() {
+    assert_roundtrips("  1 +2   # one plus two\n\n# only a comment\r\n(3)*  -4");
+}
+
+#[test]
+fn lines_split_on_newlines() {
+    let tree = SyntaxTree::parse("1 + 2\n\n3 # three").unwrap();
+    assert_eq!(3, tree.lines.len());
+    assert_eq!(vec![Token::Integer(1), Token::Plus, Token::Integer(2)],
+               tree.lines[0].significant_tokens());
+    assert!(tree.lines[1].significant_tokens().is_empty());
+    assert_eq!(Option::Some("# three"), tree.lines[2].comment());
+}
+
+#[test]
+fn unknown_character_is_error() {
+    assert!(SyntaxTree::parse("1 + $").is_err());
+}
@@ -0,0 +1,362 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+// An arbitrary-precision integer, stored as a sign and a magnitude of
+// 32-bit limbs, least significant first.  The magnitude never has
+// leading zero limbs, and zero is never negative, so equal numbers have
+// equal representations.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BigInteger {
+    negative: bool,
+    magnitude: Vec<u32>
+}
+
+const LIMB_BITS: u32 = 32;
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0;
+    for (index, limb) in long.iter().enumerate() {
+        let sum = u64::from(*limb) + u64::from(*short.get(index).unwrap_or(&0)) + carry;
+        result.push(sum as u32);
+        carry = sum >> LIMB_BITS;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+// a - b, where a is at least b
+fn subtract_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (index, limb) in a.iter().enumerate() {
+        let difference = i64::from(*limb) - i64::from(*b.get(index).unwrap_or(&0)) - borrow;
+        result.push(difference as u32);
+        borrow = if difference < 0 { 1 } else { 0 };
+    }
+    trim(&mut result);
+    result
+}
+
+fn multiply_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut result = vec![0; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, y) in b.iter().enumerate() {
+            let product = u64::from(*x) * u64::from(*y) + u64::from(result[i + j]) + carry;
+            result[i + j] = product as u32;
+            carry = product >> LIMB_BITS;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+fn divide_magnitude_by_limb(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0; a.len()];
+    let mut remainder = 0u64;
+    for (index, limb) in a.iter().enumerate().rev() {
+        let dividend = (remainder << LIMB_BITS) | u64::from(*limb);
+        quotient[index] = (dividend / u64::from(divisor)) as u32;
+        remainder = dividend % u64::from(divisor);
+    }
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+fn shift_left(a: &[u32], shift: u32, extra_limb: bool) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+    for limb in a {
+        result.push((limb << shift) | carry);
+        carry = if shift == 0 { 0 } else { limb >> (LIMB_BITS - shift) };
+    }
+    if extra_limb {
+        result.push(carry);
+    }
+    result
+}
+
+// Knuth's algorithm D (TAOCP volume 2, 4.3.1), dividing magnitudes with
+// a divisor of at least two limbs.  Each quotient limb is estimated from
+// the top limbs, after normalizing so the divisor's top bit is set, and
+// corrected at most twice.
+fn divide_magnitudes(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let n = b.len();
+    let m = a.len() - n;
+    let shift = b[n - 1].leading_zeros();
+    let divisor = shift_left(b, shift, false);
+    let mut dividend = shift_left(a, shift, true);
+    let mut quotient = vec![0; m + 1];
+    let base = 1u64 << LIMB_BITS;
+
+    for j in (0..=m).rev() {
+        let top = (u64::from(dividend[j + n]) << LIMB_BITS) | u64::from(dividend[j + n - 1]);
+        let mut estimate = top / u64::from(divisor[n - 1]);
+        let mut remainder = top % u64::from(divisor[n - 1]);
+        while estimate >= base ||
+              estimate * u64::from(divisor[n - 2]) > ((remainder << LIMB_BITS) | u64::from(dividend[j + n - 2])) {
+            estimate -= 1;
+            remainder += u64::from(divisor[n - 1]);
+            if remainder >= base {
+                break;
+            }
+        }
+
+        // subtract estimate * divisor from the current window
+        let mut borrow: i64 = 0;
+        for i in 0..n {
+            let product = estimate * u64::from(divisor[i]);
+            let difference = i64::from(dividend[i + j]) - borrow - (product & 0xFFFF_FFFF) as i64;
+            dividend[i + j] = difference as u32;
+            borrow = (product >> LIMB_BITS) as i64 - (difference >> LIMB_BITS);
+        }
+        let difference = i64::from(dividend[j + n]) - borrow;
+        dividend[j + n] = difference as u32;
+
+        // the estimate was one too large: add the divisor back
+        if difference < 0 {
+            estimate -= 1;
+            let mut carry: i64 = 0;
+            for i in 0..n {
+                let sum = i64::from(dividend[i + j]) + i64::from(divisor[i]) + carry;
+                dividend[i + j] = sum as u32;
+                carry = sum >> LIMB_BITS;
+            }
+            dividend[j + n] = (i64::from(dividend[j + n]) + carry) as u32;
+        }
+        quotient[j] = estimate as u32;
+    }
+
+    let mut remainder: Vec<u32> = (0..n).map(|i| {
+        let high = if shift == 0 { 0 } else { dividend[i + 1] << (LIMB_BITS - shift) };
+        (dividend[i] >> shift) | high
+    }).collect();
+    trim(&mut quotient);
+    trim(&mut remainder);
+    (quotient, remainder)
+}
+
+impl BigInteger {
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> BigInteger {
+        trim(&mut magnitude);
+        BigInteger {
+            negative: negative && !magnitude.is_empty(),
+            magnitude
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // the number of bits in the magnitude; zero has none
+    pub fn bits(&self) -> usize {
+        match self.magnitude.last() {
+            Some(top) => self.magnitude.len() * LIMB_BITS as usize - top.leading_zeros() as usize,
+            None => 0
+        }
+    }
+
+    pub fn to_i32(&self) -> Option<i32> {
+        let magnitude = match self.magnitude.len() {
+            0 => 0,
+            1 => i64::from(self.magnitude[0]),
+            _ => return None
+        };
+        let value = if self.negative { -magnitude } else { magnitude };
+        if value >= i64::from(i32::MIN) && value <= i64::from(i32::MAX) {
+            Some(value as i32)
+        } else {
+            None
+        }
+    }
+
+    // The nearest f64, or an infinity when out of range.  The top 64 bits
+    // are converted with the lowest one set if anything below them is, so
+    // they round the way the whole number would.
+    pub fn to_f64(&self) -> f64 {
+        let shift = self.bits().saturating_sub(64);
+        let limb = shift / LIMB_BITS as usize;
+        let offset = shift % LIMB_BITS as usize;
+        let mut window: u128 = 0;
+        for index in (limb..self.magnitude.len().min(limb + 3)).rev() {
+            window = (window << LIMB_BITS) | u128::from(self.magnitude[index]);
+        }
+        let sticky = self.magnitude[..limb].iter().any(|l| *l != 0) ||
+                     window & ((1 << offset) - 1) != 0;
+        let top = (window >> offset) as u64 | u64::from(sticky);
+        let value = top as f64 * 2f64.powi(shift as i32);
+        if self.negative { -value } else { value }
+    }
+
+    // self * 2^bits
+    pub fn shifted(&self, bits: usize) -> BigInteger {
+        let mut magnitude = vec![0; bits / LIMB_BITS as usize];
+        magnitude.extend(shift_left(&self.magnitude, (bits % LIMB_BITS as usize) as u32, true));
+        BigInteger::new(self.negative, magnitude)
+    }
+
+    pub fn multiply_by(&self, factor: u32) -> BigInteger {
+        BigInteger::new(self.negative, multiply_magnitudes(&self.magnitude, &[factor]))
+    }
+
+    // Quotient and remainder, truncating toward zero as i32 division does,
+    // so the remainder takes the dividend's sign.  None on division by zero.
+    pub fn divide(&self, divisor: &BigInteger) -> Option<(BigInteger, BigInteger)> {
+        let (quotient, remainder) = match divisor.magnitude.len() {
+            0 => return None,
+            _ if compare_magnitudes(&self.magnitude, &divisor.magnitude) == Ordering::Less =>
+                (vec![], self.magnitude.clone()),
+            1 => {
+                let (quotient, remainder) = divide_magnitude_by_limb(&self.magnitude, divisor.magnitude[0]);
+                (quotient, vec![remainder])
+            },
+            _ => divide_magnitudes(&self.magnitude, &divisor.magnitude)
+        };
+        Some((BigInteger::new(self.negative != divisor.negative, quotient),
+              BigInteger::new(self.negative, remainder)))
+    }
+}
+
+impl From<i32> for BigInteger {
+    fn from(i: i32) -> BigInteger {
+        BigInteger::from(i128::from(i))
+    }
+}
+
+impl From<i128> for BigInteger {
+    fn from(i: i128) -> BigInteger {
+        let mut magnitude = vec![];
+        let mut rest = i.unsigned_abs();
+        while rest > 0 {
+            magnitude.push(rest as u32);
+            rest >>= LIMB_BITS;
+        }
+        BigInteger::new(i < 0, magnitude)
+    }
+}
+
+impl Ord for BigInteger {
+    fn cmp(&self, other: &BigInteger) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude)
+        }
+    }
+}
+
+impl PartialOrd for BigInteger {
+    fn partial_cmp(&self, other: &BigInteger) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInteger {
+    type Output = BigInteger;
+
+    fn neg(self) -> BigInteger {
+        BigInteger::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Add for &BigInteger {
+    type Output = BigInteger;
+
+    fn add(self, other: &BigInteger) -> BigInteger {
+        if self.negative == other.negative {
+            return BigInteger::new(self.negative, add_magnitudes(&self.magnitude, &other.magnitude));
+        }
+        // opposite signs: the larger magnitude decides the sign
+        match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Less =>
+                BigInteger::new(other.negative, subtract_magnitudes(&other.magnitude, &self.magnitude)),
+            _ => BigInteger::new(self.negative, subtract_magnitudes(&self.magnitude, &other.magnitude))
+        }
+    }
+}
+
+impl Sub for &BigInteger {
+    type Output = BigInteger;
+
+    fn sub(self, other: &BigInteger) -> BigInteger {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInteger {
+    type Output = BigInteger;
+
+    fn mul(self, other: &BigInteger) -> BigInteger {
+        BigInteger::new(self.negative != other.negative,
+                        multiply_magnitudes(&self.magnitude, &other.magnitude))
+    }
+}
+
+// panics on division by zero, like the primitive integers
+impl Div for &BigInteger {
+    type Output = BigInteger;
+
+    fn div(self, other: &BigInteger) -> BigInteger {
+        self.divide(other).expect("division by zero").0
+    }
+}
+
+impl Rem for &BigInteger {
+    type Output = BigInteger;
+
+    fn rem(self, other: &BigInteger) -> BigInteger {
+        self.divide(other).expect("division by zero").1
+    }
+}
+
+// decimal, converting nine digits at a time
+impl fmt::Display for BigInteger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        let mut chunks = vec![];
+        let mut rest = self.magnitude.clone();
+        while !rest.is_empty() {
+            let (quotient, chunk) = divide_magnitude_by_limb(&rest, 1_000_000_000);
+            chunks.push(chunk);
+            rest = quotient;
+        }
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{}", first)?;
+        }
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Result::Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// A complex number in rectangular form.  The arithmetic is plain f64
+// arithmetic; whether a result overflowed or is undefined is left to the
+// caller, which sees an infinity or NaN in either part.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn from_polar(modulus: f64, argument: f64) -> Complex {
+        Complex::new(modulus * argument.cos(), modulus * argument.sin())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+
+    // the modulus, without overflowing when its square would
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    // the argument, in (-π, π]
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(&self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn exp(&self) -> Complex {
+        Complex::from_polar(self.re.exp(), self.im)
+    }
+
+    // the principal logarithm
+    pub fn ln(&self) -> Complex {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    // The principal square root, with a non-negative real part.  Whichever
+    // part is larger is found first, so neither loses digits to
+    // cancellation, and a negative real has an exact imaginary root.
+    pub fn sqrt(&self) -> Complex {
+        if self.is_zero() {
+            return Complex::new(0.0, self.im);
+        }
+        let t = ((self.abs() + self.re.abs()) / 2.0).sqrt();
+        if self.re >= 0.0 {
+            Complex::new(t, self.im / (2.0 * t))
+        } else {
+            Complex::new(self.im.abs() / (2.0 * t), t.copysign(self.im))
+        }
+    }
+
+    // by repeated squaring, so small integer powers come out exact
+    pub fn powi(&self, exponent: i32) -> Complex {
+        let mut result = Complex::new(1.0, 0.0);
+        let mut square = *self;
+        let mut n = exponent.unsigned_abs();
+        while n > 0 {
+            if n % 2 == 1 {
+                result = result * square;
+            }
+            square = square * square;
+            n /= 2;
+        }
+        if exponent < 0 {
+            Complex::new(1.0, 0.0) / result
+        } else {
+            result
+        }
+    }
+
+    // the principal value of self ^ exponent; 0 ^ w is only defined when
+    // w has a positive real part
+    pub fn powc(&self, exponent: Complex) -> Complex {
+        if self.is_zero() {
+            return if exponent.re > 0.0 {
+                Complex::new(0.0, 0.0)
+            } else {
+                Complex::new(f64::NAN, f64::NAN)
+            };
+        }
+        (exponent * self.ln()).exp()
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Complex {
+        Complex::new(re, 0.0)
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im,
+                     self.re * other.im + self.im * other.re)
+    }
+}
+
+// Smith's algorithm, which scales by the larger part of the divisor so
+// the intermediate results overflow only when the quotient does.
+// Dividing by zero gives NaN.
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        if other.is_zero() {
+            return Complex::new(f64::NAN, f64::NAN);
+        }
+        if other.re.abs() >= other.im.abs() {
+            let ratio = other.im / other.re;
+            let denominator = other.re + other.im * ratio;
+            Complex::new((self.re + self.im * ratio) / denominator,
+                         (self.im - self.re * ratio) / denominator)
+        } else {
+            let ratio = other.re / other.im;
+            let denominator = other.re * ratio + other.im;
+            Complex::new((self.re * ratio + self.im) / denominator,
+                         (self.im * ratio - self.re) / denominator)
+        }
+    }
+}
+
+// Rectangular form, as in 3+4i, -i or 2.5i.  A part within a few ULPs of
+// zero beside the whole, as rounding leaves of the imaginary part of
+// exp(πi), is written as zero, so that prints -1.
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let noise = 4.0 * f64::EPSILON * self.abs();
+        let part = |x: f64| if x.abs() <= noise { 0.0 } else { x };
+        let (re, im) = (part(self.re), part(self.im));
+        if im == 0.0 {
+            return write!(f, "{}", re);
+        }
+        let sign = if im < 0.0 { "-" } else { "+" };
+        if re != 0.0 {
+            write!(f, "{}{}", re, sign)?;
+        } else if im < 0.0 {
+            f.write_str(sign)?;
+        }
+        if im.abs() != 1.0 {
+            write!(f, "{}", im.abs())?;
+        }
+        f.write_str("i")
+    }
+}
+
@@ -0,0 +1,232 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MILLISECONDS_PER_DAY: i64 = 86_400_000;
+
+// the days since 1970-01-01 of a day in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// the year, month and day of a count of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+// years with four digits, as ISO 8601 writes them without an agreed expansion
+const FIRST_YEAR: i64 = 0;
+const LAST_YEAR: i64 = 9999;
+
+fn in_range(day: i64) -> bool {
+    let (year, _, _) = civil_from_days(day);
+    (FIRST_YEAR..=LAST_YEAR).contains(&year)
+}
+
+// the digits of a number written with exactly the given count of them
+fn digits(text: &str, count: usize) -> Option<i64> {
+    if text.len() == count && text.bytes().all(|b| b.is_ascii_digit()) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+// A day, a time of day, or a time on a day, to the millisecond.  There
+// are no time zones: today and now are in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    // days since 1970-01-01
+    day: Option<i64>,
+    // milliseconds since midnight
+    time: Option<i64>
+}
+
+impl DateTime {
+    // None unless the day is in the month, and the year has four digits
+    pub fn date(year: i64, month: i64, day: i64) -> Option<DateTime> {
+        if !(FIRST_YEAR..=LAST_YEAR).contains(&year) || !(1..=12).contains(&month) ||
+           day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(DateTime { day: Some(days_from_civil(year, month, day)), time: None })
+    }
+
+    // None unless this is a time on a 24 hour clock
+    pub fn time_of_day(hour: i64, minute: i64, millisecond: i64) -> Option<DateTime> {
+        if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..60_000).contains(&millisecond) {
+            return None;
+        }
+        Some(DateTime { day: None, time: Some((hour * 60 + minute) * 60_000 + millisecond) })
+    }
+
+    // YYYY-MM-DD
+    pub fn parse_date(text: &str) -> Option<DateTime> {
+        let mut parts = text.split('-');
+        let year = digits(parts.next()?, 4)?;
+        let month = digits(parts.next()?, 2)?;
+        let day = digits(parts.next()?, 2)?;
+        if parts.next().is_some() {
+            return None;
+        }
+        DateTime::date(year, month, day)
+    }
+
+    // H:MM, HH:MM, HH:MM:SS or HH:MM:SS.fff, with any fraction of a second
+    // rounded to the millisecond
+    pub fn parse_time(text: &str) -> Option<DateTime> {
+        let mut parts = text.split(':');
+        let hour = parts.next()?;
+        let hour = digits(hour, 2).or_else(|| digits(hour, 1))?;
+        let minute = digits(parts.next()?, 2)?;
+        let millisecond = match parts.next() {
+            Some(second) => {
+                let (whole, fraction) = match second.find('.') {
+                    Some(point) => (&second[..point], &second[point..]),
+                    None => (second, "")
+                };
+                let fraction: f64 = if fraction.is_empty() { 0.0 } else { fraction.parse().ok()? };
+                digits(whole, 2)? * 1000 + (fraction * 1000.0).round() as i64
+            },
+            None => 0
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        DateTime::time_of_day(hour, minute, millisecond)
+    }
+
+    // the current time in UTC
+    pub fn now() -> DateTime {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH)
+            .expect("the clock is set after 1970");
+        let milliseconds = since_epoch.as_millis() as i64;
+        DateTime {
+            day: Some(milliseconds.div_euclid(MILLISECONDS_PER_DAY)),
+            time: Some(milliseconds.rem_euclid(MILLISECONDS_PER_DAY))
+        }
+    }
+
+    // the current day in UTC
+    pub fn today() -> DateTime {
+        DateTime { time: None, ..DateTime::now() }
+    }
+
+    pub fn has_date(&self) -> bool {
+        self.day.is_some()
+    }
+
+    pub fn has_time(&self) -> bool {
+        self.time.is_some()
+    }
+
+    // what kind of value this is, for errors
+    pub fn describe(&self) -> &'static str {
+        match (self.day, self.time) {
+            (Some(_), None) => "a date",
+            (None, _) => "a time of day",
+            (Some(_), Some(_)) => "a date with a time"
+        }
+    }
+
+    // this date at a time of day; None unless this is only a date and
+    // that only a time
+    pub fn at(&self, time: &DateTime) -> Option<DateTime> {
+        match (self.day, self.time, time.day, time.time) {
+            (Some(day), None, None, Some(time)) => Some(DateTime { day: Some(day), time: Some(time) }),
+            _ => None
+        }
+    }
+
+    // Later by the given milliseconds, which may be negative.  A date
+    // moved by whole days stays a date; otherwise it gets a time, from
+    // midnight.  A time of day wraps around midnight.  None if the year
+    // leaves four digits.
+    pub fn add_milliseconds(&self, milliseconds: i64) -> Option<DateTime> {
+        let time = self.time.unwrap_or(0).checked_add(milliseconds)?;
+        let day = match self.day {
+            Some(day) => day.checked_add(time.div_euclid(MILLISECONDS_PER_DAY))?,
+            None => return Some(DateTime { day: None, time: Some(time.rem_euclid(MILLISECONDS_PER_DAY)) })
+        };
+        if !in_range(day) {
+            return None;
+        }
+        let time = time.rem_euclid(MILLISECONDS_PER_DAY);
+        Some(DateTime {
+            day: Some(day),
+            time: if self.time.is_none() && time == 0 { None } else { Some(time) }
+        })
+    }
+
+    // Later by the given calendar months, keeping the time.  The day of
+    // the month stays the same unless the new month is too short, when it
+    // becomes the last day: 2026-01-31 plus a month is 2026-02-28.  None
+    // for a time of day, or if the year leaves four digits.
+    pub fn add_months(&self, months: i64) -> Option<DateTime> {
+        let (year, month, day) = civil_from_days(self.day?);
+        let index = (year * 12 + month - 1).checked_add(months)?;
+        let (year, month) = (index.div_euclid(12), index.rem_euclid(12) + 1);
+        if !(FIRST_YEAR..=LAST_YEAR).contains(&year) {
+            return None;
+        }
+        let day = day.min(days_in_month(year, month));
+        Some(DateTime { day: Some(days_from_civil(year, month, day)), ..*self })
+    }
+
+    // The milliseconds from other to this.  A date counts from its
+    // midnight.  None if only one of them has a date.
+    pub fn since(&self, other: &DateTime) -> Option<i64> {
+        let start = |d: &DateTime| d.day.map(|day| day * MILLISECONDS_PER_DAY);
+        let (mine, theirs) = match (start(self), start(other)) {
+            (Some(mine), Some(theirs)) => (mine, theirs),
+            (None, None) => (0, 0),
+            _ => return None
+        };
+        Some(mine + self.time.unwrap_or(0) - theirs - other.time.unwrap_or(0))
+    }
+}
+
+// ISO 8601: 2026-10-18, 14:30:00 or 2026-10-18T14:30:00, with
+// milliseconds only when there are some
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(day) = self.day {
+            let (year, month, day) = civil_from_days(day);
+            write!(f, "{:04}-{:02}-{:02}", year, month, day)?;
+            if self.time.is_some() {
+                f.write_str("T")?;
+            }
+        }
+        if let Some(time) = self.time {
+            let (seconds, milliseconds) = (time / 1000, time % 1000);
+            write!(f, "{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)?;
+            if milliseconds != 0 {
+                write!(f, ".{:03}", milliseconds)?;
+            }
+        }
+        Result::Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+
+mod big;
+mod complex;
+mod date;
+mod rational;
+mod unit;
+
+pub use self::big::BigInteger;
+pub use self::complex::Complex;
+pub use self::date::{DateTime, MILLISECONDS_PER_DAY};
+pub use self::rational::Rational;
+pub use self::unit::{Dimension, Quantity, Unit};
+
+// What an expression evaluates to.  Integer arithmetic stays exact and
+// fails on overflow; anything involving a real, such as a square root or
+// pi, is done in floating point.  Factorials are exact however large they
+// get: one that does not fit an i32 is a Big, and arithmetic on a Big
+// stays exact.  A Big never holds a value that would fit an Integer.
+// A Rational is an exact fraction, as the inverse of a matrix of whole
+// numbers may have, and is never whole; arithmetic on it and on integers
+// stays exact.  Likewise a Complex always has a nonzero imaginary part, so the square
+// of an imaginary number is a Real.  A Quantity carries a unit, and its
+// amount is always real; one whose unit is dimensionless, like km/m, is
+// just a number.  A DateTime is a date, a time of day or both, and is
+// not a number at all.  Nor is a List, whose elements may be any values,
+// lists included.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i32),
+    Real(f64),
+    Big(BigInteger),
+    Rational(Rational),
+    Complex(Complex),
+    Quantity(Quantity),
+    DateTime(DateTime),
+    List(Vec<Value>)
+}
+
+// how complex values are written out
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ComplexForm {
+    // 3+4i
+    Rectangular,
+    // 5∠0.9272952180016122, the modulus and the angle in radians
+    Polar
+}
+
+// how exact fractions are written out
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NumericMode {
+    // 1.5, the nearest real
+    Real,
+    // 3/2
+    Rational
+}
+
+impl Value {
+    // the real part of a complex value, and a quantity in SI base units;
+    // a date or a list has none
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            Value::Integer(i) => f64::from(i),
+            Value::Real(r) => r,
+            Value::Big(ref b) => b.to_f64(),
+            Value::Rational(ref r) => r.to_f64(),
+            Value::Complex(z) => z.re,
+            Value::Quantity(ref q) => q.to_si(),
+            Value::DateTime(_) => panic!("a date is not a number"),
+            Value::List(_) => panic!("a list is not a number")
+        }
+    }
+
+    pub fn to_complex(&self) -> Complex {
+        match *self {
+            Value::Complex(z) => z,
+            _ => Complex::from(self.to_f64())
+        }
+    }
+
+    // the exact value of an integer, big or not
+    pub fn to_big(&self) -> Option<BigInteger> {
+        match *self {
+            Value::Integer(i) => Some(BigInteger::from(i)),
+            Value::Real(_) | Value::Rational(_) => None,
+            Value::Big(ref b) => Some(b.clone()),
+            Value::Complex(_) | Value::Quantity(_) | Value::DateTime(_) | Value::List(_) => None
+        }
+    }
+
+    // the exact value of an integer or a fraction
+    pub fn to_rational(&self) -> Option<Rational> {
+        match *self {
+            Value::Rational(ref r) => Some(r.clone()),
+            _ => self.to_big().map(Rational::from)
+        }
+    }
+
+    // Display writes complex values, in lists too, in rectangular form,
+    // and fractions as reals
+    pub fn display(&self, form: ComplexForm, mode: NumericMode) -> Formatted<'_> {
+        Formatted { value: self, form, mode }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Value {
+        Value::Integer(i)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Integer(b as i32)
+    }
+}
+
+// an Integer whenever the value fits
+impl From<BigInteger> for Value {
+    fn from(b: BigInteger) -> Value {
+        match b.to_i32() {
+            Some(i) => Value::Integer(i),
+            None => Value::Big(b)
+        }
+    }
+}
+
+// an Integer or a Big whenever the fraction is whole
+impl From<Rational> for Value {
+    fn from(r: Rational) -> Value {
+        if r.is_whole() {
+            Value::from(r.numerator().clone())
+        } else {
+            Value::Rational(r)
+        }
+    }
+}
+
+// a Real whenever the imaginary part is zero
+impl From<Complex> for Value {
+    fn from(z: Complex) -> Value {
+        if z.im == 0.0 {
+            Value::Real(z.re)
+        } else {
+            Value::Complex(z)
+        }
+    }
+}
+
+// a plain number whenever the unit is dimensionless
+impl From<Quantity> for Value {
+    fn from(q: Quantity) -> Value {
+        if q.unit.is_dimensionless() {
+            Value::Real(q.to_si())
+        } else {
+            Value::Quantity(q)
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Real(r) => write!(f, "{}", r),
+            Value::Big(b) => write!(f, "{}", b),
+            Value::Rational(r) => write!(f, "{}", r.to_f64()),
+            Value::Complex(z) => write!(f, "{}", z),
+            Value::Quantity(q) => write!(f, "{}", q),
+            Value::DateTime(d) => write!(f, "{}", d),
+            Value::List(values) => write_list(f, values, ComplexForm::Rectangular, NumericMode::Real)
+        }
+    }
+}
+
+// [1, 2, 3]
+fn write_list(f: &mut fmt::Formatter, values: &[Value], form: ComplexForm, mode: NumericMode) -> fmt::Result {
+    f.write_str("[")?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", value.display(form, mode))?;
+    }
+    f.write_str("]")
+}
+
+pub struct Formatted<'a> {
+    value: &'a Value,
+    form: ComplexForm,
+    mode: NumericMode
+}
+
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.value, self.form, self.mode) {
+            (Value::Complex(z), ComplexForm::Polar, _) => write!(f, "{}∠{}", z.abs(), z.arg()),
+            (Value::Rational(r), _, NumericMode::Rational) => write!(f, "{}", r),
+            (Value::List(values), form, mode) => write_list(f, values, form, mode),
+            (value, _, _) => write!(f, "{}", value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
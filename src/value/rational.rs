@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::BigInteger;
+
+// An exact fraction, kept in lowest terms with a positive denominator,
+// so equal fractions have equal representations.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rational {
+    numerator: BigInteger,
+    denominator: BigInteger
+}
+
+// the greatest common divisor, which is never negative
+fn gcd(a: &BigInteger, b: &BigInteger) -> BigInteger {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    while !b.is_zero() {
+        let remainder = &a % &b;
+        a = b;
+        b = remainder;
+    }
+    if a.is_negative() { -&a } else { a }
+}
+
+impl Rational {
+    // panics on a zero denominator, like the primitive integers
+    pub fn new(numerator: &BigInteger, denominator: &BigInteger) -> Rational {
+        assert!(!denominator.is_zero(), "division by zero");
+        let divisor = gcd(numerator, denominator);
+        let divisor = if denominator.is_negative() { -&divisor } else { divisor };
+        Rational {
+            numerator: numerator / &divisor,
+            denominator: denominator / &divisor
+        }
+    }
+
+    pub fn numerator(&self) -> &BigInteger {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &BigInteger {
+        &self.denominator
+    }
+
+    pub fn is_whole(&self) -> bool {
+        self.denominator == BigInteger::from(1)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.numerator.is_negative()
+    }
+
+    // the bits in the larger of the numerator and the denominator
+    pub fn bits(&self) -> usize {
+        self.numerator.bits().max(self.denominator.bits())
+    }
+
+    pub fn powi(&self, exponent: u32) -> Rational {
+        let one = BigInteger::from(1);
+        let (numerator, denominator) = (0..exponent).fold((one.clone(), one), |(n, d), _| {
+            (&n * &self.numerator, &d * &self.denominator)
+        });
+        Rational { numerator, denominator }
+    }
+
+    // The nearest f64.  The quotient is taken to at least 65 bits, with
+    // the lowest one set if anything is left over, so it rounds the way
+    // the fraction would.  It is scaled back in two steps so that neither
+    // overflows or underflows where the result does not.
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = if self.is_negative() { -&self.numerator } else { self.numerator.clone() };
+        let shift = 65 + self.denominator.bits() as i64 - magnitude.bits() as i64;
+        let (n, d) = if shift >= 0 {
+            (magnitude.shifted(shift as usize), self.denominator.clone())
+        } else {
+            (magnitude, self.denominator.shifted(-shift as usize))
+        };
+        let (quotient, remainder) = n.divide(&d).expect("the denominator is never zero");
+        let (quotient, shift) = if remainder.is_zero() {
+            (quotient, shift)
+        } else {
+            (&quotient.shifted(1) + &BigInteger::from(1), shift + 1)
+        };
+        let half = (shift / 2) as i32;
+        let value = quotient.to_f64() * 2f64.powi(-half) * 2f64.powi(half - shift as i32);
+        if self.is_negative() { -value } else { value }
+    }
+}
+
+impl From<BigInteger> for Rational {
+    fn from(b: BigInteger) -> Rational {
+        Rational {
+            numerator: b,
+            denominator: BigInteger::from(1)
+        }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        (&self.numerator * &other.denominator).cmp(&(&other.numerator * &self.denominator))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational {
+            numerator: -&self.numerator,
+            denominator: self.denominator.clone()
+        }
+    }
+}
+
+impl Add for &Rational {
+    type Output = Rational;
+
+    fn add(self, other: &Rational) -> Rational {
+        Rational::new(&(&(&self.numerator * &other.denominator) + &(&other.numerator * &self.denominator)),
+                      &(&self.denominator * &other.denominator))
+    }
+}
+
+impl Sub for &Rational {
+    type Output = Rational;
+
+    fn sub(self, other: &Rational) -> Rational {
+        self + &-other
+    }
+}
+
+impl Mul for &Rational {
+    type Output = Rational;
+
+    fn mul(self, other: &Rational) -> Rational {
+        Rational::new(&(&self.numerator * &other.numerator), &(&self.denominator * &other.denominator))
+    }
+}
+
+// panics on division by zero, like the primitive integers
+impl Div for &Rational {
+    type Output = Rational;
+
+    fn div(self, other: &Rational) -> Rational {
+        Rational::new(&(&self.numerator * &other.denominator), &(&self.denominator * &other.numerator))
+    }
+}
+
+// 3/2, or just the numerator when whole
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_whole() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
@@ -0,0 +1,324 @@
+extern crate rand;
+
+use super::{BigInteger, Complex, ComplexForm, DateTime, NumericMode, Quantity, Rational, Unit, Value,
+            MILLISECONDS_PER_DAY};
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
+
+fn big(i: i128) -> BigInteger {
+    BigInteger::from(i)
+}
+
+fn factorial(n: u32) -> BigInteger {
+    (2..=n).fold(big(1), |product, k| product.multiply_by(k))
+}
+
+// values at limb boundaries, plus random ones of every width
+fn interesting_values() -> Vec<i128> {
+    let mut values = vec![0, 1, 2, 9, 10, 1 << 31, (1 << 31) - 1, 1 << 32, (1 << 32) - 1, (1 << 32) + 1,
+                          1 << 63, u64::MAX as i128, 1 << 64, (1 << 96) - 1, 1 << 96, i128::MAX,
+                          // quotient limbs that are estimated one too large
+                          0x7fff_ffff_8000_0000_0000_0000_0000_0000,
+                          0x8000_0000_0000_0000_0000_0001,
+                          0x8000_0000_0000_0000_0000_0000_0000];
+    let mut random = StdRng::seed_from_u64(0);
+    for _ in 0..40 {
+        let shift = random.gen_range(0, 127);
+        values.push(random.gen::<i128>() >> shift);
+    }
+    let negated: Vec<i128> = values.iter().map(|v| -v).collect();
+    values.extend(negated);
+    values
+}
+
+#[test]
+fn arithmetic_agrees_with_i128() {
+    let values = interesting_values();
+    for a in &values {
+        assert_eq!(a.to_string(), big(*a).to_string());
+        assert_eq!(*a as f64, big(*a).to_f64(), "{} to f64", a);
+        for b in &values {
+            let (x, y) = (big(*a), big(*b));
+            assert_eq!(a.cmp(b), x.cmp(&y), "{} cmp {}", a, b);
+            if let Some(sum) = a.checked_add(*b) {
+                assert_eq!(big(sum), &x + &y, "{} + {}", a, b);
+            }
+            if let Some(difference) = a.checked_sub(*b) {
+                assert_eq!(big(difference), &x - &y, "{} - {}", a, b);
+            }
+            if let Some(product) = a.checked_mul(*b) {
+                assert_eq!(big(product), &x * &y, "{} * {}", a, b);
+            }
+            if *b != 0 {
+                assert_eq!(Some((big(a / b), big(a % b))), x.divide(&y), "{} / {}", a, b);
+            } else {
+                assert_eq!(None, x.divide(&y));
+            }
+        }
+    }
+}
+
+#[test]
+fn factorials_are_exact() {
+    assert_eq!("15511210043330985984000000", factorial(25).to_string());
+    assert_eq!("265252859812191058636308480000000", factorial(30).to_string());
+    assert_eq!(158, factorial(100).to_string().len());
+}
+
+fn magnitude(i: &BigInteger) -> BigInteger {
+    if i.is_negative() { -i } else { i.clone() }
+}
+
+#[test]
+fn division_undoes_multiplication_past_i128() {
+    let values = [factorial(40), -&factorial(57), factorial(100), big(3), big(-(1 << 100))];
+    for a in &values {
+        for b in &values {
+            let product = a * b;
+            assert_eq!(Some((a.clone(), big(0))), product.divide(b));
+            let dividend = &product + &big(1);
+            let (quotient, remainder) = dividend.divide(b).unwrap();
+            assert_eq!(dividend, &(&quotient * b) + &remainder);
+            assert!(magnitude(&remainder) < magnitude(b));
+            assert!(remainder.is_zero() || remainder.is_negative() == dividend.is_negative());
+        }
+    }
+}
+
+#[test]
+fn huge_values_convert_to_infinity() {
+    assert_eq!(f64::INFINITY, factorial(200).to_f64());
+    assert_eq!(f64::NEG_INFINITY, (-&factorial(200)).to_f64());
+    assert_eq!(9.332621544394415e157, factorial(100).to_f64());
+}
+
+fn fraction(numerator: i128, denominator: i128) -> Rational {
+    Rational::new(&big(numerator), &big(denominator))
+}
+
+#[test]
+fn fractions_are_kept_in_lowest_terms() {
+    assert_eq!(fraction(-3, 2), fraction(6, -4));
+    assert_eq!("-3/2", fraction(6, -4).to_string());
+    assert_eq!(fraction(0, 1), fraction(0, -5));
+    assert_eq!(Value::Integer(-2), Value::from(fraction(8, -4)));
+    assert_eq!(Value::Big(big(1 << 40)), Value::from(fraction(1 << 41, 2)));
+    assert_eq!(Value::Rational(fraction(1, 3)), Value::from(fraction(2, 6)));
+}
+
+#[test]
+fn fraction_arithmetic_is_exact() {
+    assert_eq!(fraction(5, 6), &fraction(1, 2) + &fraction(1, 3));
+    assert_eq!(fraction(1, 6), &fraction(1, 2) - &fraction(1, 3));
+    assert_eq!(fraction(1, 6), &fraction(1, 2) * &fraction(1, 3));
+    assert_eq!(fraction(3, 2), &fraction(1, 2) / &fraction(1, 3));
+    assert_eq!(fraction(-8, 27), fraction(-2, 3).powi(3));
+    assert!(fraction(1, 3) < fraction(1, 2));
+    assert!(fraction(-1, 2) < fraction(-1, 3));
+}
+
+#[test]
+fn fractions_convert_to_the_nearest_real() {
+    let mut rng = StdRng::seed_from_u64(43);
+    for _ in 0..1000 {
+        // both exact in an f64, so their quotient rounds once
+        let (n, d) = (rng.gen_range(-1i64 << 53, 1 << 53), rng.gen_range(-1i64 << 53, 1 << 53));
+        if d != 0 {
+            assert_eq!(n as f64 / d as f64, fraction(i128::from(n), i128::from(d)).to_f64(), "{}/{}", n, d);
+        }
+    }
+    let huge = factorial(1000);
+    assert_eq!(1.0, Rational::new(&(&huge + &big(1)), &huge).to_f64());
+    assert_eq!(2f64.powi(-1000), Rational::new(&big(1), &big(1).shifted(1000)).to_f64());
+    assert_eq!(5e-324, Rational::new(&big(1), &big(1).shifted(1074)).to_f64());
+    assert_eq!(f64::INFINITY, Rational::new(&factorial(1000), &big(3)).to_f64());
+    assert_eq!(0.0, Rational::new(&big(1), &factorial(1000)).to_f64());
+}
+
+#[test]
+fn fractions_print_as_reals_unless_rational() {
+    let value = Value::List(vec![Value::from(fraction(3, 2)), Value::Integer(2)]);
+    assert_eq!("[1.5, 2]", value.to_string());
+    assert_eq!("[3/2, 2]", value.display(ComplexForm::Rectangular, NumericMode::Rational).to_string());
+}
+
+#[test]
+fn small_bigs_become_integers() {
+    assert_eq!(Value::Integer(-7), Value::from(big(-7)));
+    assert_eq!(Value::Integer(i32::MIN), Value::from(big(i128::from(i32::MIN))));
+    assert_eq!(Value::Big(big(1 << 31)), Value::from(big(1 << 31)));
+}
+
+#[test]
+fn complex_square_roots_are_principal() {
+    assert_eq!(Complex::new(0.0, 3.0), Complex::from(-9.0).sqrt());
+    assert_eq!(Complex::new(2.0, 1.0), Complex::new(3.0, 4.0).sqrt());
+    assert_eq!(Complex::new(1.0, -2.0), Complex::new(-3.0, -4.0).sqrt());
+    for z in &[Complex::new(1e-300, 1e300), Complex::new(-5.0, 1e-10), Complex::new(0.25, -7.5)] {
+        let root = z.sqrt();
+        assert!(root.re >= 0.0);
+        let square = root * root;
+        assert!((square - *z).abs() <= 1e-15 * z.abs(), "{} squared is {}", root, square);
+    }
+}
+
+#[test]
+fn complex_division_avoids_overflow() {
+    let huge = Complex::new(1e300, 1e300);
+    assert_eq!(Complex::new(1.0, 0.0), huge / huge);
+    assert_eq!(Complex::new(0.5, -0.5), Complex::new(1.0, 0.0) / Complex::new(1.0, 1.0));
+    assert!((Complex::new(1.0, 0.0) / Complex::new(0.0, 0.0)).re.is_nan());
+}
+
+#[test]
+fn integer_powers_are_multiplied_out() {
+    let i = Complex::new(0.0, 1.0);
+    assert_eq!(Complex::new(-1.0, 0.0), i.powi(2));
+    assert_eq!(Complex::new(0.0, -1.0), i.powi(-1));
+    assert_eq!(Complex::new(-4.0, 0.0), Complex::new(1.0, 1.0).powi(4));
+    assert_eq!(Complex::new(0.0, 0.0), Complex::new(0.0, 0.0).powc(i + Complex::from(1.0)));
+    assert!(Complex::new(0.0, 0.0).powc(i).re.is_nan());
+}
+
+#[test]
+fn complex_values_print_rectangular() {
+    assert_eq!("3+4i", Complex::new(3.0, 4.0).to_string());
+    assert_eq!("0.5-i", Complex::new(0.5, -1.0).to_string());
+    assert_eq!("i", Complex::new(0.0, 1.0).to_string());
+    assert_eq!("-2.5i", Complex::new(0.0, -2.5).to_string());
+}
+
+#[test]
+fn rounding_left_in_a_complex_part_prints_as_zero() {
+    let half_turn = Complex::new(0.0, ::std::f64::consts::PI).exp();
+    assert_eq!("-1", half_turn.to_string());
+    let quarter_turn = Complex::new(0.0, ::std::f64::consts::FRAC_PI_2).exp();
+    assert_eq!("i", quarter_turn.to_string());
+    assert_eq!("0.001+0.001i", Complex::new(1e-3, 1e-3).to_string());
+    assert_eq!("1+0.000000000000001i", Complex::new(1.0, 1e-15).to_string());
+}
+
+#[test]
+fn real_complex_values_become_reals() {
+    assert_eq!(Value::Real(2.0), Value::from(Complex::new(2.0, 0.0)));
+    assert_eq!(Value::Complex(Complex::new(0.0, 1.0)), Value::from(Complex::new(0.0, 1.0)));
+}
+
+fn unit(name: &str) -> Unit {
+    Unit::named(name).unwrap()
+}
+
+#[test]
+fn units_take_si_prefixes() {
+    assert_eq!(1000.0, unit("km").scale());
+    assert_eq!(1e-6, unit("µs").scale());
+    assert_eq!(unit("µs").scale(), unit("us").scale());
+    assert_eq!(10.0, unit("dam").scale());
+    assert_eq!(unit("g").dimension(), unit("kg").dimension());
+    assert_eq!(60.0, unit("min").scale());
+    assert_eq!(1.0, unit("Pa").scale());
+    assert_eq!(None, Unit::named("kmi"));
+    assert_eq!(None, Unit::named("x"));
+}
+
+#[test]
+fn units_multiply_by_name() {
+    let speed = unit("km").divide(&unit("h")).unwrap();
+    assert_eq!("km/h", speed.to_string());
+    assert_eq!(1000.0 / 3600.0, speed.scale());
+    let force = unit("kg").multiply(&unit("m")).unwrap().divide(&unit("s").powi(2).unwrap()).unwrap();
+    assert_eq!("kg·m/s²", force.to_string());
+    let area = unit("m").multiply(&unit("m")).unwrap();
+    assert_eq!("m²", area.to_string());
+    assert_eq!(Some(unit("m")), area.sqrt());
+    assert_eq!(None, unit("m").sqrt());
+    assert_eq!("s^-1", unit("s").powi(-1).unwrap().to_string());
+    assert!(unit("km").divide(&unit("m")).unwrap().is_dimensionless());
+    assert_eq!(None, unit("m").powi(i32::MAX).unwrap().multiply(&unit("m")));
+}
+
+#[test]
+fn quantities_convert_within_a_dimension() {
+    let speed = Quantity::new(60.0, unit("mph"));
+    let metres_per_second = unit("m").divide(&unit("s")).unwrap();
+    assert_eq!(Some(Quantity::new(26.8224, metres_per_second.clone())), speed.convert(&metres_per_second));
+    assert_eq!(None, speed.convert(&unit("s")));
+    assert_eq!("3.5 km", Quantity::new(3.5, unit("km")).to_string());
+}
+
+#[test]
+fn dimensionless_quantities_become_reals() {
+    assert_eq!(Value::Real(1000.0), Value::from(Quantity::new(1.0, unit("km").divide(&unit("m")).unwrap())));
+    assert_eq!(Value::Quantity(Quantity::new(1.0, unit("m"))), Value::from(Quantity::new(1.0, unit("m"))));
+}
+
+#[test]
+fn dates_parse_only_when_they_exist() {
+    assert_eq!(DateTime::date(2024, 2, 29), DateTime::parse_date("2024-02-29"));
+    assert_eq!(None, DateTime::parse_date("2026-02-29"));
+    assert_eq!(None, DateTime::parse_date("2026-13-01"));
+    assert_eq!(None, DateTime::parse_date("26-10-18"));
+    assert_eq!(DateTime::time_of_day(9, 5, 0), DateTime::parse_time("9:05"));
+    assert_eq!(DateTime::time_of_day(23, 59, 59_250), DateTime::parse_time("23:59:59.25"));
+    assert_eq!(None, DateTime::parse_time("24:00"));
+    assert_eq!(None, DateTime::parse_time("12:60"));
+}
+
+#[test]
+fn dates_and_times_print_in_iso_8601() {
+    let date = DateTime::parse_date("2026-10-18").unwrap();
+    let time = DateTime::parse_time("14:30").unwrap();
+    assert_eq!("2026-10-18", date.to_string());
+    assert_eq!("14:30:00", time.to_string());
+    assert_eq!("2026-10-18T14:30:00", date.at(&time).unwrap().to_string());
+    assert_eq!("00:00:00.001", DateTime::time_of_day(0, 0, 1).unwrap().to_string());
+    assert_eq!(None, time.at(&date));
+}
+
+#[test]
+fn durations_print_in_iso_8601() {
+    let duration = |amount: f64, unit: &str| Quantity::new(amount, Unit::named(unit).unwrap()).to_string();
+    assert_eq!("P3DT4H", duration(3.0 + 4.0 / 24.0, "d"));
+    assert_eq!("P67D", duration(67.0, "d"));
+    assert_eq!("P0D", duration(0.0, "d"));
+    assert_eq!("PT1H30M", duration(1.5, "h"));
+    assert_eq!("PT90M", duration(90.0, "min"));
+    assert_eq!("PT1M0.25S", duration(1.0 + 1.0 / 240.0, "min"));
+    assert_eq!("P2Y", duration(2.0, "year"));
+    assert_eq!("P1Y6M", duration(1.5, "yr"));
+    assert_eq!("P1M", duration(1.0, "month"));
+    assert_eq!("P14D", duration(2.0, "wk"));
+    assert_eq!("-PT3H", duration(-3.0, "h"));
+    assert_eq!("PT18900S", duration(18900.0, "s"));
+    assert_eq!("PT0.005S", duration(5.0, "ms"));
+    assert_eq!("3 m", duration(3.0, "m"));
+}
+
+#[test]
+fn months_clamp_to_the_last_day() {
+    let date = |s| DateTime::parse_date(s).unwrap();
+    assert_eq!(Some(date("2026-02-28")), date("2026-01-31").add_months(1));
+    assert_eq!(Some(date("2024-02-29")), date("2024-01-31").add_months(1));
+    assert_eq!(Some(date("2025-11-30")), date("2026-03-31").add_months(-4));
+    assert_eq!(None, date("9999-12-01").add_months(1));
+    assert_eq!(None, DateTime::parse_time("12:00").unwrap().add_months(1));
+}
+
+#[test]
+fn times_of_day_wrap_and_dates_keep_their_kind() {
+    let date = DateTime::parse_date("2026-10-18").unwrap();
+    let time = DateTime::parse_time("23:30").unwrap();
+    assert_eq!("01:30:00", time.add_milliseconds(2 * 3_600_000).unwrap().to_string());
+    assert_eq!("2026-10-20", date.add_milliseconds(2 * MILLISECONDS_PER_DAY).unwrap().to_string());
+    assert_eq!("2026-10-17T12:00:00", date.add_milliseconds(-MILLISECONDS_PER_DAY / 2).unwrap().to_string());
+    assert_eq!(None, DateTime::parse_date("0000-01-01").unwrap().add_milliseconds(-1));
+    assert_eq!(Some(68 * MILLISECONDS_PER_DAY), DateTime::parse_date("2026-12-25").unwrap().since(&date));
+    assert_eq!(None, date.since(&time));
+}
+
+#[test]
+fn lists_print_their_elements_in_either_form() {
+    let list = Value::List(vec![Value::Integer(1), Value::Complex(Complex::new(0.0, 2.0)), Value::List(vec![])]);
+    assert_eq!("[1, 2i, []]", list.to_string());
+    assert_eq!("[1, 2∠1.5707963267948966, []]", list.display(ComplexForm::Polar, NumericMode::Real).to_string());
+}
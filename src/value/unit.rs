@@ -0,0 +1,382 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+// The powers of the seven SI base units that make up a unit: metre,
+// kilogram, second, ampere, kelvin, mole and candela, in that order,
+// followed by money, whose base unit is whatever currency the exchange
+// rates are relative to.
+pub type Dimension = [i32; 8];
+
+const DIMENSIONLESS: Dimension = [0, 0, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0, 0];
+const LUMINOUS_INTENSITY: Dimension = [0, 0, 0, 0, 0, 0, 1, 0];
+const AREA: Dimension = [2, 0, 0, 0, 0, 0, 0, 0];
+const VOLUME: Dimension = [3, 0, 0, 0, 0, 0, 0, 0];
+const SPEED: Dimension = [1, 0, -1, 0, 0, 0, 0, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0, 0, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0, 0, 0, 0];
+const CHARGE: Dimension = [0, 0, 1, 1, 0, 0, 0, 0];
+const VOLTAGE: Dimension = [2, 1, -3, -1, 0, 0, 0, 0];
+const CAPACITANCE: Dimension = [-2, -1, 4, 2, 0, 0, 0, 0];
+const RESISTANCE: Dimension = [2, 1, -3, -2, 0, 0, 0, 0];
+const CONDUCTANCE: Dimension = [-2, -1, 3, 2, 0, 0, 0, 0];
+const MAGNETIC_FLUX: Dimension = [2, 1, -2, -1, 0, 0, 0, 0];
+const MAGNETIC_FLUX_DENSITY: Dimension = [0, 1, -2, -1, 0, 0, 0, 0];
+const INDUCTANCE: Dimension = [2, 1, -2, -2, 0, 0, 0, 0];
+const MONEY: Dimension = [0, 0, 0, 0, 0, 0, 0, 1];
+
+// Each unit's name, its size in SI base units, its dimension, and whether
+// it takes an SI prefix, as km or µs do.  A name is looked up whole
+// before any prefix is tried, so min is a minute and Pa a pascal.
+const UNITS: [(&str, f64, Dimension, bool); 49] = [
+    ("m", 1.0, LENGTH, true),
+    ("g", 1e-3, MASS, true),
+    ("s", 1.0, TIME, true),
+    ("A", 1.0, CURRENT, true),
+    ("K", 1.0, TEMPERATURE, true),
+    ("mol", 1.0, AMOUNT, true),
+    ("cd", 1.0, LUMINOUS_INTENSITY, true),
+    ("Hz", 1.0, FREQUENCY, true),
+    ("N", 1.0, FORCE, true),
+    ("Pa", 1.0, PRESSURE, true),
+    ("J", 1.0, ENERGY, true),
+    ("W", 1.0, POWER, true),
+    ("C", 1.0, CHARGE, true),
+    ("V", 1.0, VOLTAGE, true),
+    ("F", 1.0, CAPACITANCE, true),
+    ("Ω", 1.0, RESISTANCE, true),
+    ("ohm", 1.0, RESISTANCE, true),
+    ("S", 1.0, CONDUCTANCE, true),
+    ("Wb", 1.0, MAGNETIC_FLUX, true),
+    ("T", 1.0, MAGNETIC_FLUX_DENSITY, true),
+    ("H", 1.0, INDUCTANCE, true),
+    ("L", 1e-3, VOLUME, true),
+    ("l", 1e-3, VOLUME, true),
+    ("t", 1e3, MASS, true),
+    ("eV", 1.602176634e-19, ENERGY, true),
+    ("Wh", 3600.0, ENERGY, true),
+    ("cal", 4.184, ENERGY, true),
+    ("bar", 1e5, PRESSURE, true),
+    ("rad", 1.0, DIMENSIONLESS, false),
+    ("deg", PI / 180.0, DIMENSIONLESS, false),
+    ("°", PI / 180.0, DIMENSIONLESS, false),
+    ("min", 60.0, TIME, false),
+    ("h", 3600.0, TIME, false),
+    ("d", 86400.0, TIME, false),
+    ("wk", 604800.0, TIME, false),
+    // a twelfth of a year, though added to a date it is a calendar month
+    ("month", 2629800.0, TIME, false),
+    // the Julian year, of 365.25 days, though added to a date it is a
+    // calendar year
+    ("yr", 31557600.0, TIME, false),
+    ("year", 31557600.0, TIME, false),
+    ("ha", 1e4, AREA, false),
+    ("inch", 0.0254, LENGTH, false),
+    ("ft", 0.3048, LENGTH, false),
+    ("yd", 0.9144, LENGTH, false),
+    ("mi", 1609.344, LENGTH, false),
+    ("nmi", 1852.0, LENGTH, false),
+    ("mph", 0.44704, SPEED, false),
+    ("kn", 1852.0 / 3600.0, SPEED, false),
+    ("lb", 0.45359237, MASS, false),
+    ("oz", 0.028349523125, MASS, false),
+    ("atm", 101325.0, PRESSURE, false)
+];
+
+// µ is written with either the micro sign or the Greek letter, or as u
+const PREFIXES: [(&str, f64); 26] = [
+    ("Q", 1e30),
+    ("R", 1e27),
+    ("Y", 1e24),
+    ("Z", 1e21),
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("da", 1e1),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("\u{b5}", 1e-6),
+    ("\u{3bc}", 1e-6),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+    ("a", 1e-18),
+    ("z", 1e-21),
+    ("y", 1e-24),
+    ("r", 1e-27),
+    ("q", 1e-30)
+];
+
+fn unprefixed(name: &str) -> Option<(f64, Dimension, bool)> {
+    UNITS.iter()
+        .find(|(unit, _, _, _)| *unit == name)
+        .map(|(_, scale, dimension, prefixable)| (*scale, *dimension, *prefixable))
+}
+
+fn prefixed(name: &str) -> Option<(f64, Dimension)> {
+    PREFIXES.iter().filter_map(|(prefix, factor)| {
+        match unprefixed(name.strip_prefix(prefix)?) {
+            Some((scale, dimension, true)) => Some((factor * scale, dimension)),
+            _ => None
+        }
+    }).next()
+}
+
+// A product of named units, each raised to a power, such as km/h.  The
+// names are kept as written so results read in the units the user chose;
+// the scale and dimension say what the product means.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    // in the order first written, with no zero powers
+    factors: Vec<(String, i32)>,
+    scale: f64,
+    dimension: Dimension
+}
+
+impl Unit {
+    // a unit in the table, with or without an SI prefix
+    pub fn named(name: &str) -> Option<Unit> {
+        let (scale, dimension) = match unprefixed(name) {
+            Some((scale, dimension, _)) => (scale, dimension),
+            None => prefixed(name)?
+        };
+        Some(Unit { factors: vec![(name.to_string(), 1)], scale, dimension })
+    }
+
+    // A currency, worth scale of the base currency.  Currencies are not in
+    // the table, as their worth depends on the exchange rates in use.
+    pub fn currency(code: &str, scale: f64) -> Unit {
+        Unit { factors: vec![(code.to_string(), 1)], scale, dimension: MONEY }
+    }
+
+    // the size of one of this unit in SI base units
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    // true of km/m as well as of m/m, so any such unit is a plain number
+    pub fn is_dimensionless(&self) -> bool {
+        self.dimension == DIMENSIONLESS
+    }
+
+    // true of USD/kg as well as of USD, but not of USD/EUR
+    pub fn is_currency(&self) -> bool {
+        self.dimension[7] != 0
+    }
+
+    // None if a power overflows
+    pub fn powi(&self, exponent: i32) -> Option<Unit> {
+        let mut dimension = self.dimension;
+        for power in dimension.iter_mut() {
+            *power = power.checked_mul(exponent)?;
+        }
+        let mut factors = vec![];
+        for (name, power) in &self.factors {
+            if exponent != 0 {
+                factors.push((name.clone(), power.checked_mul(exponent)?));
+            }
+        }
+        Some(Unit { factors, scale: self.scale.powi(exponent), dimension })
+    }
+
+    // the unit whose square this is, if every power is even
+    pub fn sqrt(&self) -> Option<Unit> {
+        if self.factors.iter().any(|(_, power)| power % 2 != 0) {
+            return None;
+        }
+        let mut dimension = self.dimension;
+        for power in dimension.iter_mut() {
+            *power /= 2;
+        }
+        Some(Unit {
+            factors: self.factors.iter().map(|(name, power)| (name.clone(), power / 2)).collect(),
+            scale: self.scale.sqrt(),
+            dimension
+        })
+    }
+
+    // Powers of the same name add up, and cancel when they reach zero.
+    // None if a power overflows.
+    pub fn multiply(&self, other: &Unit) -> Option<Unit> {
+        let mut factors = self.factors.clone();
+        for (name, power) in &other.factors {
+            match factors.iter().position(|(existing, _)| existing == name) {
+                Some(i) => factors[i].1 = factors[i].1.checked_add(*power)?,
+                None => factors.push((name.clone(), *power))
+            }
+        }
+        factors.retain(|(_, power)| *power != 0);
+        let mut dimension = self.dimension;
+        for (power, other_power) in dimension.iter_mut().zip(other.dimension.iter()) {
+            *power = power.checked_add(*other_power)?;
+        }
+        Some(Unit { factors, scale: self.scale * other.scale, dimension })
+    }
+
+    pub fn divide(&self, other: &Unit) -> Option<Unit> {
+        self.multiply(&other.powi(-1)?)
+    }
+}
+
+fn superscript(power: i32) -> String {
+    if power == 1 {
+        return String::new();
+    }
+    power.to_string().chars()
+        .map(|digit| "⁰¹²³⁴⁵⁶⁷⁸⁹".chars().nth(digit.to_digit(10).unwrap() as usize).unwrap())
+        .collect()
+}
+
+// As source that evaluates to the same unit, such as kg·m/s².  A unit
+// with nothing above the line uses negative powers instead, as in s^-1.
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let numerator: Vec<&(String, i32)> = self.factors.iter().filter(|(_, power)| *power > 0).collect();
+        if numerator.is_empty() {
+            for (i, (name, power)) in self.factors.iter().enumerate() {
+                if i > 0 {
+                    f.write_str("·")?;
+                }
+                write!(f, "{}^{}", name, power)?;
+            }
+            return Result::Ok(());
+        }
+        for (i, (name, power)) in numerator.iter().enumerate() {
+            if i > 0 {
+                f.write_str("·")?;
+            }
+            write!(f, "{}{}", name, superscript(*power))?;
+        }
+        for (name, power) in self.factors.iter().filter(|(_, power)| *power < 0) {
+            write!(f, "/{}{}", name, superscript(-power))?;
+        }
+        Result::Ok(())
+    }
+}
+
+// an amount of some unit, such as 3 km
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub amount: f64,
+    pub unit: Unit
+}
+
+impl Quantity {
+    pub fn new(amount: f64, unit: Unit) -> Quantity {
+        Quantity { amount, unit }
+    }
+
+    // the amount in SI base units
+    pub fn to_si(&self) -> f64 {
+        self.amount * self.unit.scale
+    }
+
+    // The same quantity measured in another unit, if it has the same
+    // dimension.  Nothing is rescaled when the unit is the same, even if
+    // its scale has overflowed or underflowed.
+    pub fn convert(&self, unit: &Unit) -> Option<Quantity> {
+        if self.unit.dimension != unit.dimension {
+            return None;
+        }
+        if self.unit.factors == unit.factors {
+            return Some(Quantity::new(self.amount, unit.clone()));
+        }
+        Some(Quantity::new(self.amount * self.unit.scale / unit.scale, unit.clone()))
+    }
+}
+
+// The parts of an ISO 8601 duration, largest first, and how many
+// milliseconds each is, a year being a Julian year and a month a twelfth
+// of one, as the units are.  The first three are of the date, the rest
+// of the time.
+const DURATION_PARTS: [(char, i64); 6] = [
+    ('Y', 31_557_600_000),
+    ('M', 2_629_800_000),
+    ('D', 86_400_000),
+    ('H', 3_600_000),
+    ('M', 60_000),
+    ('S', 1000)
+];
+
+// the largest of the duration parts a unit of time is written in
+fn largest_part(unit: &Unit) -> usize {
+    match unit.factors.as_slice() {
+        [(name, 1)] => match name.as_str() {
+            "yr" | "year" => 0,
+            "month" => 1,
+            "d" | "wk" => 2,
+            "h" => 3,
+            "min" => 4,
+            _ => 5
+        },
+        _ => 5
+    }
+}
+
+impl Quantity {
+    // As ISO 8601, such as P3DT4H, in parts from the largest the unit
+    // allows down to the millisecond, so 1.5 h is PT1H30M and 90 min is
+    // PT90M.  Seconds, and a duration too long or too short to count in
+    // milliseconds, are written in seconds alone, to full precision.
+    fn fmt_duration(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.to_si();
+        let milliseconds = (seconds * 1000.0).round();
+        let sign = if seconds < 0.0 { "-" } else { "" };
+        let first = largest_part(&self.unit);
+        if first == 5 || milliseconds.abs() >= 1e15 || (milliseconds == 0.0 && seconds != 0.0) {
+            return write!(f, "{}PT{}S", sign, seconds.abs());
+        }
+        let mut rest = milliseconds.abs() as i64;
+        let mut date = String::new();
+        let mut time = String::new();
+        for (i, (designator, size)) in DURATION_PARTS.iter().enumerate().skip(first) {
+            let count = rest / size;
+            rest %= size;
+            let part = if i < 3 { &mut date } else { &mut time };
+            if *designator == 'S' && rest > 0 {
+                let fraction = format!("{:03}", rest);
+                part.push_str(&format!("{}.{}S", count, fraction.trim_end_matches('0')));
+            } else if count > 0 {
+                part.push_str(&format!("{}{}", count, designator));
+            }
+        }
+        if date.is_empty() && time.is_empty() {
+            let zero = format!("0{}", DURATION_PARTS[first].0);
+            if first < 3 { date = zero } else { time = zero }
+        }
+        write!(f, "{}P{}", sign, date)?;
+        if !time.is_empty() {
+            write!(f, "T{}", time)?;
+        }
+        Result::Ok(())
+    }
+}
+
+// A duration, or any other quantity of time, is written in ISO 8601.
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.unit.dimension == TIME {
+            self.fmt_duration(f)
+        } else {
+            write!(f, "{} {}", self.amount, self.unit)
+        }
+    }
+}
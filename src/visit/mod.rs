@@ -0,0 +1,158 @@
+use super::parser::{Expression, Operation};
+
+// Read-only traversal of an expression.  Every method defaults to walking
+// into the children, so an implementation only overrides the cases it
+// cares about, calling the matching walk_* function to keep recursing.
+pub trait Visitor {
+    fn visit_expression(&mut self, e: &Expression) {
+        walk_expression(self, e)
+    }
+
+    fn visit_integer(&mut self, _i: u32) {}
+
+    fn visit_decimal(&mut self, _digits: &str) {}
+
+    fn visit_imaginary(&mut self, _i: u32) {}
+
+    fn visit_date(&mut self, _text: &str) {}
+
+    fn visit_time(&mut self, _text: &str) {}
+
+    fn visit_variable(&mut self, _name: &str) {}
+
+    fn visit_unary_minus(&mut self, e: &Expression) {
+        walk_unary_minus(self, e)
+    }
+
+    fn visit_square_root(&mut self, e: &Expression) {
+        walk_square_root(self, e)
+    }
+
+    fn visit_factorial(&mut self, e: &Expression) {
+        walk_factorial(self, e)
+    }
+
+    fn visit_percent(&mut self, e: &Expression) {
+        walk_percent(self, e)
+    }
+
+    fn visit_percent_change(&mut self, e1: &Expression, op: &Operation, e2: &Expression) {
+        walk_percent_change(self, e1, op, e2)
+    }
+
+    fn visit_binary_operation(&mut self, e1: &Expression, op: &Operation, e2: &Expression) {
+        walk_binary_operation(self, e1, op, e2)
+    }
+
+    fn visit_call(&mut self, name: &str, arguments: &[Expression]) {
+        walk_call(self, name, arguments)
+    }
+
+    fn visit_convert(&mut self, e: &Expression, unit: &Expression) {
+        walk_convert(self, e, unit)
+    }
+
+    fn visit_list(&mut self, elements: &[Expression]) {
+        walk_list(self, elements)
+    }
+
+    fn visit_index(&mut self, e: &Expression, index: &Expression) {
+        walk_index(self, e, index)
+    }
+
+    fn visit_range(&mut self, start: &Expression, end: &Expression, step: Option<&Expression>) {
+        walk_range(self, start, end, step)
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, e: &Expression) {
+    match e {
+        Expression::Integer(i) => visitor.visit_integer(*i),
+        Expression::Decimal(digits) => visitor.visit_decimal(digits),
+        Expression::Imaginary(i) => visitor.visit_imaginary(*i),
+        Expression::Date(text) => visitor.visit_date(text),
+        Expression::Time(text) => visitor.visit_time(text),
+        Expression::Variable(name) => visitor.visit_variable(name),
+        Expression::UnaryMinus(e) => visitor.visit_unary_minus(e),
+        Expression::SquareRoot(e) => visitor.visit_square_root(e),
+        Expression::Factorial(e) => visitor.visit_factorial(e),
+        Expression::Percent(e) => visitor.visit_percent(e),
+        Expression::PercentChange(e1, op, e2) =>
+            visitor.visit_percent_change(e1, op, e2),
+        Expression::BinaryOperation(e1, op, e2) =>
+            visitor.visit_binary_operation(e1, op, e2),
+        Expression::Call(name, arguments) => visitor.visit_call(name, arguments),
+        Expression::Convert(e, unit) => visitor.visit_convert(e, unit),
+        Expression::List(elements) => visitor.visit_list(elements),
+        Expression::Index(e, index) => visitor.visit_index(e, index),
+        Expression::Range(start, end, step) => visitor.visit_range(start, end, step.as_deref())
+    }
+}
+
+pub fn walk_unary_minus<V: Visitor + ?Sized>(visitor: &mut V, e: &Expression) {
+    visitor.visit_expression(e)
+}
+
+pub fn walk_square_root<V: Visitor + ?Sized>(visitor: &mut V, e: &Expression) {
+    visitor.visit_expression(e)
+}
+
+pub fn walk_factorial<V: Visitor + ?Sized>(visitor: &mut V, e: &Expression) {
+    visitor.visit_expression(e)
+}
+
+pub fn walk_percent<V: Visitor + ?Sized>(visitor: &mut V, e: &Expression) {
+    visitor.visit_expression(e)
+}
+
+pub fn walk_percent_change<V: Visitor + ?Sized>(visitor: &mut V,
+                                                e1: &Expression,
+                                                _op: &Operation,
+                                                e2: &Expression) {
+    visitor.visit_expression(e1);
+    visitor.visit_expression(e2);
+}
+
+pub fn walk_binary_operation<V: Visitor + ?Sized>(visitor: &mut V,
+                                                  e1: &Expression,
+                                                  _op: &Operation,
+                                                  e2: &Expression) {
+    visitor.visit_expression(e1);
+    visitor.visit_expression(e2);
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(visitor: &mut V, _name: &str, arguments: &[Expression]) {
+    for argument in arguments {
+        visitor.visit_expression(argument);
+    }
+}
+
+pub fn walk_convert<V: Visitor + ?Sized>(visitor: &mut V, e: &Expression, unit: &Expression) {
+    visitor.visit_expression(e);
+    visitor.visit_expression(unit);
+}
+
+pub fn walk_list<V: Visitor + ?Sized>(visitor: &mut V, elements: &[Expression]) {
+    for element in elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_index<V: Visitor + ?Sized>(visitor: &mut V, e: &Expression, index: &Expression) {
+    visitor.visit_expression(e);
+    visitor.visit_expression(index);
+}
+
+pub fn walk_range<V: Visitor + ?Sized>(visitor: &mut V,
+                                       start: &Expression,
+                                       end: &Expression,
+                                       step: Option<&Expression>) {
+    visitor.visit_expression(start);
+    visitor.visit_expression(end);
+    if let Some(step) = step {
+        visitor.visit_expression(step);
+    }
+}
+
+#[cfg(test)]
+mod test;
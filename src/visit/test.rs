@@ -0,0 +1,74 @@
+use parser::{Expression, Operation};
+use super::{Visitor, walk_unary_minus};
+
+// (1 + -2) * 3
+fn sample() -> Expression {
+    let sum = Expression::BinaryOperation(
+        Box::new(Expression::Integer(1)),
+        Operation::Plus,
+        Box::new(Expression::UnaryMinus(Box::new(Expression::Integer(2)))));
+    Expression::BinaryOperation(Box::new(sum),
+                                Operation::Times,
+                                Box::new(Expression::Integer(3)))
+}
+
+struct IntegerCollector {
+    integers: Vec<u32>
+}
+
+impl Visitor for IntegerCollector {
+    fn visit_integer(&mut self, i: u32) {
+        self.integers.push(i);
+    }
+}
+
+#[test]
+fn default_walk_reaches_every_leaf_in_order() {
+    let mut collector = IntegerCollector { integers: vec![] };
+    collector.visit_expression(&sample());
+    assert_eq!(vec![1, 2, 3], collector.integers);
+}
+
+struct NegationDepth {
+    current: u32,
+    deepest: u32
+}
+
+impl Visitor for NegationDepth {
+    fn visit_unary_minus(&mut self, e: &Expression) {
+        self.current += 1;
+        self.deepest = self.deepest.max(self.current);
+        walk_unary_minus(self, e);
+        self.current -= 1;
+    }
+}
+
+struct VariableCollector {
+    names: Vec<String>
+}
+
+impl Visitor for VariableCollector {
+    fn visit_variable(&mut self, name: &str) {
+        self.names.push(name.to_string());
+    }
+}
+
+#[test]
+fn variables_are_visited() {
+    let e = Expression::BinaryOperation(Box::new(Expression::Variable("x".to_string())),
+                                        Operation::Minus,
+                                        Box::new(Expression::UnaryMinus(Box::new(
+                                            Expression::Variable("y".to_string())))));
+    let mut collector = VariableCollector { names: vec![] };
+    collector.visit_expression(&e);
+    assert_eq!(vec!["x".to_string(), "y".to_string()], collector.names);
+}
+
+#[test]
+fn overridden_method_can_continue_walking() {
+    let e = Expression::UnaryMinus(Box::new(
+        Expression::UnaryMinus(Box::new(sample()))));
+    let mut depth = NegationDepth { current: 0, deepest: 0 };
+    depth.visit_expression(&e);
+    assert_eq!(3, depth.deepest);
+}
@@ -0,0 +1,57 @@
+extern crate rand;
+
+use common::fuzzer::{replay_corpus, run_fuzzer, Fuzzer};
+use interpreter::{Bindings, Interpreter};
+use interpreter::fuzzer::rand_bindings;
+use parser::Expression;
+use parser::fuzzer::{rand_expression, shrink_paired, EXPRESSION_MAX_DEPTH};
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
+use super::Program;
+
+// checks that compiled code agrees with the tree-walking interpreter
+struct VmFuzzer<R: Rng> {
+    random: R
+}
+
+impl<ActualR: Rng + SeedableRng> Fuzzer for VmFuzzer<ActualR> {
+    type Item = (Expression, Bindings);
+    type R = ActualR;
+
+    fn new(random: ActualR) -> VmFuzzer<ActualR> {
+        VmFuzzer { random }
+    }
+
+    fn gen_one(&mut self) -> (Expression, Bindings) {
+        let e = rand_expression(self.random_generator(), EXPRESSION_MAX_DEPTH);
+        (e, rand_bindings(self.random_generator()))
+    }
+
+    fn run_test(&mut self, (item, bindings): (Expression, Bindings)) {
+        assert_eq!(item.evaluate_in(&bindings),
+                   Program::compile(&item).evaluate_in(&bindings),
+                   "{} with {:?}", item, bindings);
+    }
+
+    fn corpus_name(&self) -> Option<&'static str> {
+        Some("vm")
+    }
+
+    fn shrink(&self, item: &(Expression, Bindings)) -> Vec<(Expression, Bindings)> {
+        shrink_paired(item)
+    }
+
+    fn random_generator(&mut self) -> &mut ActualR {
+        &mut self.random
+    }
+}
+
+#[test]
+fn run_vm_fuzzer() {
+    run_fuzzer::<VmFuzzer<StdRng>>();
+}
+
+#[test]
+fn replay_vm_corpus() {
+    replay_corpus::<VmFuzzer<StdRng>>();
+}
@@ -0,0 +1,339 @@
+use super::interpreter::{call, constant, convert, date, decimal, factorial, imaginary, index, literal, negate,
+                        percent, percent_change, range, square_root, time, unbound, unit, Bindings, Error,
+                        InternalInterpreterResult, OperationEvaluator};
+use super::calculus::{derivative_call, solve, solves_equation};
+use super::parser::{Expression, Operation};
+use super::value::Value;
+use super::visit::Visitor;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Push(Value),
+    // loads the variable at this index in the program's variable table
+    Load(usize),
+    Negate,
+    SquareRoot,
+    Factorial,
+    Percent,
+    Operate(Operation),
+    // applies a percent change with this operation
+    PercentChange(Operation),
+    // calls the named function on this many arguments, the last on top
+    Call(String, usize),
+    // converts the value below the top to the unit on top
+    Convert,
+    // collects this many values, the last on top, into a list
+    List(usize),
+    // takes the element of the list below the top at the position on top
+    Index,
+    // makes the range from the value below the top to the value on top,
+    // or, if this is true, from the two below the top by the step on top
+    Range(bool),
+    // runs the program, a derivative, with the value on top for its
+    // variable and each of the others loaded from the slot given for it
+    At(Box<Program>, Vec<Option<usize>>),
+    // solves the equation in these arguments to solve, as the interpreter
+    // does, with every variable that has a value bound
+    Solve(Vec<Expression>),
+    // raises an error found at compile time, once execution reaches it
+    Fail(Error)
+}
+
+// An expression compiled to postfix stack code.  Operands are evaluated
+// in the same order as the tree-walking interpreter, so both report the
+// same error for the same input.  Variables are resolved to slots at
+// compile time, so repeated runs never look names up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    code: Vec<Instruction>,
+    variables: Vec<String>,
+    max_stack: usize
+}
+
+struct Compiler {
+    code: Vec<Instruction>,
+    variables: Vec<String>,
+    depth: usize,
+    max_stack: usize
+}
+
+impl Compiler {
+    fn emit(&mut self, instruction: Instruction, stack_effect: isize) {
+        self.code.push(instruction);
+        self.depth = (self.depth as isize + stack_effect) as usize;
+        self.max_stack = self.max_stack.max(self.depth);
+    }
+
+    fn slot(&mut self, name: &str) -> usize {
+        match self.variables.iter().position(|v| v == name) {
+            Some(index) => index,
+            None => {
+                self.variables.push(name.to_string());
+                self.variables.len() - 1
+            }
+        }
+    }
+
+    // a literal's value, or the error it raises when reached
+    fn emit_literal(&mut self, value: InternalInterpreterResult) {
+        match value {
+            Ok(v) => self.emit(Instruction::Push(v), 1),
+            Err(message) => self.emit(Instruction::Fail(message), 1)
+        }
+    }
+
+    fn compile(&mut self, e: &Expression) {
+        match e {
+            Expression::Integer(i) => self.emit_literal(literal(*i)),
+            Expression::Decimal(digits) => self.emit_literal(decimal(digits)),
+            Expression::Date(text) => self.emit_literal(date(text)),
+            Expression::Time(text) => self.emit_literal(time(text)),
+            Expression::Imaginary(i) => self.emit(Instruction::Push(imaginary(*i)), 1),
+            Expression::Variable(name) => {
+                let slot = self.slot(name);
+                self.emit(Instruction::Load(slot), 1)
+            },
+            Expression::UnaryMinus(e) => {
+                self.compile(e);
+                self.emit(Instruction::Negate, 0)
+            },
+            Expression::SquareRoot(e) => {
+                self.compile(e);
+                self.emit(Instruction::SquareRoot, 0)
+            },
+            Expression::Factorial(e) => {
+                self.compile(e);
+                self.emit(Instruction::Factorial, 0)
+            },
+            Expression::Percent(e) => {
+                self.compile(e);
+                self.emit(Instruction::Percent, 0)
+            },
+            Expression::PercentChange(e1, op, e2) => {
+                self.compile(e1);
+                self.compile(e2);
+                self.emit(Instruction::PercentChange(*op), -1)
+            },
+            Expression::BinaryOperation(e1, op, e2) => {
+                self.compile(e1);
+                self.compile(e2);
+                self.emit(Instruction::Operate(*op), -1)
+            },
+            // a derivative is worked out once, here, and its code runs
+            // instead, or at a point, as a program of its own
+            Expression::Call(name, arguments) if name == "diff" => match (derivative_call(arguments), &arguments[1]) {
+                (Ok(derivative), Expression::Variable(x)) if arguments.len() == 3 => {
+                    self.compile(&arguments[2]);
+                    let program = Program::compile(&derivative);
+                    let slots = program.variables.iter()
+                        .map(|name| if name == x { None } else { Some(self.slot(name)) })
+                        .collect();
+                    self.emit(Instruction::At(Box::new(program), slots), 0)
+                },
+                (Ok(derivative), _) => self.compile(&derivative),
+                (Err(message), _) => self.emit(Instruction::Fail(Error::unsupported(message)), 1)
+            },
+            // an equation is evaluated afresh at every value tried for its
+            // variable, so it is left to the interpreter, given slots for
+            // the variables it mentions
+            Expression::Call(name, arguments) if solves_equation(name, arguments) => {
+                for argument in arguments {
+                    self.visit_expression(argument);
+                }
+                self.emit(Instruction::Solve(arguments.clone()), 1)
+            },
+            Expression::Call(name, arguments) => {
+                for argument in arguments {
+                    self.compile(argument);
+                }
+                self.emit(Instruction::Call(name.clone(), arguments.len()), 1 - arguments.len() as isize)
+            },
+            Expression::Convert(e, unit) => {
+                self.compile(e);
+                self.compile(unit);
+                self.emit(Instruction::Convert, -1)
+            },
+            Expression::List(elements) => {
+                for element in elements {
+                    self.compile(element);
+                }
+                self.emit(Instruction::List(elements.len()), 1 - elements.len() as isize)
+            },
+            Expression::Index(e, position) => {
+                self.compile(e);
+                self.compile(position);
+                self.emit(Instruction::Index, -1)
+            },
+            Expression::Range(start, end, step) => {
+                self.compile(start);
+                self.compile(end);
+                match step {
+                    Some(step) => {
+                        self.compile(step);
+                        self.emit(Instruction::Range(true), -2)
+                    },
+                    None => self.emit(Instruction::Range(false), -1)
+                }
+            }
+        }
+    }
+}
+
+// gives a slot to every variable it visits
+impl Visitor for Compiler {
+    fn visit_variable(&mut self, name: &str) {
+        self.slot(name);
+    }
+}
+
+impl Program {
+    pub fn compile(e: &Expression) -> Program {
+        let mut compiler = Compiler {
+            code: vec![],
+            variables: vec![],
+            depth: 0,
+            max_stack: 0
+        };
+        compiler.compile(e);
+        Program {
+            code: compiler.code,
+            variables: compiler.variables,
+            max_stack: compiler.max_stack
+        }
+    }
+
+    pub fn code(&self) -> &[Instruction] {
+        &self.code
+    }
+
+    // the free variables, in slot order
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn evaluate_in(&self, bindings: &Bindings) -> InternalInterpreterResult {
+        // bindings shadow constants and units, as in the interpreter
+        let values: Vec<Option<Value>> = self.variables.iter()
+            .map(|name| bindings.get(name).cloned().or_else(|| constant(name)).or_else(|| unit(name)))
+            .collect();
+        Machine::new().run(self, &values)
+    }
+}
+
+// Runs programs, keeping its stack between runs to avoid reallocating.
+pub struct Machine {
+    stack: Vec<Value>
+}
+
+impl Default for Machine {
+    fn default() -> Machine {
+        Machine::new()
+    }
+}
+
+impl Machine {
+    pub fn new() -> Machine {
+        Machine { stack: vec![] }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("compiled code underflowed the stack")
+    }
+
+    // values holds one entry per program variable, in slot order, with
+    // None for a variable that is unbound
+    pub fn run(&mut self, program: &Program, values: &[Option<Value>]) -> InternalInterpreterResult {
+        if values.len() != program.variables.len() {
+            return Result::Err(Error::unsupported(format!("Expected {} variable values, got {}",
+                                                          program.variables.len(),
+                                                          values.len())));
+        }
+        self.stack.clear();
+        self.stack.reserve(program.max_stack);
+
+        for instruction in &program.code {
+            match *instruction {
+                Instruction::Push(ref v) => self.stack.push(v.clone()),
+                Instruction::Load(slot) => match values[slot] {
+                    Some(ref v) => self.stack.push(v.clone()),
+                    None => return Result::Err(unbound(&program.variables[slot]))
+                },
+                Instruction::Negate => {
+                    let v = self.pop();
+                    self.stack.push(negate(v)?);
+                },
+                Instruction::SquareRoot => {
+                    let v = self.pop();
+                    self.stack.push(square_root(v)?);
+                },
+                Instruction::Factorial => {
+                    let v = self.pop();
+                    self.stack.push(factorial(v)?);
+                },
+                Instruction::Percent => {
+                    let v = self.pop();
+                    self.stack.push(percent(v)?);
+                },
+                Instruction::Operate(op) => {
+                    let v2 = self.pop();
+                    let v1 = self.pop();
+                    self.stack.push(op.operation(v1, v2)?);
+                },
+                Instruction::PercentChange(op) => {
+                    let v2 = self.pop();
+                    let v1 = self.pop();
+                    self.stack.push(percent_change(&op, v1, v2)?);
+                },
+                Instruction::Call(ref name, count) => {
+                    let arguments = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(call(name, arguments)?);
+                },
+                Instruction::Convert => {
+                    let target = self.pop();
+                    let v = self.pop();
+                    self.stack.push(convert(v, target)?);
+                },
+                Instruction::List(count) => {
+                    let values = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Value::List(values));
+                },
+                Instruction::Index => {
+                    let position = self.pop();
+                    let v = self.pop();
+                    self.stack.push(index(v, position)?);
+                },
+                Instruction::Range(has_step) => {
+                    let step = if has_step { Some(self.pop()) } else { None };
+                    let end = self.pop();
+                    let start = self.pop();
+                    self.stack.push(range(start, end, step)?);
+                },
+                Instruction::At(ref derivative, ref slots) => {
+                    let point = self.pop();
+                    let values: Vec<Option<Value>> = slots.iter()
+                        .map(|slot| match *slot {
+                            Some(slot) => values[slot].clone(),
+                            None => Some(point.clone())
+                        })
+                        .collect();
+                    self.stack.push(Machine::new().run(derivative, &values)?);
+                },
+                Instruction::Solve(ref arguments) => {
+                    let bindings: Bindings = program.variables.iter().zip(values)
+                        .filter_map(|(name, v)| v.clone().map(|v| (name.clone(), v)))
+                        .collect();
+                    self.stack.push(solve(arguments, &bindings)?);
+                },
+                Instruction::Fail(ref error) =>
+                    return Result::Err(error.clone())
+            }
+        }
+
+        Result::Ok(self.pop())
+    }
+}
+
+#[cfg(test)]
+mod test;
+#[cfg(test)]
+mod fuzzer;
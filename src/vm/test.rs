@@ -0,0 +1,194 @@
+use interpreter::{Bindings, Error, ErrorKind};
+use parser::{Expression, Operation};
+use value::{Complex, Value};
+use super::{Instruction, Machine, Program};
+
+fn var(name: &str) -> Box<Expression> {
+    Box::new(Expression::Variable(name.to_string()))
+}
+
+fn int(i: u32) -> Box<Expression> {
+    Box::new(Expression::Integer(i))
+}
+
+// (x + 2) * -x / y
+fn sample() -> Expression {
+    let sum = Box::new(Expression::BinaryOperation(var("x"), Operation::Plus, int(2)));
+    let product = Box::new(Expression::BinaryOperation(sum,
+                                                       Operation::Times,
+                                                       Box::new(Expression::UnaryMinus(var("x")))));
+    Expression::BinaryOperation(product, Operation::Div, var("y"))
+}
+
+#[test]
+fn compiles_to_postfix_code() {
+    let program = Program::compile(&sample());
+    assert_eq!(&[Instruction::Load(0),
+                 Instruction::Push(Value::Integer(2)),
+                 Instruction::Operate(Operation::Plus),
+                 Instruction::Load(0),
+                 Instruction::Negate,
+                 Instruction::Operate(Operation::Times),
+                 Instruction::Load(1),
+                 Instruction::Operate(Operation::Div)],
+               program.code());
+    assert_eq!(&["x".to_string(), "y".to_string()], program.variables());
+}
+
+#[test]
+fn runs_with_slot_values() {
+    let program = Program::compile(&sample());
+    let mut machine = Machine::new();
+    assert_eq!(Result::Ok(Value::Integer(-5)), machine.run(&program, &[Some(Value::Integer(3)), Some(Value::Integer(3))]));
+    assert_eq!(Result::Ok(Value::Integer(-12)), machine.run(&program, &[Some(Value::Integer(4)), Some(Value::Integer(2))]));
+}
+
+#[test]
+fn runs_with_bindings() {
+    let mut bindings = Bindings::new();
+    bindings.insert("x".to_string(), Value::Integer(3));
+    bindings.insert("y".to_string(), Value::Integer(3));
+    assert_eq!(Result::Ok(Value::Integer(-5)), Program::compile(&sample()).evaluate_in(&bindings));
+}
+
+#[test]
+fn division_by_zero_fails_safely() {
+    let program = Program::compile(&sample());
+    assert!(Machine::new().run(&program, &[Some(Value::Integer(1)), Some(Value::Integer(0))]).is_err());
+}
+
+#[test]
+fn unbound_variable_fails_safely() {
+    let program = Program::compile(&sample());
+    assert_eq!(Result::Err(Error::new(ErrorKind::UnboundVariable, "Unbound variable: y")),
+               Machine::new().run(&program, &[Some(Value::Integer(1)), None]));
+}
+
+#[test]
+fn wrong_number_of_values_fails_safely() {
+    let program = Program::compile(&sample());
+    assert!(Machine::new().run(&program, &[Some(Value::Integer(1))]).is_err());
+}
+
+#[test]
+fn out_of_range_integer_fails_when_reached() {
+    // 1 / 0 + 2147483648 reports the division first, like the interpreter
+    let e = Expression::BinaryOperation(
+        Box::new(Expression::BinaryOperation(int(1), Operation::Div, int(0))),
+        Operation::Plus,
+        int(2147483648));
+    assert_eq!(Result::Err(Error::division_by_zero()),
+               Program::compile(&e).evaluate_in(&Bindings::new()));
+}
+
+#[test]
+fn square_root_compiles() {
+    let e = Expression::SquareRoot(var("x"));
+    let program = Program::compile(&e);
+    assert_eq!(&[Instruction::Load(0), Instruction::SquareRoot], program.code());
+    assert_eq!(Result::Ok(Value::Real(2.0)),
+               Machine::new().run(&program, &[Some(Value::Integer(4))]));
+}
+
+#[test]
+fn constants_are_loaded_unless_bound() {
+    let program = Program::compile(&Expression::Variable("pi".to_string()));
+    assert_eq!(Result::Ok(Value::Real(::std::f64::consts::PI)),
+               program.evaluate_in(&Bindings::new()));
+    let mut bindings = Bindings::new();
+    bindings.insert("pi".to_string(), Value::Integer(3));
+    assert_eq!(Result::Ok(Value::Integer(3)), program.evaluate_in(&bindings));
+}
+
+#[test]
+fn postfix_operators_compile() {
+    let e = Expression::PercentChange(Box::new(Expression::Factorial(var("x"))),
+                                      Operation::Minus,
+                                      Box::new(Expression::Percent(int(50))));
+    let program = Program::compile(&e);
+    assert_eq!(&[Instruction::Load(0), Instruction::Factorial,
+                 Instruction::Push(Value::Integer(50)), Instruction::Percent,
+                 Instruction::PercentChange(Operation::Minus)],
+               program.code());
+    assert_eq!(Result::Ok(Value::Real(23.88)),
+               Machine::new().run(&program, &[Some(Value::Integer(4))]));
+}
+
+#[test]
+fn calls_take_their_arguments_from_the_stack() {
+    let e = Expression::Call("abs".to_string(),
+                             vec![Expression::BinaryOperation(var("x"), Operation::Plus, Box::new(Expression::Imaginary(4)))]);
+    let program = Program::compile(&e);
+    assert_eq!(&[Instruction::Load(0),
+                 Instruction::Push(Value::Complex(Complex::new(0.0, 4.0))),
+                 Instruction::Operate(Operation::Plus),
+                 Instruction::Call("abs".to_string(), 1)],
+               program.code());
+    assert_eq!(Result::Ok(Value::Real(5.0)),
+               Machine::new().run(&program, &[Some(Value::Integer(3))]));
+}
+
+#[test]
+fn conversions_take_the_unit_from_the_top() {
+    let e = Expression::Convert(Box::new(Expression::BinaryOperation(Box::new(Expression::Decimal("1.5".to_string())),
+                                                                     Operation::Times,
+                                                                     var("h"))),
+                                var("min"));
+    let program = Program::compile(&e);
+    assert_eq!(&[Instruction::Push(Value::Real(1.5)), Instruction::Load(0),
+                 Instruction::Operate(Operation::Times), Instruction::Load(1), Instruction::Convert],
+               program.code());
+    assert_eq!(Result::Ok("PT90M".to_string()),
+               program.evaluate_in(&Bindings::new()).map(|v| v.to_string()));
+}
+
+#[test]
+fn lists_and_ranges_collect_from_the_stack() {
+    let list = Expression::List(vec![Expression::Integer(1), Expression::Variable("x".to_string())]);
+    let e = Expression::Index(Box::new(list), Box::new(Expression::Range(int(0), int(1), None)));
+    let program = Program::compile(&e);
+    assert_eq!(&[Instruction::Push(Value::Integer(1)), Instruction::Load(0), Instruction::List(2),
+                 Instruction::Push(Value::Integer(0)), Instruction::Push(Value::Integer(1)), Instruction::Range(false),
+                 Instruction::Index],
+               program.code());
+    assert_eq!(Result::Err(Error::units("Cannot index with a list")),
+               Machine::new().run(&program, &[Some(Value::Integer(7))]));
+}
+
+#[test]
+fn derivatives_compile_to_their_own_code() {
+    let square = Expression::BinaryOperation(var("x"), Operation::Power, int(2));
+    let e = Expression::Call("diff".to_string(), vec![square, Expression::Variable("x".to_string())]);
+    let program = Program::compile(&e);
+    assert_eq!(&[Instruction::Push(Value::Integer(2)), Instruction::Load(0), Instruction::Operate(Operation::Times)],
+               program.code());
+    assert_eq!(Result::Ok(Value::Integer(6)), Machine::new().run(&program, &[Some(Value::Integer(3))]));
+    let by_number = Expression::Call("diff".to_string(), vec![*var("x"), *int(2)]);
+    assert_eq!(&[Instruction::Fail(Error::unsupported("Cannot differentiate with respect to anything but a variable"))],
+               Program::compile(&by_number).code());
+}
+
+#[test]
+fn derivatives_at_a_point_run_as_programs_of_their_own() {
+    let product = Expression::BinaryOperation(var("x"), Operation::Times, var("y"));
+    let point = Expression::BinaryOperation(var("y"), Operation::Plus, int(1));
+    let e = Expression::Call("diff".to_string(), vec![product, Expression::Variable("x".to_string()), point]);
+    let program = Program::compile(&e);
+    let derivative = Program::compile(&Expression::Variable("y".to_string()));
+    assert_eq!(&[Instruction::Load(0), Instruction::Push(Value::Integer(1)), Instruction::Operate(Operation::Plus),
+                 Instruction::At(Box::new(derivative), vec![Some(0)])],
+               program.code());
+    assert_eq!(Result::Ok(Value::Integer(7)), Machine::new().run(&program, &[Some(Value::Integer(7))]));
+}
+
+#[test]
+fn equations_are_solved_with_their_variables_loaded() {
+    let lhs = Expression::BinaryOperation(var("x"), Operation::Power, int(2));
+    let equation = Expression::BinaryOperation(Box::new(lhs), Operation::Equal, var("a"));
+    let arguments = vec![equation, Expression::Variable("x".to_string())];
+    let program = Program::compile(&Expression::Call("solve".to_string(), arguments.clone()));
+    assert_eq!(&[Instruction::Solve(arguments)], program.code());
+    assert_eq!(&["x".to_string(), "a".to_string()], program.variables());
+    assert_eq!(Result::Ok(Value::List(vec![Value::Real(-3.0), Value::Real(3.0)])),
+               Machine::new().run(&program, &[None, Some(Value::Integer(9))]));
+}
@@ -0,0 +1,55 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// what the calculator prints for one expression on its command line
+fn run(expression: &str) -> String {
+    run_with(&[expression])
+}
+
+// what it prints for options followed by an expression
+fn run_with(arguments: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_calculator"))
+        .args(arguments)
+        .output()
+        .expect("the calculator runs");
+    String::from_utf8(output.stdout).expect("the output is UTF-8")
+}
+
+#[test]
+fn derivatives_print_before_their_value_at_a_point() {
+    assert_eq!("Derivative: 2 * x\nInterpreter result: 6\n", run("diff(x^2, x, 3)"));
+    assert_eq!("Derivative: 3 * x ^ 2 - 2\nInterpreter result: 10\n", run("diff(x^3 - 2x, x, 2)"));
+}
+
+#[test]
+fn pi_stands_alone_next_to_a_name() {
+    assert_eq!("Interpreter result: Error: Unbound variable: r\n", run("rπ"));
+    assert_eq!("Interpreter result: Error: Unbound variable: r\n", run("2πr"));
+    assert_eq!("Interpreter result: 3.141592653589793 m\n", run("mπ"));
+    assert_eq!("Interpreter result: 6.283185307179586 m\n", run("2πm"));
+}
+
+#[test]
+fn scripts_format_from_standard_input() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_calculator"))
+        .arg("fmt")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("the calculator runs");
+    child.stdin.take().unwrap().write_all(b"(1+2)*x # scaled\n\n\n2^3\n").unwrap();
+    let output = child.wait_with_output().expect("the calculator finishes");
+    assert_eq!("(1 + 2) * x # scaled\n\n2 ^ 3\n", String::from_utf8(output.stdout).unwrap());
+}
+
+#[test]
+fn rounding_does_not_show_in_complex_results() {
+    assert_eq!("Interpreter result: -1\n", run("exp(i*pi)"));
+}
+
+#[test]
+fn fractions_print_exactly_in_rational_mode() {
+    assert_eq!("Interpreter result: [[-2, 1], [1.5, -0.5]]\n", run("inverse([[1, 2], [3, 4]])"));
+    assert_eq!("Interpreter result: [[-2, 1], [3/2, -1/2]]\n",
+               run_with(&["--rational", "inverse([[1, 2], [3, 4]])"]));
+}